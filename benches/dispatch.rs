@@ -0,0 +1,53 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use ignis::{
+    classfile::{ClassfileBuilder, MethodFlags, OwnedCode},
+    vm::{self, Args, Value},
+};
+use std::{hint::black_box, path::Path};
+
+/// Virtual calls made by each iteration, on a receiver whose class either directly extends the
+/// one declaring the method, or sits six classes below it.
+const CALLS: i32 = 1_000;
+
+/// Serves the `Dispatch` classes along with a bare `java/lang/Object`, so no JDK is needed.
+fn provider(classname: &str) -> Option<Vec<u8>> {
+    match classname {
+        "java/lang/Object" => {
+            let code = OwnedCode {
+                max_stack: 0,
+                max_locals: 1,
+                bytecode: vec![0xB1], // RETURN
+            };
+            let object = ClassfileBuilder::new(classname).method(
+                MethodFlags::PUBLIC,
+                "<init>",
+                "()V",
+                Some(code),
+            );
+            Some(object.to_bytes())
+        }
+        _ => std::fs::read(format!("./tests/sources/{classname}.class")).ok(),
+    }
+}
+
+fn dispatch(c: &mut Criterion) {
+    let args = Args {
+        class_provider: Some(Box::new(provider)),
+        ..Default::default()
+    };
+    vm::initialise(args, Path::new("/does/not/exist")).unwrap();
+
+    // both should take about the same, as the depth only matters when the table is first built
+    for method in ["shallow", "deep"] {
+        c.bench_function(&format!("dispatch {method}"), |b| {
+            b.iter(|| {
+                let args = [Value::Int(black_box(CALLS))];
+                let sides = vm::invoke("Dispatch", method, "(I)I", &args).unwrap();
+                assert_eq!(sides, Some(Value::Int(4 * CALLS)));
+            })
+        });
+    }
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,24 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use ignis::classfile::Classfile;
+use std::hint::black_box;
+
+/// Classes parsed by the benchmark: a plain one, an enum and a large one making use of
+/// annotations and `invokedynamic`.
+const CLASSES: [&str; 3] = ["Person", "TaskStatus", "Catalogue"];
+
+fn parse(c: &mut Criterion) {
+    for class in CLASSES {
+        let bytes = std::fs::read(format!("./tests/sources/{class}.class")).unwrap();
+
+        c.bench_function(&format!("parse {class}"), |b| {
+            b.iter(|| {
+                let arena = bumpalo::Bump::new();
+                let classfile = Classfile::new(black_box(&bytes), &arena).unwrap();
+                black_box(classfile.methods.len());
+            })
+        });
+    }
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
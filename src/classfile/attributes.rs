@@ -1,21 +1,21 @@
 //! The `attributes` in a `field_info` structure provide additional metadata about a given field.
 //! Those attributes are stored into an array of `attributes`, duh.
 
-use super::{ClassfileError, constant_pool::ConstantPool};
+use super::{ClassfileError, ParseOptions, constant_pool::ConstantPool};
 use crate::classfile::{
-    constant_pool::{ConstantPoolEntry, ConstantPoolError},
+    constant_pool::{ConstantPoolEntry, ConstantPoolError, PoolIndex},
     read,
 };
 use bitflags::bitflags;
 use bumpalo::collections::Vec;
-use std::io::{BufReader, Read};
+use std::io::{BufRead, BufReader, Read};
 use thiserror::Error;
 
 /// Attributes as defined by JSVM (4.7)
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub(in crate::classfile) enum Attribute<'at> {
     ConstantValue {
-        constantvalue_index: u16,
+        constantvalue_index: PoolIndex,
     },
     Code {
         max_stack: u16,
@@ -28,21 +28,21 @@ pub(in crate::classfile) enum Attribute<'at> {
         entries: &'at [StackMapEntry<'at>],
     },
     Exceptions {
-        exception_index_table: &'at [u16],
+        exception_index_table: &'at [PoolIndex],
     },
     InnerClasses {
         classes: &'at [InnerClassEntry],
     },
     EnclosingMethod {
-        class_index: u16,
-        method_index: u16,
+        class_index: PoolIndex,
+        method_index: PoolIndex,
     },
     Synthetic,
     Signature {
-        signature_index: u16,
+        signature_index: PoolIndex,
     },
     SourceFile {
-        sourcefile_index: u16,
+        sourcefile_index: PoolIndex,
     },
     SourceDebugExtension,
     LineNumberTable {
@@ -71,7 +71,9 @@ pub(in crate::classfile) enum Attribute<'at> {
         element_value: ElementValue<'at>,
         bytes: &'at [u8],
     },
-    BootstrapMethods,
+    BootstrapMethods {
+        bootstrap_methods: &'at [BootstrapMethodEntry<'at>],
+    },
     MethodParameters {
         parameters: &'at [MethodParameterEntry],
     },
@@ -79,32 +81,100 @@ pub(in crate::classfile) enum Attribute<'at> {
     ModulePackages,
     ModuleMainClass,
     NestHost {
-        host_class_index: u16,
+        host_class_index: PoolIndex,
     },
     NestMembers {
-        classes: &'at [u16],
+        classes: &'at [PoolIndex],
     },
     Record {
         components: &'at [RecordComponentInfo<'at>],
     },
-    PermittedSubclasses,
+    PermittedSubclasses {
+        classes: &'at [PoolIndex],
+    },
+    /// Attribute whose contents were skipped over, either as requested by
+    /// [`ParseOptions::skip_attribute_bodies`](super::ParseOptions::skip_attribute_bodies) or
+    /// because it isn't parsed at all.
+    Skipped {
+        name: &'at str,
+    },
 }
 
+macro_rules! attribute_kinds {
+    ($($kind:ident),* $(,)?) => {
+        /// Kinds of the attributes defined by JVMS (4.7), named as in the class file.
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        pub enum AttributeKind {
+            $($kind),*
+        }
+
+        impl AttributeKind {
+            /// Name of the attribute as found in the class file, e.g. `SourceFile`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$kind => stringify!($kind)),*
+                }
+            }
+
+            /// Kind of the attribute called `name`, if it's one defined by JVMS.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($kind) => Some(Self::$kind),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+attribute_kinds!(
+    ConstantValue,
+    Code,
+    StackMapTable,
+    Exceptions,
+    InnerClasses,
+    EnclosingMethod,
+    Synthetic,
+    Signature,
+    SourceFile,
+    SourceDebugExtension,
+    LineNumberTable,
+    LocalVariableTable,
+    LocalVariableTypeTable,
+    Deprecated,
+    RuntimeVisibleAnnotations,
+    RuntimeInvisibleAnnotations,
+    RuntimeVisibleParameterAnnotations,
+    RuntimeInvisibleParameterAnnotations,
+    RuntimeVisibleTypeAnnotations,
+    RuntimeInvisibleTypeAnnotations,
+    AnnotationDefault,
+    BootstrapMethods,
+    MethodParameters,
+    Module,
+    ModulePackages,
+    ModuleMainClass,
+    NestHost,
+    NestMembers,
+    Record,
+    PermittedSubclasses,
+);
+
 /// `element_value` structure as defined by JSVM (4.7.16.1)
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) enum ElementValue<'at> {
     ConstValueIndex {
         tag: u8,
-        const_value_index: u16,
+        const_value_index: PoolIndex,
     },
     EnumConstValue {
         tag: u8,
-        type_name_index: u16,
-        const_name_index: u16,
+        type_name_index: PoolIndex,
+        const_name_index: PoolIndex,
     },
     ClassInfoIndex {
         tag: u8,
-        class_info_index: u16,
+        class_info_index: PoolIndex,
     },
     Annotation {
         tag: u8,
@@ -118,10 +188,10 @@ pub(in crate::classfile) enum ElementValue<'at> {
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct ExceptionEntry {
-    start_pc: u16,
-    end_pc: u16,
-    handler_pc: u16,
-    catch_type: u16,
+    pub(in crate::classfile) start_pc: u16,
+    pub(in crate::classfile) end_pc: u16,
+    pub(in crate::classfile) handler_pc: u16,
+    pub(in crate::classfile) catch_type: PoolIndex,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -156,12 +226,24 @@ pub(in crate::classfile) enum StackMapEntry<'st> {
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct InnerClassEntry {
-    inner_class_info_index: u16,
-    outer_class_info_index: u16,
-    inner_name_index: u16,
+    inner_class_info_index: PoolIndex,
+    outer_class_info_index: PoolIndex,
+    inner_name_index: PoolIndex,
     inner_class_access_flags: InnerClassFlags,
 }
 
+/// A nested class relationship recorded in the `InnerClasses` attribute, with its indexes resolved.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InnerClassInfo<'c> {
+    /// Binary name of the inner class, e.g. `Outer$Nested`.
+    pub class_name: &'c str,
+    /// The class declaring it as a member; `None` for local and anonymous classes.
+    pub outer_class: Option<&'c str>,
+    /// Simple name as written in the source; `None` for anonymous classes.
+    pub name: Option<&'c str>,
+    pub flags: InnerClassFlags,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct LineNumberEntry {
     start_pc: u16,
@@ -170,47 +252,63 @@ pub(in crate::classfile) struct LineNumberEntry {
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct LocalVariableEntry {
-    start_pc: u16,
+    pub(in crate::classfile) start_pc: u16,
     length: u16,
-    name_index: u16,
-    descriptor_index: u16,
-    index: u16,
+    pub(in crate::classfile) name_index: PoolIndex,
+    pub(in crate::classfile) descriptor_index: PoolIndex,
+    pub(in crate::classfile) index: u16,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct LocalVariableTypeEntry {
     start_pc: u16,
     length: u16,
-    name_index: u16,
-    signature_index: u16,
+    name_index: PoolIndex,
+    signature_index: PoolIndex,
     index: u16,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct Annotation<'el> {
-    type_index: u16,
+    type_index: PoolIndex,
     element_value_pairs: &'el [ElementValuePair<'el>],
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct MethodParameterEntry {
-    name_index: u16,
+    name_index: PoolIndex,
     access_flags: MethodParameterFlags,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct ElementValuePair<'el> {
-    element_name_index: u16,
+    element_name_index: PoolIndex,
     element_value: ElementValue<'el>,
 }
 
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub(in crate::classfile) struct BootstrapMethodEntry<'at> {
+    bootstrap_method_ref: PoolIndex,
+    bootstrap_arguments: &'at [PoolIndex],
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub(in crate::classfile) struct RecordComponentInfo<'at> {
-    name_index: u16,
-    descriptor_index: u16,
+    name_index: PoolIndex,
+    descriptor_index: PoolIndex,
     attributes: &'at [Attribute<'at>],
 }
 
+/// A component of a record class, recorded in the `Record` attribute, with its indexes resolved.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RecordComponent<'c> {
+    pub name: &'c str,
+    pub descriptor: &'c str,
+    /// Generic signature of the component; `None` unless its type uses type variables or
+    /// parameterized types.
+    pub signature: Option<&'c str>,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 #[repr(u8)]
 pub(in crate::classfile) enum FrameType {
@@ -233,14 +331,14 @@ pub(in crate::classfile) enum VerificationTypeInfo {
     DoubleVariable,
     NullVariable,
     UninitializedThisVariable,
-    ObjectVariable { cpool_index: u16 },
+    ObjectVariable { cpool_index: PoolIndex },
     UninitializedVariable { offset: u16 },
 }
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
     // TODO: add documentation for this ones
-    pub(crate) struct InnerClassFlags: u16 {
+    pub struct InnerClassFlags: u16 {
         const PUBLIC     = 0x0001;
         const PRIVATE    = 0x0002;
         const PROTECTED  = 0x0004;
@@ -277,9 +375,57 @@ impl<'at> AsRef<Attribute<'at>> for Attribute<'at> {
 }
 
 impl<'at> Attribute<'at> {
+    /// Kind of this attribute. Skipped attributes are only known by name, so the ones JVMS
+    /// doesn't define have none.
+    pub(in crate::classfile) fn kind(&self) -> Option<AttributeKind> {
+        let kind = match self {
+            Self::ConstantValue { .. } => AttributeKind::ConstantValue,
+            Self::Code { .. } => AttributeKind::Code,
+            Self::StackMapTable { .. } => AttributeKind::StackMapTable,
+            Self::Exceptions { .. } => AttributeKind::Exceptions,
+            Self::InnerClasses { .. } => AttributeKind::InnerClasses,
+            Self::EnclosingMethod { .. } => AttributeKind::EnclosingMethod,
+            Self::Synthetic { .. } => AttributeKind::Synthetic,
+            Self::Signature { .. } => AttributeKind::Signature,
+            Self::SourceFile { .. } => AttributeKind::SourceFile,
+            Self::SourceDebugExtension { .. } => AttributeKind::SourceDebugExtension,
+            Self::LineNumberTable { .. } => AttributeKind::LineNumberTable,
+            Self::LocalVariableTable { .. } => AttributeKind::LocalVariableTable,
+            Self::LocalVariableTypeTable { .. } => AttributeKind::LocalVariableTypeTable,
+            Self::Deprecated { .. } => AttributeKind::Deprecated,
+            Self::RuntimeVisibleAnnotations { .. } => AttributeKind::RuntimeVisibleAnnotations,
+            Self::RuntimeInvisibleAnnotations { .. } => AttributeKind::RuntimeInvisibleAnnotations,
+            Self::RuntimeVisibleParameterAnnotations { .. } => {
+                AttributeKind::RuntimeVisibleParameterAnnotations
+            }
+            Self::RuntimeInvisibleParameterAnnotations { .. } => {
+                AttributeKind::RuntimeInvisibleParameterAnnotations
+            }
+            Self::RuntimeVisibleTypeAnnotations { .. } => {
+                AttributeKind::RuntimeVisibleTypeAnnotations
+            }
+            Self::RuntimeInvisibleTypeAnnotations { .. } => {
+                AttributeKind::RuntimeInvisibleTypeAnnotations
+            }
+            Self::AnnotationDefault { .. } => AttributeKind::AnnotationDefault,
+            Self::BootstrapMethods { .. } => AttributeKind::BootstrapMethods,
+            Self::MethodParameters { .. } => AttributeKind::MethodParameters,
+            Self::Module { .. } => AttributeKind::Module,
+            Self::ModulePackages { .. } => AttributeKind::ModulePackages,
+            Self::ModuleMainClass { .. } => AttributeKind::ModuleMainClass,
+            Self::NestHost { .. } => AttributeKind::NestHost,
+            Self::NestMembers { .. } => AttributeKind::NestMembers,
+            Self::Record { .. } => AttributeKind::Record,
+            Self::PermittedSubclasses { .. } => AttributeKind::PermittedSubclasses,
+            Self::Skipped { name } => return AttributeKind::from_name(name),
+        };
+
+        Some(kind)
+    }
+
     fn new<'pool>(
         reader: &mut BufReader<impl Read>,
-        name_index: u16,
+        name_index: PoolIndex,
         length: u32,
         constant_pool: &'at ConstantPool<'at>,
         arena: &'at bumpalo::Bump,
@@ -301,9 +447,7 @@ impl<'at> Attribute<'at> {
                 let max_locals: u16 = read(reader)?;
                 let code_len: u32 = read(reader)?;
 
-                let mut code = bumpalo::vec![in arena; 0; code_len as usize];
-                reader.read_exact(&mut code)?;
-                let code = code.into_bump_slice();
+                let code = read_bytes(reader, code_len, attribute_name, arena)?;
 
                 let expection_table_len: u16 = read(reader)?;
                 let mut exception_table =
@@ -313,11 +457,12 @@ impl<'at> Attribute<'at> {
                         start_pc: read::<u16>(reader)?,
                         end_pc: read::<u16>(reader)?,
                         handler_pc: read::<u16>(reader)?,
-                        catch_type: read::<u16>(reader)?,
+                        catch_type: read(reader)?,
                     });
                 }
 
-                let attributes = get_attributes(reader, constant_pool, arena)?;
+                let attributes =
+                    get_attributes(reader, constant_pool, arena, ParseOptions::default())?;
                 Attribute::Code {
                     max_stack,
                     max_locals,
@@ -415,7 +560,7 @@ impl<'at> Attribute<'at> {
                     Vec::with_capacity_in(exceptions_count as usize, arena);
 
                 for _ in (0..exceptions_count) {
-                    exception_index_table.push(read::<u16>(reader)?)
+                    exception_index_table.push(read(reader)?)
                 }
 
                 Attribute::Exceptions {
@@ -446,8 +591,8 @@ impl<'at> Attribute<'at> {
             }
 
             "EnclosingMethod" => {
-                let class_index: u16 = read(reader)?;
-                let method_index: u16 = read(reader)?;
+                let class_index: PoolIndex = read(reader)?;
+                let method_index: PoolIndex = read(reader)?;
 
                 Attribute::EnclosingMethod {
                     class_index,
@@ -457,10 +602,10 @@ impl<'at> Attribute<'at> {
             "Synthetic" => Attribute::Synthetic,
             "Deprecated" => Attribute::Deprecated,
             "Signature" => Attribute::Signature {
-                signature_index: read::<u16>(reader)?,
+                signature_index: read(reader)?,
             },
             "SourceFile" => Attribute::SourceFile {
-                sourcefile_index: read::<u16>(reader)?,
+                sourcefile_index: read(reader)?,
             },
 
             "LineNumberTable" => {
@@ -524,10 +669,9 @@ impl<'at> Attribute<'at> {
             }
 
             "RuntimeVisibleAnnotations" => {
-                let mut bytes = bumpalo::vec![in arena; 0; length as usize];
-                reader.read_exact(&mut bytes)?;
-                let bytes = bytes.into_bump_slice();
-                let mut reader = BufReader::new(&bytes[..]);
+                let bytes = read_bytes(reader, length, attribute_name, arena)?;
+                // the body is already in memory, so it is read straight from the slice
+                let mut reader = bytes;
 
                 let annotation_count = read::<u16>(&mut reader)? as usize;
                 let mut annotations = Vec::with_capacity_in(annotation_count, arena);
@@ -556,10 +700,8 @@ impl<'at> Attribute<'at> {
             }
 
             "AnnotationDefault" => {
-                let mut bytes = bumpalo::vec![in arena; 0; length as usize];
-                reader.read_exact(&mut bytes)?;
-                let bytes = bytes.into_bump_slice();
-                let mut reader = BufReader::new(&bytes[..]);
+                let bytes = read_bytes(reader, length, attribute_name, arena)?;
+                let mut reader = bytes;
 
                 Attribute::AnnotationDefault {
                     element_value: get_element_value(&mut reader, constant_pool, arena)?,
@@ -567,6 +709,30 @@ impl<'at> Attribute<'at> {
                 }
             }
 
+            "BootstrapMethods" => {
+                let methods_count = read::<u16>(reader)? as usize;
+                let mut bootstrap_methods = Vec::with_capacity_in(methods_count, arena);
+
+                for _ in (0..methods_count) {
+                    let bootstrap_method_ref: PoolIndex = read(reader)?;
+                    let arguments_count = read::<u16>(reader)? as usize;
+                    let mut bootstrap_arguments = Vec::with_capacity_in(arguments_count, arena);
+
+                    for _ in (0..arguments_count) {
+                        bootstrap_arguments.push(read(reader)?);
+                    }
+
+                    bootstrap_methods.push(BootstrapMethodEntry {
+                        bootstrap_method_ref,
+                        bootstrap_arguments: bootstrap_arguments.into_bump_slice(),
+                    });
+                }
+
+                Attribute::BootstrapMethods {
+                    bootstrap_methods: bootstrap_methods.into_bump_slice(),
+                }
+            }
+
             "MethodParameters" => {
                 let parameter_count = read::<u8>(reader)? as usize;
                 let mut parameters = Vec::with_capacity_in(parameter_count, arena);
@@ -598,6 +764,19 @@ impl<'at> Attribute<'at> {
                 }
             }
 
+            "PermittedSubclasses" => {
+                let classes_count = read::<u16>(reader)? as usize;
+                let mut classes = Vec::with_capacity_in(classes_count, arena);
+
+                for _ in (0..classes_count) {
+                    classes.push(read(reader)?);
+                }
+
+                Attribute::PermittedSubclasses {
+                    classes: classes.into_bump_slice(),
+                }
+            }
+
             "Record" => {
                 let component_count = read::<u16>(reader)? as usize;
                 let mut components = Vec::with_capacity_in(component_count, arena);
@@ -606,7 +785,12 @@ impl<'at> Attribute<'at> {
                     components.push(RecordComponentInfo {
                         name_index: read(reader)?,
                         descriptor_index: read(reader)?,
-                        attributes: get_attributes(reader, constant_pool, arena)?,
+                        attributes: get_attributes(
+                            reader,
+                            constant_pool,
+                            arena,
+                            ParseOptions::default(),
+                        )?,
                     })
                 }
 
@@ -614,13 +798,73 @@ impl<'at> Attribute<'at> {
                     components: components.into_bump_slice(),
                 }
             }
-            _ => unimplemented!("Parsing for Attribute: {attribute_name} is not yet implemented"),
+            // JVMS (4.7.1) requires attributes that aren't understood to be silently ignored
+            _ => {
+                skip_bytes(reader, length, attribute_name)?;
+                Attribute::Skipped {
+                    name: attribute_name,
+                }
+            }
         };
 
         Ok(attribute)
     }
 }
 
+impl InnerClassEntry {
+    pub(in crate::classfile) fn resolve<'c>(
+        &self,
+        constant_pool: &'c ConstantPool<'c>,
+    ) -> Result<InnerClassInfo<'c>, ConstantPoolError> {
+        // a zero index means the information is absent rather than pointing at an entry
+        let outer_class = constant_pool
+            .get_optional(self.outer_class_info_index)?
+            .map(|_| constant_pool.get_classname(self.outer_class_info_index))
+            .transpose()?;
+        let name = constant_pool
+            .get_optional(self.inner_name_index)?
+            .map(|_| constant_pool.get_utf8(self.inner_name_index))
+            .transpose()?;
+
+        Ok(InnerClassInfo {
+            class_name: constant_pool.get_classname(self.inner_class_info_index)?,
+            outer_class,
+            name,
+            flags: self.inner_class_access_flags,
+        })
+    }
+}
+
+impl LocalVariableEntry {
+    /// Whether `pc` falls within `[start_pc, start_pc + length)`, where the variable has a value.
+    pub(in crate::classfile) fn covers(&self, pc: u16) -> bool {
+        (self.start_pc as u32..self.start_pc as u32 + self.length as u32).contains(&(pc as u32))
+    }
+}
+
+impl<'at> RecordComponentInfo<'at> {
+    pub(in crate::classfile) fn resolve<'c>(
+        &self,
+        constant_pool: &'c ConstantPool<'c>,
+    ) -> Result<RecordComponent<'c>, ConstantPoolError> {
+        let signature = self
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Signature { signature_index } => Some(*signature_index),
+                _ => None,
+            })
+            .map(|index| constant_pool.get_utf8(index))
+            .transpose()?;
+
+        Ok(RecordComponent {
+            name: constant_pool.get_utf8(self.name_index)?,
+            descriptor: constant_pool.get_utf8(self.descriptor_index)?,
+            signature,
+        })
+    }
+}
+
 impl<R: Read> TryFrom<&mut BufReader<R>> for VerificationTypeInfo {
     type Error = ClassfileError;
 
@@ -636,7 +880,7 @@ impl<R: Read> TryFrom<&mut BufReader<R>> for VerificationTypeInfo {
             5 => Ok(VerificationTypeInfo::NullVariable),
             6 => Ok(VerificationTypeInfo::UninitializedThisVariable),
             7 => {
-                let cpool_index = read::<u16>(reader)?;
+                let cpool_index = read(reader)?;
                 Ok(VerificationTypeInfo::ObjectVariable { cpool_index })
             }
             8 => {
@@ -667,32 +911,93 @@ pub(in crate::classfile) fn get_attributes<'at>(
     reader: &mut BufReader<impl Read>,
     constant_pool: &'at ConstantPool<'at>,
     arena: &'at bumpalo::Bump,
+    options: ParseOptions,
 ) -> Result<&'at [Attribute<'at>], ClassfileError> {
     let attributes_count: u16 = read(reader)?;
     let mut attributes =
         bumpalo::collections::Vec::with_capacity_in(attributes_count as usize, arena);
 
     for _ in 0..attributes_count {
-        let name_index: u16 = read(reader)?;
+        let name_index: PoolIndex = read(reader)?;
         let length = read::<u32>(reader)?;
 
-        let attribute = Attribute::new(reader, name_index, length, constant_pool, arena)?;
+        let attribute = match options.skip_attribute_bodies {
+            true => {
+                let name = constant_pool.get_utf8(name_index)?;
+                skip_bytes(reader, length, name)?;
+                Attribute::Skipped { name }
+            }
+            false => Attribute::new(reader, name_index, length, constant_pool, arena)?,
+        };
         attributes.push(attribute);
     }
 
     Ok(attributes.into_bump_slice())
 }
-fn get_annotation<'at>(
+
+/// Reads `length` bytes into the arena. As the length comes from the class file itself, the
+/// buffer only grows as bytes actually arrive instead of being allocated upfront, so a bogus
+/// length fails cleanly rather than exhausting memory.
+fn read_bytes<'at>(
     reader: &mut BufReader<impl Read>,
+    length: u32,
+    attribute_name: &str,
+    arena: &'at bumpalo::Bump,
+) -> Result<&'at [u8], ClassfileError> {
+    // most of the time the bytes are already buffered and can be copied over directly
+    if let Some(buffered) = reader.buffer().get(..length as usize) {
+        let bytes = arena.alloc_slice_copy(buffered);
+        reader.consume(length as usize);
+        return Ok(bytes);
+    }
+
+    let mut bytes = std::vec::Vec::new();
+    reader
+        .by_ref()
+        .take(length as u64)
+        .read_to_end(&mut bytes)?;
+
+    if bytes.len() != length as usize {
+        return Err(ClassfileError::TruncatedAttribute {
+            name: attribute_name.to_string(),
+            length,
+        });
+    }
+
+    Ok(arena.alloc_slice_copy(&bytes))
+}
+
+/// Consumes `length` bytes without keeping them.
+fn skip_bytes(
+    reader: &mut BufReader<impl Read>,
+    length: u32,
+    attribute_name: &str,
+) -> Result<(), ClassfileError> {
+    let skipped = std::io::copy(
+        &mut reader.by_ref().take(length as u64),
+        &mut std::io::sink(),
+    )?;
+    if skipped != length as u64 {
+        return Err(ClassfileError::TruncatedAttribute {
+            name: attribute_name.to_string(),
+            length,
+        });
+    }
+
+    Ok(())
+}
+
+fn get_annotation<'at>(
+    reader: &mut impl Read,
     constant_pool: &'at ConstantPool<'at>,
     arena: &'at bumpalo::Bump,
 ) -> Result<Annotation<'at>, ClassfileError> {
-    let type_index: u16 = read(reader)?;
+    let type_index: PoolIndex = read(reader)?;
     let num_element_pairs = read::<u16>(reader)? as usize;
     let mut element_value_pairs = Vec::with_capacity_in(num_element_pairs, arena);
 
     for _ in (0..num_element_pairs) {
-        let element_name_index: u16 = read(reader)?;
+        let element_name_index: PoolIndex = read(reader)?;
         let element_value = get_element_value(reader, constant_pool, arena)?;
 
         element_value_pairs.push(ElementValuePair {
@@ -708,7 +1013,7 @@ fn get_annotation<'at>(
 }
 
 fn get_element_value<'el>(
-    reader: &mut BufReader<impl Read>,
+    reader: &mut impl Read,
     constant_pool: &'el ConstantPool,
     arena: &'el bumpalo::Bump,
 ) -> Result<ElementValue<'el>, ClassfileError> {
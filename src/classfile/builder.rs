@@ -0,0 +1,207 @@
+//! Programmatic construction of `.class` files.
+//! [`ClassfileBuilder`] collects the declarations of a class and serialises them, interning every
+//! name and descriptor into a freshly built constant pool, so the output can be fed back to
+//! [`Classfile::new`](super::Classfile::new) or to any other JVM.
+
+use super::{AccessFlags, FieldFlags, MAGIC, MethodFlags, OwnedCode};
+use std::collections::HashMap;
+
+/// Class file version emitted by default, matching Java 17.
+const DEFAULT_VERSION: (u16, u16) = (61, 0);
+
+#[derive(Debug, Clone)]
+pub struct ClassfileBuilder {
+    version: (u16, u16),
+    access_flags: AccessFlags,
+    name: String,
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    fields: Vec<(FieldFlags, String, String)>,
+    methods: Vec<(MethodFlags, String, String, Option<OwnedCode>)>,
+}
+
+/// Constant pool under construction, where equal entries share a single index.
+#[derive(Debug, Default)]
+struct PoolBuilder {
+    entries: Vec<Entry>,
+    indexes: HashMap<Entry, u16>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+enum Entry {
+    Utf8(String),
+    Class(u16),
+}
+
+impl ClassfileBuilder {
+    /// Starts a public class named `name`, given in its internal form, e.g. `java/lang/Object`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            version: DEFAULT_VERSION,
+            access_flags: AccessFlags::PUBLIC | AccessFlags::SUPER,
+            name: name.to_string(),
+            super_class: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+        }
+    }
+
+    pub fn version(mut self, major: u16, minor: u16) -> Self {
+        self.version = (major, minor);
+        self
+    }
+
+    pub fn access_flags(mut self, access_flags: AccessFlags) -> Self {
+        self.access_flags = access_flags;
+        self
+    }
+
+    pub fn super_class(mut self, name: &str) -> Self {
+        self.super_class = Some(name.to_string());
+        self
+    }
+
+    pub fn interface(mut self, name: &str) -> Self {
+        self.interfaces.push(name.to_string());
+        self
+    }
+
+    pub fn field(mut self, flags: FieldFlags, name: &str, descriptor: &str) -> Self {
+        self.fields
+            .push((flags, name.to_string(), descriptor.to_string()));
+        self
+    }
+
+    /// Declares a method, with a `Code` attribute when `code` is given. Abstract and native
+    /// methods must have none.
+    pub fn method(
+        mut self,
+        flags: MethodFlags,
+        name: &str,
+        descriptor: &str,
+        code: Option<OwnedCode>,
+    ) -> Self {
+        self.methods
+            .push((flags, name.to_string(), descriptor.to_string(), code));
+        self
+    }
+
+    /// Serialises the class into the `.class` file format defined by JVMS (4.1).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut pool = PoolBuilder::default();
+        let mut body = Vec::new();
+
+        let this_class = pool.class(&self.name);
+        let super_class = self
+            .super_class
+            .as_deref()
+            .map_or(0, |name| pool.class(name));
+
+        write_u16(&mut body, self.access_flags.bits());
+        write_u16(&mut body, this_class);
+        write_u16(&mut body, super_class);
+
+        write_u16(&mut body, self.interfaces.len() as u16);
+        for interface in &self.interfaces {
+            write_u16(&mut body, pool.class(interface));
+        }
+
+        write_u16(&mut body, self.fields.len() as u16);
+        for (flags, name, descriptor) in &self.fields {
+            write_u16(&mut body, flags.bits());
+            write_u16(&mut body, pool.utf8(name));
+            write_u16(&mut body, pool.utf8(descriptor));
+            write_u16(&mut body, 0);
+        }
+
+        write_u16(&mut body, self.methods.len() as u16);
+        for (flags, name, descriptor, code) in &self.methods {
+            write_u16(&mut body, flags.bits());
+            write_u16(&mut body, pool.utf8(name));
+            write_u16(&mut body, pool.utf8(descriptor));
+
+            match code {
+                Some(code) => {
+                    write_u16(&mut body, 1);
+                    write_u16(&mut body, pool.utf8("Code"));
+                    write_code(&mut body, code);
+                }
+                None => write_u16(&mut body, 0),
+            }
+        }
+
+        // no class attributes
+        write_u16(&mut body, 0);
+
+        let mut bytes = Vec::with_capacity(body.len());
+        bytes.extend(MAGIC.to_be_bytes());
+        write_u16(&mut bytes, self.version.1);
+        write_u16(&mut bytes, self.version.0);
+        pool.write(&mut bytes);
+        bytes.extend(body);
+
+        bytes
+    }
+}
+
+impl PoolBuilder {
+    fn utf8(&mut self, string: &str) -> u16 {
+        self.intern(Entry::Utf8(string.to_string()))
+    }
+
+    fn class(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.intern(Entry::Class(name_index))
+    }
+
+    fn intern(&mut self, entry: Entry) -> u16 {
+        if let Some(&index) = self.indexes.get(&entry) {
+            return index;
+        }
+
+        self.entries.push(entry.clone());
+        // the pool is 1-indexed
+        let index = self.entries.len() as u16;
+        self.indexes.insert(entry, index);
+
+        index
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        write_u16(bytes, self.entries.len() as u16 + 1);
+
+        for entry in &self.entries {
+            match entry {
+                Entry::Utf8(string) => {
+                    let encoded = cesu8::to_java_cesu8(string);
+                    bytes.push(1);
+                    write_u16(bytes, encoded.len() as u16);
+                    bytes.extend(encoded.iter());
+                }
+                Entry::Class(name_index) => {
+                    bytes.push(7);
+                    write_u16(bytes, *name_index);
+                }
+            }
+        }
+    }
+}
+
+/// Writes the length and contents of a `Code` attribute, without exception handlers nor nested
+/// attributes.
+fn write_code(bytes: &mut Vec<u8>, code: &OwnedCode) {
+    let length = 2 + 2 + 4 + code.bytecode.len() + 2 + 2;
+    bytes.extend((length as u32).to_be_bytes());
+
+    write_u16(bytes, code.max_stack);
+    write_u16(bytes, code.max_locals);
+    bytes.extend((code.bytecode.len() as u32).to_be_bytes());
+    bytes.extend(&code.bytecode);
+    write_u16(bytes, 0);
+    write_u16(bytes, 0);
+}
+
+fn write_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend(value.to_be_bytes());
+}
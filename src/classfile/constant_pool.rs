@@ -7,10 +7,13 @@
 
 use bumpalo::{Bump, collections::Vec};
 use core::fmt::{Display, Formatter};
-use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::{
+    collections::HashMap,
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+};
 use thiserror::Error;
 
-use crate::classfile::ClassfileError;
+use crate::classfile::{ClassfileError, FromBeBytes};
 
 /// Constant pool of a given Java class.
 #[derive(Debug, PartialEq, Clone)]
@@ -32,20 +35,20 @@ pub(crate) enum ConstantPoolEntry<'c> {
     Long(i64) = 5,
     Double(f64) = 6,
 
-    Class(u16) = 7,
-    StringRef(u16) = 8,
+    Class(PoolIndex) = 7,
+    StringRef(PoolIndex) = 8,
 
-    FieldRef(u16, u16) = 9,
-    MethodRef(u16, u16) = 10,
-    InterfaceMethodRef(u16, u16) = 11,
-    NameAndType(u16, u16) = 12,
+    FieldRef(PoolIndex, PoolIndex) = 9,
+    MethodRef(PoolIndex, PoolIndex) = 10,
+    InterfaceMethodRef(PoolIndex, PoolIndex) = 11,
+    NameAndType(PoolIndex, PoolIndex) = 12,
 
-    MethodHandle(u8, u16) = 15,
-    MethodType(u16) = 16,
-    Dynamic(u16, u16) = 17,
-    InvokeDynamic(u16, u16) = 18,
-    Module(u16) = 19,
-    Package(u16) = 20,
+    MethodHandle(u8, PoolIndex) = 15,
+    MethodType(PoolIndex) = 16,
+    Dynamic(PoolIndex, PoolIndex) = 17,
+    InvokeDynamic(PoolIndex, PoolIndex) = 18,
+    Module(PoolIndex) = 19,
+    Package(PoolIndex) = 20,
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -56,10 +59,64 @@ pub enum ConstantPoolError {
     InvalidAttr(usize),
     #[error("Accessed reserved slot: {0}")]
     UnusableSlot(u16),
+    #[error("Entry {0} is part of a reference cycle")]
+    ReferenceCycle(u16),
     #[error(transparent)]
     Formatter(#[from] core::fmt::Error),
 }
 
+/// Read-only view of a constant pool entry, with its references to other entries resolved.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PublicConstant<'c> {
+    Utf8(&'c str),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    /// Binary name of the class, e.g. `java/lang/Object`.
+    Class(&'c str),
+    String(&'c str),
+    FieldRef(MemberRef<'c>),
+    MethodRef(MemberRef<'c>),
+    InterfaceMethodRef(MemberRef<'c>),
+    NameAndType {
+        name: &'c str,
+        descriptor: &'c str,
+    },
+    MethodHandle {
+        kind: u8,
+        reference: u16,
+    },
+    MethodType(&'c str),
+    Dynamic {
+        bootstrap_method: u16,
+        name: &'c str,
+        descriptor: &'c str,
+    },
+    InvokeDynamic {
+        bootstrap_method: u16,
+        name: &'c str,
+        descriptor: &'c str,
+    },
+    Module(&'c str),
+    Package(&'c str),
+}
+
+/// A field or method reference, resolved down to its names.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MemberRef<'c> {
+    pub class: &'c str,
+    pub name: &'c str,
+    pub descriptor: &'c str,
+}
+
+/// Index into the [constant pool](ConstantPool).
+///
+/// Pool indexes are 1-based, with `0` never referring to an entry, so they are kept apart from
+/// plain integers to avoid mixing them with 0-based positions.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub struct PoolIndex(pub u16);
+
 impl<'c> ConstantPool<'c> {
     pub fn new(
         reader: &mut BufReader<&'c [u8]>,
@@ -69,18 +126,30 @@ impl<'c> ConstantPool<'c> {
 
         let count = read::<u16>(reader)? as usize;
         let mut pool = ConstantPool::with_capacity(count, arena);
+        // equal strings share a single arena allocation
+        let mut strings: HashMap<&'c str, &'c str> = HashMap::with_capacity(count);
+        // raw bytes of the `Utf8` entry being read, reused across entries
+        let mut scratch = std::vec::Vec::new();
         let mut idx = 0;
 
-        while idx < count - 1 {
+        // `count` is one more than the number of slots, `Long` and `Double` entries taking two
+        while idx + 1 < count {
             let tag = read::<u8>(reader)?;
             let entry = match tag {
                 1 => {
                     let length = read::<u16>(reader)? as usize;
-                    let mut bytes = bumpalo::vec![in arena; 0; length];
-                    reader.read_exact(&mut bytes)?;
-
-                    let utf8 = cesu8::from_java_cesu8(&bytes)?;
-                    let string = arena.alloc_str(&utf8);
+                    scratch.resize(length, 0);
+                    reader.read_exact(&mut scratch)?;
+
+                    let utf8 = cesu8::from_java_cesu8(&scratch)?;
+                    let string = match strings.get(utf8.as_ref()) {
+                        Some(&string) => string,
+                        None => {
+                            let string: &'c str = arena.alloc_str(&utf8);
+                            strings.insert(string, string);
+                            string
+                        }
+                    };
 
                     ConstantPoolEntry::Utf8(string)
                 }
@@ -94,11 +163,11 @@ impl<'c> ConstantPool<'c> {
                     idx += 1;
                     ConstantPoolEntry::Double(read::<f64>(reader)?)
                 }
-                7 => ConstantPoolEntry::Class(read::<u16>(reader)?),
-                8 => ConstantPoolEntry::StringRef(read::<u16>(reader)?),
+                7 => ConstantPoolEntry::Class(read(reader)?),
+                8 => ConstantPoolEntry::StringRef(read(reader)?),
                 9 | 10 | 11 | 17 | 18 => {
-                    let class_index: u16 = read(reader)?;
-                    let name_and_type_index: u16 = read(reader)?;
+                    let class_index: PoolIndex = read(reader)?;
+                    let name_and_type_index: PoolIndex = read(reader)?;
                     match tag {
                         9 => ConstantPoolEntry::FieldRef(class_index, name_and_type_index),
                         10 => ConstantPoolEntry::MethodRef(class_index, name_and_type_index),
@@ -109,11 +178,11 @@ impl<'c> ConstantPool<'c> {
                         _ => ConstantPoolEntry::InvokeDynamic(class_index, name_and_type_index),
                     }
                 }
-                12 => ConstantPoolEntry::NameAndType(read::<u16>(reader)?, read::<u16>(reader)?),
-                15 => ConstantPoolEntry::MethodHandle(read::<u8>(reader)?, read::<u16>(reader)?),
-                16 => ConstantPoolEntry::MethodType(read::<u16>(reader)?),
-                19 => ConstantPoolEntry::Module(read::<u16>(reader)?),
-                20 => ConstantPoolEntry::Package(read::<u16>(reader)?),
+                12 => ConstantPoolEntry::NameAndType(read(reader)?, read(reader)?),
+                15 => ConstantPoolEntry::MethodHandle(read::<u8>(reader)?, read(reader)?),
+                16 => ConstantPoolEntry::MethodType(read(reader)?),
+                19 => ConstantPoolEntry::Module(read(reader)?),
+                20 => ConstantPoolEntry::Package(read(reader)?),
                 _ => unreachable!("ConstantPoolEntry for tag: {tag} is not defined"),
             };
 
@@ -141,30 +210,87 @@ impl<'c> ConstantPool<'c> {
     /// Tries to access a [pool entry](ConstantPoolEntry) in a given index.
     ///
     /// **Note**: it uses 1-index based.
-    pub fn get(&self, index: u16) -> Result<&ConstantPoolEntry, ConstantPoolError> {
+    pub fn get(
+        &self,
+        index: impl Into<PoolIndex>,
+    ) -> Result<&ConstantPoolEntry, ConstantPoolError> {
         self.get_with(index, |entry| Ok(entry))
     }
 
-    pub fn get_classname(&self, index: u16) -> Result<&str, ConstantPoolError> {
+    /// Same as [`ConstantPool::get`], but treats the reserved index `0` as an absent entry rather
+    /// than an invalid one, as some structures use it to mean "none".
+    pub fn get_optional(
+        &self,
+        index: impl Into<PoolIndex>,
+    ) -> Result<Option<&ConstantPoolEntry>, ConstantPoolError> {
+        match index.into() {
+            PoolIndex(0) => Ok(None),
+            index => self.get(index).map(Some),
+        }
+    }
+
+    pub fn get_classname(&self, index: impl Into<PoolIndex>) -> Result<&str, ConstantPoolError> {
+        let index = index.into();
         self.get_with(index, |entry| match entry {
             ConstantPoolEntry::Class(name_index) => {
                 self.get_with(*name_index, |utf8_entry| match utf8_entry {
                     ConstantPoolEntry::Utf8(s) => Ok(*s),
-                    _ => Err(ConstantPoolError::InvalidIndex(*name_index)),
+                    _ => Err(ConstantPoolError::InvalidIndex(name_index.0)),
                 })
             }
-            _ => Err(ConstantPoolError::InvalidIndex(index)),
+            _ => Err(ConstantPoolError::InvalidIndex(index.0)),
+        })
+    }
+
+    pub fn get_utf8(&self, index: impl Into<PoolIndex>) -> Result<&str, ConstantPoolError> {
+        let index = index.into();
+        self.get_with(index, |entry| match entry {
+            ConstantPoolEntry::Utf8(s) => Ok(*s),
+            _ => Err(ConstantPoolError::InvalidIndex(index.0)),
+        })
+    }
+
+    /// Resolves a `NameAndType` entry into its member name and descriptor.
+    pub fn resolve_name_and_type(
+        &self,
+        index: impl Into<PoolIndex>,
+    ) -> Result<(&str, &str), ConstantPoolError> {
+        let index = index.into();
+        self.get_with(index, |entry| match entry {
+            ConstantPoolEntry::NameAndType(name, descriptor) => {
+                Ok((self.get_utf8(*name)?, self.get_utf8(*descriptor)?))
+            }
+            _ => Err(ConstantPoolError::InvalidIndex(index.0)),
+        })
+    }
+
+    /// Resolves a `FieldRef`, `MethodRef` or `InterfaceMethodRef` entry into the name of the
+    /// owning class, the member name and its descriptor.
+    pub fn resolve_ref(
+        &self,
+        index: impl Into<PoolIndex>,
+    ) -> Result<(&str, &str, &str), ConstantPoolError> {
+        let index = index.into();
+        self.get_with(index, |entry| match entry {
+            ConstantPoolEntry::FieldRef(class, name_and_type)
+            | ConstantPoolEntry::MethodRef(class, name_and_type)
+            | ConstantPoolEntry::InterfaceMethodRef(class, name_and_type) => {
+                let (name, descriptor) = self.resolve_name_and_type(*name_and_type)?;
+                Ok((self.get_classname(*class)?, name, descriptor))
+            }
+            _ => Err(ConstantPoolError::InvalidIndex(index.0)),
         })
     }
 
     pub fn get_with<F, T>(
         &'c self,
-        index: u16,
+        index: impl Into<PoolIndex>,
         check_and_convert: F,
     ) -> Result<T, ConstantPoolError>
     where
         F: FnOnce(&'c ConstantPoolEntry<'c>) -> Result<T, ConstantPoolError>,
     {
+        let PoolIndex(index) = index.into();
         if index == 0 || index as usize > self.entries.len() {
             return Err(ConstantPoolError::InvalidIndex(index));
         }
@@ -177,7 +303,92 @@ impl<'c> ConstantPool<'c> {
         }
     }
 
-    fn format(&self, index: u16, f: &mut Formatter) -> Result<(), ConstantPoolError> {
+    /// Entries of the pool in order, leaving out the unusable slots following `Long` and `Double`
+    /// entries.
+    pub fn entries(&self) -> impl Iterator<Item = ConstantPoolEntry<'c>> + '_ {
+        self.entries.iter().flatten().copied()
+    }
+
+    /// Number of slots in the pool, including the unusable ones following `Long` and `Double`
+    /// entries. Valid indexes range from `1` to this length.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn public_constant(&'c self, index: impl Into<PoolIndex>) -> Option<PublicConstant<'c>> {
+        let index = index.into();
+        let name_and_type = |index: PoolIndex| self.resolve_name_and_type(index).ok();
+        let member = |class: PoolIndex, name_and_type_index: PoolIndex| {
+            let (name, descriptor) = name_and_type(name_and_type_index)?;
+            Some(MemberRef {
+                class: self.get_classname(class).ok()?,
+                name,
+                descriptor,
+            })
+        };
+
+        let constant = match self.get(index).ok()? {
+            ConstantPoolEntry::Utf8(string) => PublicConstant::Utf8(string),
+            ConstantPoolEntry::Integer(int) => PublicConstant::Integer(*int),
+            ConstantPoolEntry::Float(float) => PublicConstant::Float(*float),
+            ConstantPoolEntry::Long(long) => PublicConstant::Long(*long),
+            ConstantPoolEntry::Double(double) => PublicConstant::Double(*double),
+
+            ConstantPoolEntry::Class(name) => PublicConstant::Class(self.get_utf8(*name).ok()?),
+            ConstantPoolEntry::StringRef(string) => {
+                PublicConstant::String(self.get_utf8(*string).ok()?)
+            }
+            ConstantPoolEntry::FieldRef(class, nat) => {
+                PublicConstant::FieldRef(member(*class, *nat)?)
+            }
+            ConstantPoolEntry::MethodRef(class, nat) => {
+                PublicConstant::MethodRef(member(*class, *nat)?)
+            }
+            ConstantPoolEntry::InterfaceMethodRef(class, nat) => {
+                PublicConstant::InterfaceMethodRef(member(*class, *nat)?)
+            }
+            ConstantPoolEntry::NameAndType(..) => {
+                let (name, descriptor) = name_and_type(index)?;
+                PublicConstant::NameAndType { name, descriptor }
+            }
+
+            ConstantPoolEntry::MethodHandle(kind, reference) => PublicConstant::MethodHandle {
+                kind: *kind,
+                reference: reference.0,
+            },
+            ConstantPoolEntry::MethodType(descriptor) => {
+                PublicConstant::MethodType(self.get_utf8(*descriptor).ok()?)
+            }
+            ConstantPoolEntry::Dynamic(bootstrap, nat) => {
+                let (name, descriptor) = name_and_type(*nat)?;
+                PublicConstant::Dynamic {
+                    bootstrap_method: bootstrap.0,
+                    name,
+                    descriptor,
+                }
+            }
+            ConstantPoolEntry::InvokeDynamic(bootstrap, nat) => {
+                let (name, descriptor) = name_and_type(*nat)?;
+                PublicConstant::InvokeDynamic {
+                    bootstrap_method: bootstrap.0,
+                    name,
+                    descriptor,
+                }
+            }
+            ConstantPoolEntry::Module(name) => PublicConstant::Module(self.get_utf8(*name).ok()?),
+            ConstantPoolEntry::Package(name) => PublicConstant::Package(self.get_utf8(*name).ok()?),
+        };
+
+        Some(constant)
+    }
+
+    fn format(
+        &self,
+        index: PoolIndex,
+        f: &mut Formatter,
+        depth: usize,
+    ) -> Result<(), ConstantPoolError> {
+        check_depth(index, depth)?;
         let entry = self.get(index)?;
 
         match entry {
@@ -192,13 +403,12 @@ impl<'c> ConstantPool<'c> {
             | ConstantPoolEntry::MethodType(idx)
             | ConstantPoolEntry::Module(idx)
             | ConstantPoolEntry::Package(idx) => {
-                return self.format(*idx, f);
+                return self.format(*idx, f, depth + 1);
             }
 
-            ConstantPoolEntry::MethodHandle(idx, info) => {
-                self.format(*idx as _, f)?;
-                write!(f, ".")?;
-                Ok(self.format(*info, f)?)
+            ConstantPoolEntry::MethodHandle(kind, info) => {
+                write!(f, "{kind}.")?;
+                Ok(self.format(*info, f, depth + 1)?)
             }
 
             ConstantPoolEntry::FieldRef(idx, info)
@@ -207,27 +417,34 @@ impl<'c> ConstantPool<'c> {
             | ConstantPoolEntry::NameAndType(idx, info)
             | ConstantPoolEntry::Dynamic(idx, info)
             | ConstantPoolEntry::InvokeDynamic(idx, info) => {
-                self.format(*idx, f)?;
+                self.format(*idx, f, depth + 1)?;
                 write!(f, ".")?;
-                Ok(self.format(*info, f)?)
+                Ok(self.format(*info, f, depth + 1)?)
             }
         }
         .map_err(Into::into)
     }
 
-    fn format_entry(&self, index: u16, f: &mut Formatter) -> Result<(), ConstantPoolError> {
+    fn format_entry(
+        &self,
+        index: PoolIndex,
+        f: &mut Formatter,
+        depth: usize,
+    ) -> Result<(), ConstantPoolError> {
         fn format_pair(
             this: &ConstantPool,
             name: &str,
-            first: u16,
-            second: u16,
+            first: PoolIndex,
+            second: PoolIndex,
             f: &mut Formatter,
+            depth: usize,
         ) -> Result<(), ConstantPoolError> {
             write!(f, "{name}: {} => (", first)?;
-            this.format_entry(second, f)?;
+            this.format_entry(second, f, depth + 1)?;
             write!(f, ")").map_err(Into::into)
         }
 
+        check_depth(index, depth)?;
         match self.get(index)? {
             ConstantPoolEntry::Utf8(s) => write!(f, "Utf8: \"{s}\""),
             ConstantPoolEntry::Integer(int) => write!(f, "Integer: {int}"),
@@ -237,26 +454,26 @@ impl<'c> ConstantPool<'c> {
 
             ConstantPoolEntry::Class(idx) => {
                 write!(f, "Class: {} => (", idx)?;
-                self.format_entry(*idx, f)?;
+                self.format_entry(*idx, f, depth + 1)?;
                 write!(f, ")")
             }
             ConstantPoolEntry::StringRef(idx) => {
                 write!(f, "StringRef: {} => (", idx)?;
-                self.format_entry(*idx, f)?;
+                self.format_entry(*idx, f, depth + 1)?;
                 write!(f, ")")
             }
 
             ConstantPoolEntry::FieldRef(idx, info) => {
-                return format_pair(self, "FieldRef", *idx, *info, f);
+                return format_pair(self, "FieldRef", *idx, *info, f, depth);
             }
             ConstantPoolEntry::MethodRef(idx, info) => {
-                return format_pair(self, "MethodRef", *idx, *info, f);
+                return format_pair(self, "MethodRef", *idx, *info, f, depth);
             }
             ConstantPoolEntry::NameAndType(idx, info) => {
-                return format_pair(self, "NameAndType", *idx, *info, f);
+                return format_pair(self, "NameAndType", *idx, *info, f, depth);
             }
             ConstantPoolEntry::InterfaceMethodRef(idx, info) => {
-                return format_pair(self, "InterfaceMethodRef", *idx, *info, f);
+                return format_pair(self, "InterfaceMethodRef", *idx, *info, f, depth);
             }
             _ => unimplemented!(),
         }
@@ -264,6 +481,38 @@ impl<'c> ConstantPool<'c> {
     }
 }
 
+/// Deepest chain of references a well-formed pool can have, from a `MethodHandle` down to the
+/// `Utf8` name of the class of the member it refers to.
+const MAX_REFERENCE_DEPTH: usize = 4;
+
+/// Fails once following references from an entry went deeper than any well-formed pool allows,
+/// which can only be due to a cycle.
+fn check_depth(index: PoolIndex, depth: usize) -> Result<(), ConstantPoolError> {
+    match depth > MAX_REFERENCE_DEPTH {
+        true => Err(ConstantPoolError::ReferenceCycle(index.0)),
+        false => Ok(()),
+    }
+}
+
+impl From<u16> for PoolIndex {
+    fn from(index: u16) -> Self {
+        Self(index)
+    }
+}
+
+impl Display for PoolIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl FromBeBytes for PoolIndex {
+    type Bytes = [u8; 2];
+    fn from_be_bytes(bytes: Self::Bytes) -> Self {
+        Self(u16::from_be_bytes(bytes))
+    }
+}
+
 impl<'c> ConstantPoolEntry<'c> {
     /// JVM mandates that `Long` and `Double` constraints must occupy two slots in the constant
     /// pool.
@@ -280,7 +529,8 @@ impl<'c> Display for ConstantPool<'c> {
 
         for idx in 0..self.entries.len() as u16 {
             writeln!(f, "   {idx}, ")?;
-            self.format_entry(idx, f).map_err(|_| std::fmt::Error)?;
+            self.format_entry(PoolIndex(idx), f, 0)
+                .map_err(|_| std::fmt::Error)?;
         }
 
         Ok(())
@@ -308,9 +558,9 @@ mod tests {
         pool.push(ConstantPoolEntry::Integer(1i32)); // 2
         pool.push(ConstantPoolEntry::Long(2i64)); // 3 - 4
         pool.push(ConstantPoolEntry::Double(f64::EPSILON)); // 5 - 6
-        pool.push(ConstantPoolEntry::Class(1)); // 7 
-        pool.push(ConstantPoolEntry::MethodRef(1, 7)); // 8
-        pool.push(ConstantPoolEntry::FieldRef(1, 7)); // 9
+        pool.push(ConstantPoolEntry::Class(PoolIndex(1))); // 7
+        pool.push(ConstantPoolEntry::MethodRef(PoolIndex(1), PoolIndex(7))); // 8
+        pool.push(ConstantPoolEntry::FieldRef(PoolIndex(1), PoolIndex(7))); // 9
 
         assert_eq!(pool.get(0).unwrap_err(), ConstantPoolError::InvalidIndex(0));
         assert_eq!(
@@ -322,9 +572,234 @@ mod tests {
         assert_eq!(pool.get(6).unwrap_err(), ConstantPoolError::UnusableSlot(6));
 
         assert_eq!(pool.get(1)?, &ConstantPoolEntry::Utf8("hello world"));
-        assert_eq!(pool.get(8)?, &ConstantPoolEntry::MethodRef(1, 7));
-        assert_eq!(pool.get(9)?, &ConstantPoolEntry::FieldRef(1, 7));
+        assert_eq!(
+            pool.get(8)?,
+            &ConstantPoolEntry::MethodRef(PoolIndex(1), PoolIndex(7))
+        );
+        assert_eq!(
+            pool.get(9)?,
+            &ConstantPoolEntry::FieldRef(PoolIndex(1), PoolIndex(7))
+        );
 
         Ok(())
     }
+
+    #[test]
+    fn pool_index_zero_is_invalid() {
+        let arena = Bump::new();
+        let mut pool = ConstantPool::default(&arena);
+        pool.push(ConstantPoolEntry::Utf8("first"));
+
+        let entry = pool.get_with(PoolIndex(0), |entry| Ok(*entry));
+        assert_eq!(entry.unwrap_err(), ConstantPoolError::InvalidIndex(0));
+
+        // the first entry lives at index 1
+        assert_eq!(pool.get_utf8(PoolIndex(1)), Ok("first"));
+        assert_eq!(PoolIndex::from(1).to_string(), "#1");
+    }
+
+    #[test]
+    fn optional_index_zero_is_absent() {
+        let arena = Bump::new();
+        let mut pool = ConstantPool::default(&arena);
+        pool.push(ConstantPoolEntry::Utf8("first"));
+
+        assert_eq!(pool.get_optional(0), Ok(None));
+        assert_eq!(
+            pool.get_optional(1),
+            Ok(Some(&ConstantPoolEntry::Utf8("first")))
+        );
+        assert_eq!(
+            pool.get_optional(2),
+            Err(ConstantPoolError::InvalidIndex(2))
+        );
+    }
+
+    #[test]
+    fn boundary_numeric_constants() -> Result<(), ClassfileError> {
+        const INTEGERS: [i32; 3] = [i32::MIN, i32::MAX, -1];
+        // quiet NaN with a payload, signalling NaN, infinities, smallest denormal and -0.0
+        const FLOATS: [u32; 6] = [
+            0x7FC0_0001,
+            0x7F80_0001,
+            0x7F80_0000,
+            0xFF80_0000,
+            0x0000_0001,
+            0x8000_0000,
+        ];
+        const LONGS: [i64; 2] = [i64::MIN, i64::MAX];
+        const DOUBLES: [u64; 3] = [
+            0x7FF0_0000_0000_0001,
+            0x0000_0000_0000_0001,
+            0x8000_0000_0000_0000,
+        ];
+
+        let mut bytes = vec![];
+        INTEGERS.iter().for_each(|value| {
+            bytes.push(3);
+            bytes.extend(value.to_be_bytes());
+        });
+        FLOATS.iter().for_each(|bits| {
+            bytes.push(4);
+            bytes.extend(bits.to_be_bytes());
+        });
+        LONGS.iter().for_each(|value| {
+            bytes.push(5);
+            bytes.extend(value.to_be_bytes());
+        });
+        DOUBLES.iter().for_each(|bits| {
+            bytes.push(6);
+            bytes.extend(bits.to_be_bytes());
+        });
+        let slots = INTEGERS.len() + FLOATS.len() + 2 * (LONGS.len() + DOUBLES.len());
+        let count = (slots as u16 + 1).to_be_bytes();
+        let bytes = [&count[..], &bytes].concat();
+
+        let arena = Bump::new();
+        let mut reader = BufReader::new(bytes.as_slice());
+        let pool = ConstantPool::new(&mut reader, &arena)?;
+
+        let mut index = 1u16;
+        let mut next = |slots: u16| {
+            let entry = *pool.get(index).unwrap();
+            index += slots;
+            entry
+        };
+
+        for value in INTEGERS {
+            assert_eq!(next(1), ConstantPoolEntry::Integer(value));
+        }
+        for bits in FLOATS {
+            // NaNs never compare equal, so the bit patterns are compared instead
+            let ConstantPoolEntry::Float(float) = next(1) else {
+                panic!("expected a float constant");
+            };
+            assert_eq!(float.to_bits(), bits);
+        }
+        for value in LONGS {
+            assert_eq!(next(2), ConstantPoolEntry::Long(value));
+        }
+        for bits in DOUBLES {
+            let ConstantPoolEntry::Double(double) = next(2) else {
+                panic!("expected a double constant");
+            };
+            assert_eq!(double.to_bits(), bits);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_strings_are_shared() -> Result<(), ClassfileError> {
+        let strings = [
+            "()V",
+            "java/lang/Object",
+            "()V",
+            "<init>",
+            "java/lang/Object",
+        ];
+        let mut bytes = (strings.len() as u16 + 1).to_be_bytes().to_vec();
+        for string in strings {
+            bytes.push(1);
+            bytes.extend((string.len() as u16).to_be_bytes());
+            bytes.extend(string.as_bytes());
+        }
+
+        let arena = Bump::new();
+        let mut reader = BufReader::new(bytes.as_slice());
+        let pool = ConstantPool::new(&mut reader, &arena)?;
+
+        for (index, string) in strings.iter().enumerate() {
+            assert_eq!(pool.get_utf8(index as u16 + 1)?, *string);
+        }
+        assert!(std::ptr::eq(pool.get_utf8(1)?, pool.get_utf8(3)?));
+        assert!(std::ptr::eq(pool.get_utf8(2)?, pool.get_utf8(5)?));
+        assert!(!std::ptr::eq(pool.get_utf8(1)?, pool.get_utf8(4)?));
+
+        Ok(())
+    }
+
+    /// Parses a pool of `count` slots out of `entries`, checking that nothing past it is read.
+    fn pool_of<'c>(count: u16, entries: &[u8], arena: &'c Bump) -> ConstantPool<'c> {
+        let mut bytes = count.to_be_bytes().to_vec();
+        bytes.extend(entries);
+        bytes.push(0xAB);
+
+        let mut reader = BufReader::new(&*arena.alloc_slice_copy(&bytes));
+        let pool = ConstantPool::new(&mut reader, arena).unwrap();
+        assert_eq!(crate::classfile::read::<u8>(&mut reader).unwrap(), 0xAB);
+
+        pool
+    }
+
+    #[test]
+    fn two_slot_entries_at_the_boundaries() -> Result<(), ConstantPoolError> {
+        let arena = Bump::new();
+        let integer = [3, 0, 0, 0, 7];
+        let long = [5, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+
+        // a `Long` as the very last entry
+        let pool = pool_of(4, &[&integer[..], &long].concat(), &arena);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.get(1)?, &ConstantPoolEntry::Integer(7));
+        assert_eq!(
+            pool.get(2)?,
+            &ConstantPoolEntry::Long(0x1122_3344_5566_7788)
+        );
+        assert_eq!(pool.get(3), Err(ConstantPoolError::UnusableSlot(3)));
+        assert_eq!(pool.get(4), Err(ConstantPoolError::InvalidIndex(4)));
+
+        // an entry right after a `Long` lands past its unusable slot
+        let pool = pool_of(4, &[&long[..], &integer].concat(), &arena);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.get(2), Err(ConstantPoolError::UnusableSlot(2)));
+        assert_eq!(pool.get(3)?, &ConstantPoolEntry::Integer(7));
+
+        // a count of 0 is invalid, but must not underflow
+        assert_eq!(pool_of(0, &[], &arena).len(), 0);
+        assert_eq!(pool_of(1, &[], &arena).len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reference_cycles_are_detected() {
+        struct Formatted<'p, 'c>(&'p ConstantPool<'c>, u16, bool);
+
+        impl Display for Formatted<'_, '_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                let Self(pool, index, entry) = self;
+                let result = match entry {
+                    true => pool.format_entry(PoolIndex(*index), f, 0),
+                    false => pool.format(PoolIndex(*index), f, 0),
+                };
+
+                match result {
+                    Err(ConstantPoolError::ReferenceCycle(index)) => {
+                        write!(f, "<cycle at {index}>")
+                    }
+                    result => result.map_err(|_| std::fmt::Error),
+                }
+            }
+        }
+
+        let arena = Bump::new();
+        let mut pool = ConstantPool::default(&arena);
+        pool.push(ConstantPoolEntry::Class(PoolIndex(1))); // 1
+        pool.push(ConstantPoolEntry::Utf8("Person")); // 2
+        pool.push(ConstantPoolEntry::Class(PoolIndex(2))); // 3
+        pool.push(ConstantPoolEntry::NameAndType(PoolIndex(2), PoolIndex(4))); // 4
+
+        // the entries referring to themselves, directly or through a `NameAndType` pointing at
+        // itself, are cut short rather than overflowing the stack
+        for (index, entry) in [(1, false), (1, true), (4, false), (4, true)] {
+            let formatted = Formatted(&pool, index, entry).to_string();
+            assert!(formatted.contains("<cycle at"), "{formatted}");
+        }
+        assert_eq!(Formatted(&pool, 3, false).to_string(), "Person");
+        assert_eq!(
+            Formatted(&pool, 3, true).to_string(),
+            "Class: #2 => (Utf8: \"Person\")"
+        );
+    }
 }
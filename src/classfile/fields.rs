@@ -2,7 +2,9 @@
 //! A `field_info` structure is used to represent a field (instance variable or class variable) in a Java class.
 
 use super::attributes::Attribute;
-use crate::classfile::{ClassfileError, ConstantPool, get_attributes, read};
+use crate::classfile::{
+    ClassfileError, ConstantPool, ParseOptions, constant_pool::PoolIndex, get_attributes, read,
+};
 use bitflags::bitflags;
 use bumpalo::{Bump, collections::Vec};
 use std::io::{BufReader, Read};
@@ -11,8 +13,8 @@ use std::io::{BufReader, Read};
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Field<'at> {
     pub(super) access_flags: FieldFlags,
-    pub(super) name_index: u16,
-    pub(super) descriptor_index: u16,
+    pub(super) name_index: PoolIndex,
+    pub(super) descriptor_index: PoolIndex,
     pub(super) attributes: &'at [Attribute<'at>],
 }
 
@@ -41,6 +43,24 @@ bitflags! {
     }
 }
 
+impl FieldFlags {
+    /// Java source modifiers set by these flags, in declaration order.
+    pub(super) fn modifiers(self) -> impl Iterator<Item = &'static str> {
+        [
+            (Self::PUBLIC, "public"),
+            (Self::PRIVATE, "private"),
+            (Self::PROTECTED, "protected"),
+            (Self::STATIC, "static"),
+            (Self::FINAL, "final"),
+            (Self::TRANSIENT, "transient"),
+            (Self::VOLATILE, "volatile"),
+        ]
+        .into_iter()
+        .filter(move |(flag, _)| self.contains(*flag))
+        .map(|(_, modifier)| modifier)
+    }
+}
+
 impl<'f> Field<'f> {
     pub fn contains(&self, flags: &[FieldFlags]) -> bool {
         flags.iter().all(|flag| self.access_flags.contains(*flag))
@@ -51,6 +71,7 @@ pub(in crate::classfile) fn parse_fields<'c>(
     reader: &mut BufReader<impl Read>,
     constant_pool: &'c ConstantPool<'c>,
     arena: &'c Bump,
+    options: ParseOptions,
 ) -> Result<&'c [Field<'c>], ClassfileError> {
     let fields_count = read::<u16>(reader)? as usize;
     let mut fields_vec = Vec::with_capacity_in(fields_count, arena);
@@ -60,7 +81,7 @@ pub(in crate::classfile) fn parse_fields<'c>(
             access_flags: FieldFlags::from_bits_truncate(read(reader)?),
             name_index: read(reader)?,
             descriptor_index: read(reader)?,
-            attributes: get_attributes(reader, constant_pool, arena)?,
+            attributes: get_attributes(reader, constant_pool, arena, options)?,
         };
 
         fields_vec.push(entry);
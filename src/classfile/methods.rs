@@ -1,4 +1,9 @@
-use crate::classfile::{ClassfileError, ConstantPool, attributes::Attribute, get_attributes, read};
+use crate::classfile::{
+    ClassfileError, ConstantPool, ParseOptions,
+    attributes::{Attribute, LocalVariableEntry},
+    constant_pool::PoolIndex,
+    get_attributes, read,
+};
 use bitflags::bitflags;
 use bumpalo::{Bump, collections::Vec};
 use std::io::{BufReader, Read};
@@ -7,11 +12,30 @@ use std::io::{BufReader, Read};
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Method<'m> {
     pub(super) access_flags: MethodFlags,
-    pub(super) name_index: u16,
-    pub(super) descriptor_index: u16,
+    pub(super) name_index: PoolIndex,
+    pub(super) descriptor_index: PoolIndex,
     pub(super) attributes: &'m [Attribute<'m>],
 }
 
+/// One-call overview of a method, as returned by
+/// [`Classfile::method_summary`](super::Classfile::method_summary).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MethodSummary<'m> {
+    pub descriptor: &'m str,
+    pub is_public: bool,
+    pub is_private: bool,
+    pub is_static: bool,
+    pub is_final: bool,
+    pub is_abstract: bool,
+    pub is_native: bool,
+    pub is_synchronized: bool,
+    pub is_synthetic: bool,
+    pub is_bridge: bool,
+    pub is_varargs: bool,
+    /// Whether the method carries a `Code` attribute, which abstract and native ones lack.
+    pub has_code: bool,
+}
+
 bitflags! {
     /// Method access and proprieties flags.
     #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
@@ -43,16 +67,121 @@ bitflags! {
     }
 }
 
+impl MethodFlags {
+    /// Java source modifiers set by these flags, in declaration order.
+    pub(super) fn modifiers(self) -> impl Iterator<Item = &'static str> {
+        [
+            (Self::PUBLIC, "public"),
+            (Self::PRIVATE, "private"),
+            (Self::PROTECTED, "protected"),
+            (Self::ABSTRACT, "abstract"),
+            (Self::STATIC, "static"),
+            (Self::FINAL, "final"),
+            (Self::SYNCHRONIZED, "synchronized"),
+            (Self::NATIVE, "native"),
+            (Self::STRICT, "strictfp"),
+        ]
+        .into_iter()
+        .filter(move |(flag, _)| self.contains(*flag))
+        .map(|(_, modifier)| modifier)
+    }
+}
+
 impl<'c> Method<'c> {
     pub fn contains(&self, flags: &[MethodFlags]) -> bool {
         flags.iter().all(|flag| self.access_flags.contains(*flag))
     }
+
+    pub fn flags(&self) -> MethodFlags {
+        self.access_flags
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.access_flags.contains(MethodFlags::PUBLIC)
+    }
+    pub fn is_private(&self) -> bool {
+        self.access_flags.contains(MethodFlags::PRIVATE)
+    }
+    pub fn is_static(&self) -> bool {
+        self.access_flags.contains(MethodFlags::STATIC)
+    }
+    pub fn is_final(&self) -> bool {
+        self.access_flags.contains(MethodFlags::FINAL)
+    }
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags.contains(MethodFlags::ABSTRACT)
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.access_flags.contains(MethodFlags::NATIVE)
+    }
+    pub fn is_synchronized(&self) -> bool {
+        self.access_flags.contains(MethodFlags::SYNCHRONIZED)
+    }
+    pub fn is_varargs(&self) -> bool {
+        self.access_flags.contains(MethodFlags::VARARGS)
+    }
+    pub fn is_bridge(&self) -> bool {
+        self.access_flags.contains(MethodFlags::BRIDGE)
+    }
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.contains(MethodFlags::SYNTHETIC)
+    }
+
+    /// Bytecode of the method `Code` attribute; abstract and native methods have none.
+    pub(super) fn code(&self) -> Option<&'c [u8]> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code { code, .. } => Some(*code),
+                _ => None,
+            })
+    }
+
+    /// Entry of the `LocalVariableTable` attributes declaring the local `index` whose range
+    /// covers `pc`, picking the innermost one should several ranges overlap.
+    pub(super) fn local_variable(&self, pc: u16, index: u16) -> Option<&'c LocalVariableEntry> {
+        self.attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                Attribute::Code { attributes, .. } => Some(attributes.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|attribute| match attribute {
+                Attribute::LocalVariableTable {
+                    local_variable_table,
+                } => Some(local_variable_table.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter(|entry| entry.index == index && entry.covers(pc))
+            .max_by_key(|entry| entry.start_pc)
+    }
+
+    pub(super) fn summary<'d>(&self, descriptor: &'d str) -> MethodSummary<'d> {
+        MethodSummary {
+            descriptor,
+            is_public: self.is_public(),
+            is_private: self.is_private(),
+            is_static: self.is_static(),
+            is_final: self.is_final(),
+            is_abstract: self.is_abstract(),
+            is_native: self.is_native(),
+            is_synchronized: self.is_synchronized(),
+            is_synthetic: self.is_synthetic(),
+            is_bridge: self.is_bridge(),
+            is_varargs: self.is_varargs(),
+            has_code: self.code().is_some(),
+        }
+    }
 }
 
 pub(in crate::classfile) fn parse_methods<'m>(
     reader: &mut BufReader<impl Read>,
     constant_pool: &'m ConstantPool<'m>,
     arena: &'m Bump,
+    options: ParseOptions,
 ) -> Result<&'m [Method<'m>], ClassfileError> {
     let methods_count = read::<u16>(reader)? as usize;
     let mut methods = Vec::with_capacity_in(methods_count, arena);
@@ -62,7 +191,7 @@ pub(in crate::classfile) fn parse_methods<'m>(
             access_flags: MethodFlags::from_bits_truncate(read(reader)?),
             name_index: read(reader)?,
             descriptor_index: read(reader)?,
-            attributes: get_attributes(reader, constant_pool, arena)?,
+            attributes: get_attributes(reader, constant_pool, arena, options)?,
         };
 
         methods.push(entry)
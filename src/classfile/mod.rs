@@ -10,24 +10,36 @@
 #![allow(elided_named_lifetimes, private_interfaces)]
 
 mod attributes;
+mod builder;
 mod constant_pool;
 mod fields;
 mod methods;
+mod names;
+mod owned;
 
+pub use attributes::{AttributeKind, InnerClassFlags, InnerClassInfo, RecordComponent};
+pub use builder::ClassfileBuilder;
+pub(crate) use constant_pool::ConstantPoolEntry;
+pub use constant_pool::{MemberRef, PublicConstant};
 pub use fields::FieldFlags;
-pub use methods::MethodFlags;
+pub use methods::{MethodFlags, MethodSummary};
+pub use names::{binary_name, source_type_name};
+pub use owned::{OwnedClassfile, OwnedCode, OwnedField, OwnedMethod, parse_many};
 
 use crate::classfile::{
     fields::parse_fields,
     methods::{Method, parse_methods},
 };
 
-use self::attributes::get_attributes;
+use self::attributes::{Attribute, get_attributes};
 use bitflags::bitflags;
 use bumpalo::{Bump, collections::Vec};
-use constant_pool::{ConstantPool, ConstantPoolError};
+use constant_pool::{ConstantPool, ConstantPoolError, PoolIndex};
 use fields::Field;
-use std::io::{BufReader, Cursor, Read};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{BufReader, Cursor, Read},
+};
 use thiserror::Error;
 
 /// Classfile structure defined by JVMS (4.1)
@@ -36,16 +48,35 @@ pub struct Classfile<'cf> {
     version: Version,
     constant_pool: &'cf ConstantPool<'cf>,
     access_flags: AccessFlags,
-    this_class: u16,
-    super_class: u16,
-    interfaces: &'cf [u16],
+    this_class: PoolIndex,
+    super_class: PoolIndex,
+    interfaces: &'cf [PoolIndex],
     pub fields: &'cf [Field<'cf>],
     pub methods: &'cf [Method<'cf>],
+    attributes: &'cf [Attribute<'cf>],
+}
+
+/// Name, descriptor and bytecode of a method, as listed by [`Classfile::all_method_code`].
+pub type MethodCode<'c> = (&'c str, &'c str, &'c [u8]);
+
+/// Entry of a method exception table, as listed by `Classfile::exception_table`.
+pub(crate) type ExceptionRange<'c> = (u16, u16, u16, Option<&'c str>);
+
+/// Tunes how much of a class file [`Classfile::with_options`] parses.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ParseOptions {
+    /// Skips over the contents of every attribute, keeping only their names. Much faster when
+    /// only the shape of the class matters, but anything read from attributes, such as the code
+    /// of methods or the source file, is then missing.
+    pub skip_attribute_bodies: bool,
+    /// Fails with [`ClassfileError::TrailingBytes`] if anything follows the class structure,
+    /// rather than ignoring it.
+    pub strict_eof: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 /// This determines the version of the `class` file format.
-pub(crate) struct Version {
+pub struct Version {
     major: u16,
     minor: u16,
 }
@@ -62,6 +93,30 @@ pub enum ClassfileError {
     Version(u16),
     #[error(transparent)]
     ConstantPool(#[from] ConstantPoolError),
+    #[error("Method {0} is not declared in this class")]
+    MethodNotFound(String),
+    #[error("Attribute {name} claims {length} bytes, past the end of the class file")]
+    TruncatedAttribute { name: String, length: u32 },
+    #[error("{0} unexpected bytes after the end of the class file")]
+    TrailingBytes(usize),
+    #[error("Parser panicked: {0}")]
+    Panicked(String),
+    #[error("Malformed classfile at byte {offset}: {source}")]
+    Malformed {
+        /// Position in the class file where parsing stopped.
+        offset: usize,
+        source: Box<ClassfileError>,
+    },
+}
+
+impl ClassfileError {
+    /// Position in the class file where parsing failed, for errors raised while parsing.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Malformed { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
 }
 
 /// Magic header number for a `.class` file.
@@ -70,7 +125,7 @@ pub(crate) const MAGIC: u32 = 0xCAFEBABE;
 bitflags! {
     /// Class, field, method, and module access and property flags
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-    pub(crate) struct AccessFlags: u16 {
+    pub struct AccessFlags: u16 {
         /// Declared public; may be accessed from outside its package.
         const PUBLIC = 0x0001;
         /// Declared final; no subclasses allowed.
@@ -92,6 +147,35 @@ bitflags! {
     }
 }
 
+impl AccessFlags {
+    /// Java source modifiers set by these flags, ending with the kind of the declared type.
+    fn modifiers(self) -> impl Iterator<Item = &'static str> {
+        let kind = match self {
+            flags if flags.contains(Self::MODULE) => "module",
+            flags if flags.contains(Self::ANNOTATION) => "@interface",
+            flags if flags.contains(Self::INTERFACE) => "interface",
+            flags if flags.contains(Self::ENUM) => "enum",
+            _ => "class",
+        };
+        // interfaces are implicitly abstract and enums implicitly final
+        let implicit = match kind {
+            "class" => Self::empty(),
+            "enum" => Self::FINAL,
+            _ => Self::ABSTRACT,
+        };
+
+        [
+            (Self::PUBLIC, "public"),
+            (Self::ABSTRACT, "abstract"),
+            (Self::FINAL, "final"),
+        ]
+        .into_iter()
+        .filter(move |(flag, _)| self.contains(*flag) && !implicit.contains(*flag))
+        .map(|(_, modifier)| modifier)
+        .chain([kind])
+    }
+}
+
 trait FromBeBytes {
     type Bytes: Sized;
     fn from_be_bytes(bytes: Self::Bytes) -> Self;
@@ -113,38 +197,64 @@ macro_rules! impl_from_be_bytes {
 impl_from_be_bytes!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
 
 impl<'c> Classfile<'c> {
+    /// Parses the class file in `buff`. Parsing failures are reported as
+    /// [`ClassfileError::Malformed`], carrying the offset where parsing stopped.
     pub fn new<'b>(buff: &'b [u8], arena: &'c Bump) -> Result<Classfile<'c>, ClassfileError>
     where
         'b: 'c,
     {
-        let mut reader = BufReader::new(buff);
+        Self::with_options(buff, arena, ParseOptions::default())
+    }
 
-        let magic = read::<u32>(&mut reader)?;
-        if magic != MAGIC {
-            return Err(ClassfileError::InvalidClassfile);
-        }
+    /// Same as [`Classfile::new`], parsing only what `options` asks for.
+    pub fn with_options<'b>(
+        buff: &'b [u8],
+        arena: &'c Bump,
+        options: ParseOptions,
+    ) -> Result<Classfile<'c>, ClassfileError>
+    where
+        'b: 'c,
+    {
+        let mut reader: BufReader<&'c [u8]> = BufReader::new(buff);
+        // bytes still in the inner slice or buffered by the reader were not consumed yet
+        let remaining = |reader: &BufReader<&[u8]>| reader.get_ref().len() + reader.buffer().len();
+
+        let classfile = Self::parse(&mut reader, arena, options).map_err(|source| {
+            ClassfileError::Malformed {
+                offset: buff.len() - remaining(&reader),
+                source: Box::new(source),
+            }
+        })?;
 
-        let minor = read::<u16>(&mut reader)?;
-        let major = read::<u16>(&mut reader)?;
-        if !Version::is_valid(major) {
-            return Err(ClassfileError::Version(major));
+        match remaining(&reader) {
+            trailing if options.strict_eof && trailing > 0 => {
+                Err(ClassfileError::TrailingBytes(trailing))
+            }
+            _ => Ok(classfile),
         }
-        let version = Version::new(major, minor);
-
-        let constant_pool = arena.alloc(ConstantPool::new(&mut reader, arena)?);
-        let access_flags = AccessFlags::from_bits_truncate(read::<u16>(&mut reader)?);
-        let this_class: u16 = read(&mut reader)?;
-        let super_class: u16 = read(&mut reader)?;
+    }
 
-        let interfaces_count = read::<u16>(&mut reader)? as usize;
+    fn parse(
+        reader: &mut BufReader<&'c [u8]>,
+        arena: &'c Bump,
+        options: ParseOptions,
+    ) -> Result<Self, ClassfileError> {
+        let version = read_header(reader)?;
+        let constant_pool = arena.alloc(ConstantPool::new(reader, arena)?);
+        let access_flags = AccessFlags::from_bits_truncate(read::<u16>(reader)?);
+        let this_class: PoolIndex = read(reader)?;
+        let super_class: PoolIndex = read(reader)?;
+
+        let interfaces_count = read::<u16>(reader)? as usize;
         let mut interfaces = Vec::with_capacity_in(interfaces_count, arena);
         for _ in (0..interfaces_count) {
-            interfaces.push(read::<u16>(&mut reader)?);
+            interfaces.push(read(reader)?);
         }
-        let interfaces: &'c [u16] = interfaces.into_bump_slice();
+        let interfaces: &'c [PoolIndex] = interfaces.into_bump_slice();
 
-        let fields = parse_fields(&mut reader, constant_pool, arena)?;
-        let methods = parse_methods(&mut reader, constant_pool, arena)?;
+        let fields = parse_fields(reader, constant_pool, arena, options)?;
+        let methods = parse_methods(reader, constant_pool, arena, options)?;
+        let attributes = get_attributes(reader, constant_pool, arena, options)?;
 
         Ok(Classfile {
             version,
@@ -155,6 +265,7 @@ impl<'c> Classfile<'c> {
             interfaces,
             fields,
             methods,
+            attributes,
         })
     }
 
@@ -172,29 +283,62 @@ impl<'c> Classfile<'c> {
         self.access_flags.contains(AccessFlags::ENUM)
     }
 
+    /// Whether `ACC_SUPER` is set, asking `invokespecial` to treat superclass methods specially.
+    /// Compilers have set it on every class since JDK 1.0.2.
+    pub fn is_super(&self) -> bool {
+        self.access_flags.contains(AccessFlags::SUPER)
+    }
+
     pub fn version(&self) -> (u16, u16) {
         (self.version.major, self.version.minor)
     }
 
+    /// Access flags of this class as the modifiers of its Java source declaration, e.g.
+    /// `public final class` or `public interface`.
+    pub fn class_flags_string(&self) -> String {
+        self.access_flags
+            .modifiers()
+            .collect::<std::vec::Vec<_>>()
+            .join(" ")
+    }
+
     pub fn class_name(&self) -> Option<&str> {
         self.constant_pool.get_classname(self.this_class).ok()
     }
 
+    /// Binary name of this class, e.g. `java.lang.Object`, as opposed to the internal one
+    /// returned by [`Classfile::class_name`].
+    pub fn class_name_binary(&self) -> Option<String> {
+        self.class_name().map(binary_name)
+    }
+
     pub fn super_class(&self) -> Option<&str> {
         self.constant_pool.get_classname(self.super_class).ok()
     }
 
-    pub fn field_names(&'c self, arena: &'c Bump) -> Result<Vec<&'c str>, ConstantPoolError> {
-        use self::constant_pool::ConstantPoolEntry;
+    /// Number of constant pool slots. Valid indexes for [`Classfile::constant`] range from `1`
+    /// to this length, with the slot after a `Long` or `Double` left unusable.
+    pub fn constant_pool_len(&self) -> usize {
+        self.constant_pool.len()
+    }
+
+    /// Resolves the constant pool entry at the 1-based `index`, if it's a usable slot.
+    pub fn constant(&self, index: u16) -> Option<PublicConstant<'c>> {
+        self.constant_pool.public_constant(index)
+    }
+
+    /// Renders the `FieldRef`, `MethodRef` or `InterfaceMethodRef` at `index` the way
+    /// disassemblers do, e.g. `java/lang/Object.<init>:()V`.
+    pub fn describe_ref(&self, index: u16) -> Option<String> {
+        let (owner, name, descriptor) = self.constant_pool.resolve_ref(index).ok()?;
+        Some(format!("{owner}.{name}:{descriptor}"))
+    }
 
+    pub fn field_names(&'c self, arena: &'c Bump) -> Result<Vec<&'c str>, ConstantPoolError> {
         let mut names = Vec::new_in(arena);
 
         for f in self.fields.iter() {
-            let name = self.constant_pool.get_with(f.name_index, |e| match e {
-                ConstantPoolEntry::Utf8(s) => Ok(*s),
-                _ => Err(ConstantPoolError::InvalidIndex(f.name_index)),
-            })?;
-            names.push(name);
+            names.push(self.constant_pool.get_utf8(f.name_index)?);
         }
 
         Ok(names)
@@ -204,22 +348,11 @@ impl<'c> Classfile<'c> {
         &'c self,
         arena: &'c Bump,
     ) -> Result<Vec<'c, (&'c str, &'c str)>, ClassfileError> {
-        use self::constant_pool::ConstantPoolEntry;
-
         let mut methods = Vec::new_in(arena);
 
         for m in self.methods.iter() {
-            let name = self.constant_pool.get_with(m.name_index, |e| match e {
-                ConstantPoolEntry::Utf8(s) => Ok(*s),
-                _ => Err(ConstantPoolError::InvalidIndex(m.name_index)),
-            })?;
-
-            let descriptor = self
-                .constant_pool
-                .get_with(m.descriptor_index, |e| match e {
-                    ConstantPoolEntry::Utf8(s) => Ok(*s),
-                    _ => Err(ConstantPoolError::InvalidIndex(m.descriptor_index)),
-                })?;
+            let name = self.constant_pool.get_utf8(m.name_index)?;
+            let descriptor = self.constant_pool.get_utf8(m.descriptor_index)?;
 
             methods.push((name, descriptor));
         }
@@ -227,6 +360,55 @@ impl<'c> Classfile<'c> {
         Ok(methods)
     }
 
+    /// Name and descriptor of every overload of the method `name` declared by this class, in
+    /// declaration order.
+    pub fn methods_by_name(
+        &'c self,
+        name: &str,
+        arena: &'c Bump,
+    ) -> Result<Vec<'c, (&'c str, &'c str)>, ClassfileError> {
+        let mut methods = Vec::new_in(arena);
+
+        for m in self.methods.iter() {
+            let method_name = self.constant_pool.get_utf8(m.name_index)?;
+            if method_name == name {
+                let descriptor = self.constant_pool.get_utf8(m.descriptor_index)?;
+                methods.push((method_name, descriptor));
+            }
+        }
+
+        Ok(methods)
+    }
+
+    /// Name, descriptor and bytecode of every method of this class, in declaration order.
+    /// Abstract and native methods are omitted, as they have no `Code` attribute.
+    pub fn all_method_code(
+        &'c self,
+        arena: &'c Bump,
+    ) -> Result<Vec<'c, MethodCode<'c>>, ClassfileError> {
+        let mut methods = Vec::new_in(arena);
+
+        for m in self.methods.iter() {
+            let Some(code) = m.code() else { continue };
+            let name = self.constant_pool.get_utf8(m.name_index)?;
+            let descriptor = self.constant_pool.get_utf8(m.descriptor_index)?;
+
+            methods.push((name, descriptor, code));
+        }
+
+        Ok(methods)
+    }
+
+    pub fn interface_count(&self) -> usize {
+        self.interfaces.len()
+    }
+
+    /// Constant pool indexes of the `Class` entries of the direct superinterfaces, in declaration
+    /// order, each resolvable through [`Classfile::constant`].
+    pub fn interface_indices(&self) -> impl ExactSizeIterator<Item = u16> + '_ {
+        self.interfaces.iter().map(|index| index.0)
+    }
+
     pub fn interface_names<'a>(
         &self,
         arena: &'a bumpalo::Bump,
@@ -238,6 +420,406 @@ impl<'c> Classfile<'c> {
         }
         Ok(names.into_bump_slice())
     }
+
+    /// Resolves the entries of the `InnerClasses` attribute, describing every nested class this
+    /// class refers to, including itself when it is nested.
+    pub fn inner_classes(
+        &'c self,
+        arena: &'c Bump,
+    ) -> Result<Vec<'c, InnerClassInfo<'c>>, ClassfileError> {
+        let mut inner_classes = Vec::new_in(arena);
+        for attribute in self.attributes {
+            if let Attribute::InnerClasses { classes } = attribute {
+                for entry in classes.iter() {
+                    inner_classes.push(entry.resolve(self.constant_pool)?);
+                }
+            }
+        }
+
+        Ok(inner_classes)
+    }
+
+    /// Resolves the components of a record class, in declaration order. Classes that are not
+    /// records yield an empty list.
+    pub fn record_components(
+        &'c self,
+        arena: &'c Bump,
+    ) -> Result<Vec<'c, RecordComponent<'c>>, ClassfileError> {
+        let mut components = Vec::new_in(arena);
+        for attribute in self.attributes {
+            if let Attribute::Record {
+                components: entries,
+            } = attribute
+            {
+                for entry in entries.iter() {
+                    components.push(entry.resolve(self.constant_pool)?);
+                }
+            }
+        }
+
+        Ok(components)
+    }
+
+    /// Resolves the names of the classes allowed to extend or implement this sealed class.
+    /// Classes that are not sealed yield an empty list.
+    pub fn permitted_subclasses(
+        &'c self,
+        arena: &'c Bump,
+    ) -> Result<Vec<'c, &'c str>, ClassfileError> {
+        let mut names = Vec::new_in(arena);
+        for attribute in self.attributes {
+            if let Attribute::PermittedSubclasses { classes } = attribute {
+                for &idx in classes.iter() {
+                    names.push(self.constant_pool.get_classname(idx)?);
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Kinds of the attributes of the class itself, in the order the class file lists them.
+    /// Attributes not defined by JVMS are left out.
+    pub fn attributes(&self) -> impl Iterator<Item = AttributeKind> + '_ {
+        self.attributes.iter().filter_map(Attribute::kind)
+    }
+
+    /// Attributes of the class itself, as opposed to the ones of its fields and methods.
+    pub(in crate::classfile) fn class_attributes(&self) -> &'c [Attribute<'c>] {
+        self.attributes
+    }
+
+    /// Generic signature of the class, as recorded by its `Signature` attribute, e.g.
+    /// `<T:Ljava/lang/Object;>Ljava/lang/Object;`. Classes that declare no type parameters and
+    /// extend no parameterized types have none.
+    pub fn generic_signature(&self) -> Option<&str> {
+        signature(self.attributes, self.constant_pool)
+    }
+
+    /// Generic signature of the field `name`, e.g. `Ljava/util/List<Ljava/lang/String;>;`.
+    pub fn field_signature(&self, name: &str) -> Option<&str> {
+        let field = self
+            .fields
+            .iter()
+            .find(|f| self.constant_pool.get_utf8(f.name_index) == Ok(name))?;
+
+        signature(field.attributes, self.constant_pool)
+    }
+
+    /// Generic signature of the method `name` with the given erased `descriptor`.
+    pub fn method_signature(&self, name: &str, descriptor: &str) -> Option<&str> {
+        let method = self.find_method(name, descriptor)?;
+        signature(method.attributes, self.constant_pool)
+    }
+
+    /// Name of the source file this class was compiled from, as recorded by the `SourceFile`
+    /// attribute.
+    pub fn source_file(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::SourceFile { sourcefile_index } => {
+                    self.constant_pool.get_utf8(*sourcefile_index).ok()
+                }
+                _ => None,
+            })
+    }
+
+    /// Resolves the `EnclosingMethod` attribute of a local or anonymous class into the name of
+    /// the enclosing class and, unless the class is declared in an initializer, the name and
+    /// descriptor of the enclosing method.
+    pub fn enclosing_method(&self) -> Option<(String, Option<(String, String)>)> {
+        let (class_index, method_index) =
+            self.attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::EnclosingMethod {
+                        class_index,
+                        method_index,
+                    } => Some((*class_index, *method_index)),
+                    _ => None,
+                })?;
+
+        let class = self.constant_pool.get_classname(class_index).ok()?;
+        // a zero method index means the class is not enclosed by a specific method
+        let method = match self.constant_pool.get_optional(method_index).ok()? {
+            Some(_) => {
+                let (name, descriptor) = self
+                    .constant_pool
+                    .resolve_name_and_type(method_index)
+                    .ok()?;
+                Some((name.to_string(), descriptor.to_string()))
+            }
+            None => None,
+        };
+
+        Some((class.to_string(), method))
+    }
+
+    /// Name of the host of the nest this class belongs to, as recorded by the `NestHost`
+    /// attribute. A class without that attribute is the host of its own nest, so its own name
+    /// is returned instead.
+    pub fn nest_host(&self) -> Option<&str> {
+        let host = self
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::NestHost { host_class_index } => Some(*host_class_index),
+                _ => None,
+            })
+            .unwrap_or(self.this_class);
+
+        self.constant_pool.get_classname(host).ok()
+    }
+
+    /// Resolves the names of the classes that, as recorded by the `NestMembers` attribute of a
+    /// nest host, belong to its nest. Classes hosted by another class yield an empty list.
+    pub fn nest_members(&'c self, arena: &'c Bump) -> Result<Vec<'c, &'c str>, ClassfileError> {
+        let mut names = Vec::new_in(arena);
+        for attribute in self.attributes {
+            if let Attribute::NestMembers { classes } = attribute {
+                for &idx in classes.iter() {
+                    names.push(self.constant_pool.get_classname(idx)?);
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Names of the classes referenced by this class, in constant pool order and without
+    /// duplicates. Array classes are reported by their element class, and primitive arrays are
+    /// skipped. The class itself is not included.
+    pub fn dependencies(&'c self, arena: &'c Bump) -> Result<Vec<'c, &'c str>, ClassfileError> {
+        let this_class = self.constant_pool.get_classname(self.this_class)?;
+        let mut names = Vec::new_in(arena);
+
+        // member references point at `Class` entries, so those cover every referenced class
+        for idx in 1..=self.constant_pool.len() as u16 {
+            let Ok(ConstantPoolEntry::Class(name_index)) = self.constant_pool.get(idx) else {
+                continue;
+            };
+
+            let name = self.constant_pool.get_utf8(*name_index)?;
+            let name = match name.trim_start_matches('[') {
+                element if element.len() == name.len() => name,
+                element => match element.strip_prefix('L') {
+                    Some(class) => class.trim_end_matches(';'),
+                    None => continue,
+                },
+            };
+
+            if name != this_class && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Resolves the checked exceptions declared by the method `name` with the given `descriptor`,
+    /// i.e. its `throws` clause. Methods without an `Exceptions` attribute yield an empty list.
+    pub fn method_exceptions(
+        &'c self,
+        name: &str,
+        descriptor: &str,
+        arena: &'c Bump,
+    ) -> Result<Vec<'c, &'c str>, ClassfileError> {
+        let method = self
+            .find_method(name, descriptor)
+            .ok_or_else(|| ClassfileError::MethodNotFound(format!("{name}:{descriptor}")))?;
+
+        let mut exceptions = Vec::new_in(arena);
+        for attribute in method.attributes {
+            if let Attribute::Exceptions {
+                exception_index_table,
+            } = attribute
+            {
+                for &idx in exception_index_table.iter() {
+                    exceptions.push(self.constant_pool.get_classname(idx)?);
+                }
+            }
+        }
+
+        Ok(exceptions)
+    }
+
+    /// Entries of the constant pool in order, leaving out the unusable slots following `Long`
+    /// and `Double` entries.
+    pub(crate) fn constant_pool_entries(&self) -> impl Iterator<Item = ConstantPoolEntry<'c>> {
+        self.constant_pool.entries()
+    }
+
+    /// Exception table of the method `name` with the given `descriptor`, as
+    /// `(start_pc, end_pc, handler_pc, catch_type)` entries, where `catch_type` is `None` for
+    /// handlers catching everything, as `finally` blocks do.
+    pub(crate) fn exception_table(
+        &self,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<std::vec::Vec<ExceptionRange<'c>>, ClassfileError> {
+        let method = self
+            .find_method(name, descriptor)
+            .ok_or_else(|| ClassfileError::MethodNotFound(format!("{name}:{descriptor}")))?;
+
+        let mut table = std::vec::Vec::new();
+        for attribute in method.attributes {
+            if let Attribute::Code {
+                exception_table, ..
+            } = attribute
+            {
+                for entry in exception_table.iter() {
+                    let catch_type = match entry.catch_type {
+                        PoolIndex(0) => None,
+                        index => Some(self.constant_pool.get_classname(index)?),
+                    };
+                    table.push((entry.start_pc, entry.end_pc, entry.handler_pc, catch_type));
+                }
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Whether this class declares a method `name` with the given `descriptor`.
+    pub fn has_method(&self, name: &str, descriptor: &str) -> bool {
+        self.find_method(name, descriptor).is_some()
+    }
+
+    /// Decodes the flags of the method `name` with the given `descriptor`, if declared by this
+    /// class.
+    pub fn method_summary(&self, name: &str, descriptor: &str) -> Option<MethodSummary<'c>> {
+        let method = self.find_method(name, descriptor)?;
+        let descriptor = self.constant_pool.get_utf8(method.descriptor_index).ok()?;
+
+        Some(method.summary(descriptor))
+    }
+
+    /// Name and descriptor of the local variable in slot `index` of the method `name` with the
+    /// given `descriptor`, as declared by its `LocalVariableTable` at `pc`. Classes compiled
+    /// without debugging information (`javac -g`) have no such table.
+    pub fn local_variable(
+        &self,
+        name: &str,
+        descriptor: &str,
+        pc: u16,
+        index: u16,
+    ) -> Option<(&'c str, &'c str)> {
+        let entry = self
+            .find_method(name, descriptor)?
+            .local_variable(pc, index)?;
+
+        Some((
+            self.constant_pool.get_utf8(entry.name_index).ok()?,
+            self.constant_pool.get_utf8(entry.descriptor_index).ok()?,
+        ))
+    }
+
+    /// Finds the method `name` with the given `descriptor` declared by this class, without
+    /// allocating.
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&Method<'c>> {
+        self.methods.iter().find(|m| {
+            self.constant_pool.get_utf8(m.name_index) == Ok(name)
+                && self.constant_pool.get_utf8(m.descriptor_index) == Ok(descriptor)
+        })
+    }
+}
+
+/// Renders the class roughly the way `javap` does. Entries that can't be resolved through the
+/// constant pool are shown by their pool index instead.
+impl<'c> Display for Classfile<'c> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let pool = self.constant_pool;
+        let class_name = |index: PoolIndex| match pool.get_classname(index) {
+            Ok(name) => binary_name(name),
+            Err(_) => index.to_string(),
+        };
+        let utf8 = |index: PoolIndex| pool.get_utf8(index).unwrap_or("?");
+
+        writeln!(
+            f,
+            "// class version {}.{}",
+            self.version.major, self.version.minor
+        )?;
+        write_modifiers(f, self.access_flags.modifiers())?;
+        write!(f, "{}", class_name(self.this_class))?;
+
+        if self.super_class != PoolIndex(0) {
+            write!(f, " extends {}", class_name(self.super_class))?;
+        }
+        for (idx, &interface) in self.interfaces.iter().enumerate() {
+            let separator = if idx == 0 { " implements " } else { ", " };
+            write!(f, "{separator}{}", class_name(interface))?;
+        }
+        writeln!(f, " {{")?;
+
+        for field in self.fields {
+            write!(f, "  ")?;
+            write_modifiers(f, field.access_flags.modifiers())?;
+            writeln!(
+                f,
+                "{}: {}",
+                utf8(field.name_index),
+                utf8(field.descriptor_index)
+            )?;
+        }
+        if !self.fields.is_empty() && !self.methods.is_empty() {
+            writeln!(f)?;
+        }
+        for method in self.methods {
+            write!(f, "  ")?;
+            write_modifiers(f, method.access_flags.modifiers())?;
+            writeln!(
+                f,
+                "{}{}",
+                utf8(method.name_index),
+                utf8(method.descriptor_index)
+            )?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+/// Resolves the `Signature` attribute among `attributes`, if any.
+fn signature<'c>(
+    attributes: &[Attribute<'c>],
+    constant_pool: &'c ConstantPool<'c>,
+) -> Option<&'c str> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Signature { signature_index } => constant_pool.get_utf8(*signature_index).ok(),
+        _ => None,
+    })
+}
+
+fn write_modifiers<'m>(
+    f: &mut Formatter<'_>,
+    mut modifiers: impl Iterator<Item = &'m str>,
+) -> fmt::Result {
+    modifiers.try_for_each(|modifier| write!(f, "{modifier} "))
+}
+
+/// Reads the version of the class file in `bytes`, looking at nothing past its header. This is
+/// much cheaper than [`Classfile::new`] when only the version matters, e.g. to sort classes by
+/// Java release.
+pub fn classfile_version(mut bytes: &[u8]) -> Result<Version, ClassfileError> {
+    read_header(&mut bytes)
+}
+
+/// Reads the magic number and the version, checking both are supported.
+fn read_header(reader: &mut impl Read) -> Result<Version, ClassfileError> {
+    let magic = read::<u32>(reader)?;
+    if magic != MAGIC {
+        return Err(ClassfileError::InvalidClassfile);
+    }
+
+    let minor = read::<u16>(reader)?;
+    let major = read::<u16>(reader)?;
+    if !Version::is_valid(major) {
+        return Err(ClassfileError::Version(major));
+    }
+
+    Ok(Version::new(major, minor))
 }
 
 impl Version {
@@ -245,6 +827,14 @@ impl Version {
         Self { major, minor }
     }
 
+    pub fn major(&self) -> u16 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u16 {
+        self.minor
+    }
+
     fn is_valid(major: u16) -> bool {
         (45..=68).contains(&major)
     }
@@ -260,3 +850,103 @@ where
 
     Ok(T::from_be_bytes(bytes))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_attributes_are_retained() -> Result<(), ClassfileError> {
+        let arena = Bump::new();
+        let bytes = std::fs::read("./tests/sources/Person.class")?;
+        let classfile = Classfile::new(&bytes, &arena)?;
+
+        let attributes = classfile.class_attributes();
+        assert!(!attributes.is_empty());
+        assert!(
+            attributes
+                .iter()
+                .any(|attribute| matches!(attribute, Attribute::SourceFile { .. }))
+        );
+
+        // none of the method attributes leak into the class ones
+        assert!(
+            !attributes
+                .iter()
+                .any(|attribute| matches!(attribute, Attribute::Code { .. }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn annotations_of_a_large_class() -> Result<(), ClassfileError> {
+        // this is the class parsed by the `parse` benchmark. Annotation bodies used to be read
+        // through a nested `BufReader`, allocating a fresh buffer for every attribute, and each
+        // `Utf8` constant through its own vector; both now read from memory already at hand
+        let arena = Bump::new();
+        let bytes = std::fs::read("./tests/sources/Catalogue.class")?;
+        let classfile = Classfile::new(&bytes, &arena)?;
+        assert_eq!(classfile.methods.len(), 25);
+
+        let annotated: std::vec::Vec<_> = classfile
+            .methods
+            .iter()
+            .flat_map(|method| method.attributes)
+            .filter_map(|attribute| match attribute {
+                Attribute::RuntimeVisibleAnnotations { annotations, bytes } => {
+                    Some((annotations.len(), bytes.len()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // every third method is `@Deprecated(since = .., forRemoval = ..)`
+        assert_eq!(annotated.len(), 8);
+        // a single annotation of two `const_value_index` elements
+        assert!(
+            annotated
+                .iter()
+                .all(|&lengths| lengths == (1, 2 + 4 + 2 * 5))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_method_ref() -> Result<(), ClassfileError> {
+        let arena = Bump::new();
+        let bytes = std::fs::read("./tests/sources/Person.class")?;
+        let classfile = Classfile::new(&bytes, &arena)?;
+        let pool = classfile.constant_pool;
+
+        let method_ref = (1..=pool.len() as u16)
+            .find(|&idx| matches!(pool.get(idx), Ok(ConstantPoolEntry::MethodRef(..))))
+            .unwrap();
+        // the call to the implicit `super()` in the constructor
+        assert_eq!(
+            pool.resolve_ref(method_ref)?,
+            ("java/lang/Object", "<init>", "()V")
+        );
+
+        let ConstantPoolEntry::MethodRef(_, name_and_type) = pool.get(method_ref)? else {
+            unreachable!()
+        };
+        assert_eq!(
+            pool.resolve_name_and_type(*name_and_type)?,
+            ("<init>", "()V")
+        );
+
+        // neither resolves entries of other kinds
+        assert_eq!(
+            pool.resolve_name_and_type(method_ref),
+            Err(ConstantPoolError::InvalidIndex(method_ref))
+        );
+        assert_eq!(
+            pool.resolve_ref(classfile.this_class),
+            Err(ConstantPoolError::InvalidIndex(classfile.this_class.0))
+        );
+
+        Ok(())
+    }
+}
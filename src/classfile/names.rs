@@ -0,0 +1,36 @@
+//! Conversions from the internal forms of names and descriptors stored in class files, e.g.
+//! `java/lang/Object` or `[Ljava/lang/String;`, to the forms found in Java source code.
+
+/// Binary name of the class with the given internal name, e.g. `java.lang.Object` for
+/// `java/lang/Object`. Nested classes keep their `$`, as in `java.util.Map$Entry`.
+pub fn binary_name(internal_name: &str) -> String {
+    internal_name.replace('/', ".")
+}
+
+/// Source form of the type described by the field `descriptor`, e.g. `int[]` for `[I` or
+/// `java.lang.String` for `Ljava/lang/String;`. Returns `None` if the descriptor is malformed.
+pub fn source_type_name(descriptor: &str) -> Option<String> {
+    let element = descriptor.trim_start_matches('[');
+    let dimensions = descriptor.len() - element.len();
+
+    let name = match element {
+        "B" => "byte".to_string(),
+        "C" => "char".to_string(),
+        "D" => "double".to_string(),
+        "F" => "float".to_string(),
+        "I" => "int".to_string(),
+        "J" => "long".to_string(),
+        "S" => "short".to_string(),
+        "Z" => "boolean".to_string(),
+        "V" if dimensions == 0 => "void".to_string(),
+        _ => {
+            let class = element.strip_prefix('L')?.strip_suffix(';')?;
+            if class.is_empty() || class.contains(';') {
+                return None;
+            }
+            binary_name(class)
+        }
+    };
+
+    Some(name + &"[]".repeat(dimensions))
+}
@@ -0,0 +1,214 @@
+//! Arena-free representation of a parsed class.
+//! A [`Classfile`] borrows everything from the arena it was parsed into, so it can't outlive it.
+//! [`OwnedClassfile`] copies the parts needed to keep a class around, e.g. in a registry.
+
+use super::{
+    AccessFlags, Classfile, ClassfileError, FieldFlags, MethodFlags, attributes::Attribute,
+};
+use std::{any::Any, num::NonZeroUsize, panic, path::Path, thread};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedClassfile {
+    version: (u16, u16),
+    access_flags: AccessFlags,
+    name: String,
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    pub fields: Vec<OwnedField>,
+    pub methods: Vec<OwnedMethod>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OwnedField {
+    pub name: String,
+    pub descriptor: String,
+    pub flags: FieldFlags,
+    /// Constant pool index of the field `ConstantValue` attribute, which holds the initial value
+    /// of constant static fields.
+    pub constant_value: Option<u16>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OwnedMethod {
+    pub name: String,
+    pub descriptor: String,
+    pub flags: MethodFlags,
+    /// The method `Code` attribute; abstract and native methods have none.
+    pub code: Option<OwnedCode>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OwnedCode {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub bytecode: Vec<u8>,
+}
+
+impl<'c> Classfile<'c> {
+    /// Deep-copies this class out of its arena.
+    pub fn to_owned(&self) -> Result<OwnedClassfile, ClassfileError> {
+        let pool = self.constant_pool;
+
+        let interfaces = self
+            .interfaces
+            .iter()
+            .map(|&idx| pool.get_classname(idx).map(String::from))
+            .collect::<Result<_, _>>()?;
+
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| {
+                let constant_value =
+                    field
+                        .attributes
+                        .iter()
+                        .find_map(|attribute| match attribute {
+                            Attribute::ConstantValue {
+                                constantvalue_index,
+                            } => Some(constantvalue_index.0),
+                            _ => None,
+                        });
+
+                Ok(OwnedField {
+                    name: pool.get_utf8(field.name_index)?.to_string(),
+                    descriptor: pool.get_utf8(field.descriptor_index)?.to_string(),
+                    flags: field.access_flags,
+                    constant_value,
+                })
+            })
+            .collect::<Result<_, ClassfileError>>()?;
+
+        let methods = self
+            .methods
+            .iter()
+            .map(|method| {
+                let code = method
+                    .attributes
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::Code {
+                            max_stack,
+                            max_locals,
+                            code,
+                            ..
+                        } => Some(OwnedCode {
+                            max_stack: *max_stack,
+                            max_locals: *max_locals,
+                            bytecode: code.to_vec(),
+                        }),
+                        _ => None,
+                    });
+
+                Ok(OwnedMethod {
+                    name: pool.get_utf8(method.name_index)?.to_string(),
+                    descriptor: pool.get_utf8(method.descriptor_index)?.to_string(),
+                    flags: method.access_flags,
+                    code,
+                })
+            })
+            .collect::<Result<_, ClassfileError>>()?;
+
+        Ok(OwnedClassfile {
+            version: self.version(),
+            access_flags: self.access_flags,
+            name: pool.get_classname(self.this_class)?.to_string(),
+            super_class: self.super_class().map(String::from),
+            interfaces,
+            fields,
+            methods,
+        })
+    }
+}
+
+impl OwnedClassfile {
+    pub fn is_public(&self) -> bool {
+        self.access_flags.contains(AccessFlags::PUBLIC)
+    }
+    pub fn is_final(&self) -> bool {
+        self.access_flags.contains(AccessFlags::FINAL)
+    }
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags.contains(AccessFlags::ABSTRACT)
+    }
+
+    pub fn is_enum(&self) -> bool {
+        self.access_flags.contains(AccessFlags::ENUM)
+    }
+
+    pub fn is_super(&self) -> bool {
+        self.access_flags.contains(AccessFlags::SUPER)
+    }
+
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn super_class(&self) -> Option<&str> {
+        self.super_class.as_deref()
+    }
+
+    pub fn interface_names(&self) -> &[String] {
+        &self.interfaces
+    }
+
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&OwnedMethod> {
+        self.methods
+            .iter()
+            .find(|m| m.name == name && m.descriptor == descriptor)
+    }
+}
+
+/// Parses the class files at `paths` in parallel, each into an arena of its own, so the results
+/// are owned and come back in the same order as `paths`.
+pub fn parse_many<P: AsRef<Path> + Sync>(
+    paths: &[P],
+) -> Vec<Result<OwnedClassfile, ClassfileError>> {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = paths.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| chunk.iter().map(parse_owned).collect::<Vec<_>>()))
+            .collect();
+
+        workers
+            .into_iter()
+            .zip(paths.chunks(chunk_size))
+            .flat_map(|(worker, chunk)| {
+                worker.join().unwrap_or_else(|payload| {
+                    let message = panic_message(payload.as_ref());
+                    let failed = || Err(ClassfileError::Panicked(message.clone()));
+                    chunk.iter().map(|_| failed()).collect()
+                })
+            })
+            .collect()
+    })
+}
+
+/// Parses a single class file, turning a panic in the parser into an error for this path alone,
+/// so the rest of its chunk still gets parsed.
+fn parse_owned(path: impl AsRef<Path>) -> Result<OwnedClassfile, ClassfileError> {
+    let bytes = std::fs::read(path)?;
+
+    panic::catch_unwind(|| {
+        let arena = bumpalo::Bump::new();
+        Classfile::new(&bytes, &arena)?.to_owned()
+    })
+    .unwrap_or_else(|payload| Err(ClassfileError::Panicked(panic_message(payload.as_ref()))))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "unknown panic".to_string()),
+    }
+}
@@ -0,0 +1,135 @@
+//! Static inspection of method bytecode, without executing nor verifying it.
+
+pub use crate::vm::interpreter::instructions::opcode::Opcode;
+
+use Opcode::*;
+use std::collections::HashMap;
+
+/// Counts how many times every opcode appears in `code`, skipping over the operands of each
+/// instruction so they are never mistaken for opcodes. Counting stops at the first truncated
+/// instruction.
+pub fn analyze_opcodes(code: &[u8]) -> HashMap<Opcode, usize> {
+    let mut counts = HashMap::new();
+    let mut pc = 0;
+
+    while pc < code.len() {
+        let Some(length) = instruction_length(code, pc) else {
+            break;
+        };
+
+        *counts.entry(Opcode::from(code[pc])).or_default() += 1;
+        pc += length;
+    }
+
+    counts
+}
+
+/// Length in bytes, operands included, of the instruction at `pc`, or `None` if `code` ends
+/// before the instruction does.
+fn instruction_length(code: &[u8], pc: usize) -> Option<usize> {
+    let int = |at: usize| Some(i32::from_be_bytes(code.get(at..at + 4)?.try_into().ok()?));
+
+    let length = match Opcode::from(code[pc]) {
+        BIPUSH | LDC | ILOAD | LLOAD | FLOAD | DLOAD | ALOAD | ISTORE | LSTORE | FSTORE
+        | DSTORE | ASTORE | RET | NEWARRAY => 2,
+
+        SIPUSH | LDC_W | LDC2_W | IINC | IFEQ | IFNE | IFLT | IFGE | IFGT | IFLE | IF_ICMPEQ
+        | IF_ICMPNE | IF_ICMPLT | IF_ICMPGE | IF_ICMPGT | IF_ICMPLE | IF_ACMPEQ | IF_ACMPNE
+        | GOTO | JSR | GETSTATIC | PUTSTATIC | GETFIELD | PUTFIELD | INVOKEVIRTUAL
+        | INVOKESPECIAL | INVOKESTATIC | NEW | ANEWARRAY | CHECKCAST | INSTANCEOF | IFNULL
+        | IFNONNULL => 3,
+
+        MULTIANEWARRAY => 4,
+        INVOKEINTERFACE | INVOKEDYNAMIC | GOTO_W | JSR_W => 5,
+
+        WIDE => match Opcode::from(*code.get(pc + 1)?) {
+            IINC => 6,
+            _ => 4,
+        },
+
+        TABLESWITCH | LOOKUPSWITCH => {
+            // operands are 4-byte aligned relative to the start of the code
+            let operands = (pc + 4) & !3;
+            let end = match Opcode::from(code[pc]) {
+                TABLESWITCH => {
+                    let count = (int(operands + 8)? as i64 - int(operands + 4)? as i64 + 1).max(0);
+                    operands + 12 + count as usize * 4
+                }
+                _ => operands + 8 + int(operands + 4)?.max(0) as usize * 8,
+            };
+
+            end - pc
+        }
+
+        _ => 1,
+    };
+
+    (pc + length <= code.len()).then_some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::{Classfile, ClassfileError};
+    use bumpalo::Bump;
+
+    #[test]
+    fn person_constructor_opcodes() -> Result<(), ClassfileError> {
+        let arena = Bump::new();
+        let bytes = std::fs::read("./tests/sources/Person.class")?;
+        let person = Classfile::new(&bytes, &arena)?.to_owned()?;
+        let constructor = person
+            .find_method("<init>", "(Ljava/lang/String;I)V")
+            .and_then(|method| method.code.as_ref())
+            .unwrap();
+
+        // aload_0; invokespecial #1; return
+        let counts = analyze_opcodes(&constructor.bytecode);
+        assert_eq!(counts.get(&ALOAD_0), Some(&1));
+        assert_eq!(counts.get(&RETURN), Some(&1));
+        assert_eq!(counts.get(&INVOKESPECIAL), Some(&1));
+        // the `#1` operand of `invokespecial` isn't an `aconst_null`
+        assert_eq!(counts.get(&ACONST_NULL), None);
+        assert_eq!(counts.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn switch_operands_are_skipped() {
+        // iconst_0; tableswitch (padding, default, low = 0, high = 1, two offsets); return
+        let code = [
+            ICONST_0 as u8,
+            TABLESWITCH as u8,
+            0,
+            0,
+            0,
+            0,
+            0,
+            27,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            1,
+            0,
+            0,
+            0,
+            27,
+            0,
+            0,
+            0,
+            27,
+            RETURN as u8,
+        ];
+
+        let counts = analyze_opcodes(&code);
+        assert_eq!(counts.get(&ICONST_0), Some(&1));
+        assert_eq!(counts.get(&TABLESWITCH), Some(&1));
+        assert_eq!(counts.get(&RETURN), Some(&1));
+        assert_eq!(counts.get(&NOP), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! Propagation of Java exceptions through the frames of the interpreter.
+
+use crate::vm::{
+    Result, VmException,
+    interpreter::stack::StackFrames,
+    runtime::heap::{Instance, with_mut_heap},
+};
+use tracing::trace;
+
+/// Throws `exception` from the instruction at `pc` of the current frame, unwinding the frames up
+/// to the first one with a handler for it. Fails with the exception itself if none catches it.
+pub(super) fn throw(frames: &mut StackFrames, pc: usize, exception: VmException) -> Result<()> {
+    let instance = Instance::without_fields(&exception.class);
+    let reference = with_mut_heap(|heap| heap.allocate_exception(instance));
+
+    throw_object(frames, pc, reference, exception)
+}
+
+/// Same as [`throw`], for the already allocated exception object at `reference`, as `athrow`
+/// does, so handlers receive that very object.
+pub(in crate::vm::interpreter) fn throw_object(
+    frames: &mut StackFrames,
+    pc: usize,
+    reference: i32,
+    exception: VmException,
+) -> Result<()> {
+    // the instruction may have moved past its operands before failing
+    if let Some(frame) = frames.last_mut() {
+        frame.pc = pc;
+    }
+
+    while let Some(frame) = frames.last_mut() {
+        if let Some(handler_pc) = frame.find_handler(&exception.class)? {
+            frame.enter_handler(handler_pc, reference)?;
+
+            trace!(
+                "{exception} caught by {}@{handler_pc}",
+                frame.current_classname
+            );
+            return Ok(());
+        }
+
+        frames.pop();
+    }
+
+    Err(exception.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmError,
+        interpreter::{StackFrame, execute, instructions::opcode::Opcode::*, run},
+        runtime::{
+            constant_pool::{Constant, RuntimeConstantPool},
+            method_area::{Class, ExceptionHandler, Method, MethodArea, with_method_area},
+        },
+    };
+    use std::sync::Arc;
+
+    /// `return ((int[]) null)[0];`
+    const DEREFERENCE_NULL: [u8; 4] = [
+        ACONST_NULL as u8,
+        ICONST_0 as u8,
+        IALOAD as u8,
+        IRETURN as u8,
+    ];
+
+    fn handler(start_pc: u16, end_pc: u16, handler_pc: u16, catch_type: &str) -> ExceptionHandler {
+        ExceptionHandler {
+            start_pc,
+            end_pc,
+            handler_pc,
+            catch_type: Some(catch_type.to_string()),
+        }
+    }
+
+    #[test]
+    fn null_dereference_is_caught() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        // try { return ((int[]) null)[0]; } catch (NullPointerException e) { return 5; }
+        let mut code = DEREFERENCE_NULL.to_vec();
+        code.extend([POP as u8, ICONST_5 as u8, IRETURN as u8]);
+
+        let handlers = [handler(0, 4, 4, "java/lang/NullPointerException")];
+        let frame =
+            StackFrame::from_bytecode(&code, 0, 2).with_exception_handlers(Arc::from(handlers));
+
+        assert_eq!(execute(frame)?, [5]);
+        Ok(())
+    }
+
+    #[test]
+    fn exception_unwinds_to_the_caller() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Dereference".into()),
+                Constant::Class(1),
+                Constant::Utf8("get".into()),
+                Constant::Utf8("()I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+            ],
+            vec![],
+        );
+        let class = Class::with_classname("Dereference")
+            .with_constant_pool(pool)
+            .with_method(Method::new(
+                "Dereference",
+                "get:()I",
+                2,
+                0,
+                &DEREFERENCE_NULL,
+            ));
+        with_method_area(|area| area.insert(class));
+
+        // try { return get(); } catch (RuntimeException e) { return -1; }
+        let code = [
+            INVOKESTATIC as u8,
+            0,
+            6,
+            IRETURN as u8,
+            POP as u8,
+            ICONST_M1 as u8,
+            IRETURN as u8,
+        ];
+        let handlers = [handler(0, 4, 4, "java/lang/RuntimeException")];
+        let frame = StackFrame::new(0, 1, Arc::from(code), Arc::from("Dereference"))
+            .with_exception_handlers(Arc::from(handlers));
+
+        assert_eq!(execute(frame)?, [-1]);
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_memory_is_caught() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        // try { ... } catch (OutOfMemoryError e) { return 5; }
+        let code = [NOP as u8, POP as u8, ICONST_5 as u8, IRETURN as u8];
+        let handlers = [handler(0, 1, 1, "java/lang/OutOfMemoryError")];
+        let frame =
+            StackFrame::from_bytecode(&code, 0, 1).with_exception_handlers(Arc::from(handlers));
+        let mut frames = StackFrames::from(vec![frame]);
+
+        let error = VmException::new("java/lang/OutOfMemoryError", "Java heap space");
+        throw(&mut frames, 0, error)?;
+        assert_eq!(run(frames, None, None)?, [5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn uncaught_exception_is_returned() {
+        let _ = MethodArea::initialise(".");
+        let handlers = [handler(0, 4, 0, "java/lang/ClassCastException")];
+        let frame = StackFrame::from_bytecode(&DEREFERENCE_NULL, 0, 2)
+            .with_exception_handlers(Arc::from(handlers));
+
+        assert!(matches!(
+            execute(frame),
+            Err(VmError::Exception(exception))
+                if exception.class == "java/lang/NullPointerException"
+        ));
+    }
+}
@@ -1,30 +1,64 @@
 use crate::vm::{
     Result,
-    interpreter::{ValueRef, stack::Value},
+    interpreter::{
+        ArithmeticMode, StackFrame, ValueRef, native::natives, stack::StackFrames, stack::Value,
+        trace::ExecutionTrace,
+    },
     runtime::{heap::with_mut_heap, method_area::with_method_area},
 };
+use std::sync::Arc;
 
 // for as it now, executor is not going to hold any state
 // but this may change in the future, for now it's going to be a
 // more namespace delimiter
-struct Executor {}
+pub(super) struct Executor {}
 
 impl Executor {
     const INITIALISE_METHOD: &str = "<init>:()V";
 
-    fn execute<'a>(classname: &str, method_name: &str, args: &[Value]) -> Result<Vec<ValueRef>> {
+    /// Runs `method_name` of `classname` with `args`, giving up after `max_instructions`, if any,
+    /// recording the executed instructions into `trace`, if any, and handling integer overflows
+    /// according to `mode`.
+    pub fn execute<'a>(
+        classname: &str,
+        method_name: &str,
+        args: &[Value],
+        max_instructions: Option<usize>,
+        trace: Option<&mut ExecutionTrace>,
+        mode: ArithmeticMode,
+    ) -> Result<Vec<ValueRef>> {
         let class = with_method_area(|area| area.get(classname))?;
         let method = class.get_method(method_name)?;
+
+        if method.is_native() {
+            // natives invoked from the host have no calling frame, so they get a detached one
+            let mut frame = StackFrame::new(0, 0, Arc::default(), Arc::from(classname));
+            let value = natives().invoke(classname, method_name, &mut frame, args)?;
+
+            return Ok(value.map(|v| v.to_slots().to_vec()).unwrap_or_default());
+        }
+
         let mut frame = method.new_frame()?;
-        // TODO: set args
+        let slots = args.iter().flat_map(|arg| arg.to_slots());
+        for (index, slot) in slots.enumerate() {
+            frame.store_variable(index, slot)?;
+        }
 
-        super::execute(frame)
+        let frames = StackFrames::from(vec![frame]).with_arithmetic_mode(mode);
+        super::run(frames, trace, max_instructions)
     }
 
     pub fn default_constructor(classname: &str) -> Result<ValueRef> {
         let instance = with_method_area(|area| area.create_instance_with_default(classname))?;
-        let instance_ref = with_mut_heap(|heap| heap.allocate_instance(instance));
-        Self::execute(classname, Self::INITIALISE_METHOD, &[instance_ref.into()])?;
+        let instance_ref = with_mut_heap(|heap| heap.allocate_instance(instance))?;
+        Self::execute(
+            classname,
+            Self::INITIALISE_METHOD,
+            &[instance_ref.into()],
+            None,
+            None,
+            ArithmeticMode::default(),
+        )?;
 
         Ok(instance_ref)
     }
@@ -17,26 +17,204 @@ pub(in crate::vm::interpreter::instructions) fn process(
     match opcode {
         LCMP => frame.compare::<i64>(0, opcode)?,
         FCMPL => frame.compare::<f32>(-1, opcode)?,
-        DCMPL => frame.compare::<f32>(-1, opcode)?,
+        DCMPL => frame.compare::<f64>(-1, opcode)?,
         DCMPG => frame.compare::<f64>(1, opcode)?,
         FCMPG => frame.compare::<f32>(1, opcode)?,
 
-        IFEQ => frame.unary_branch(|a| a == 0, opcode),
-        IFNE => frame.unary_branch(|a| a != 0, opcode),
-        IFLT => frame.unary_branch(|a| a < 0, opcode),
-        IFGT => frame.unary_branch(|a| a > 0, opcode),
-        IFLE => frame.unary_branch(|a| a <= 0, opcode),
-        IFGE => frame.unary_branch(|a| a >= 0, opcode),
+        IFEQ => frame.unary_branch(|a| a == 0, opcode)?,
+        IFNE => frame.unary_branch(|a| a != 0, opcode)?,
+        IFLT => frame.unary_branch(|a| a < 0, opcode)?,
+        IFGT => frame.unary_branch(|a| a > 0, opcode)?,
+        IFLE => frame.unary_branch(|a| a <= 0, opcode)?,
+        IFGE => frame.unary_branch(|a| a >= 0, opcode)?,
 
-        IF_ICMPEQ | IF_ACMPEQ => frame.binary_branch(|a, b| a == b, opcode),
-        IF_ICMPNE | IF_ACMPNE => frame.binary_branch(|a, b| a != b, opcode),
-        IF_ICMPLT => frame.binary_branch(|a, b| a < b, opcode),
-        IF_ICMPLE => frame.binary_branch(|a, b| a <= b, opcode),
-        IF_ICMPGT => frame.binary_branch(|a, b| a > b, opcode),
-        IF_ICMPGE => frame.binary_branch(|a, b| a >= b, opcode),
+        IF_ICMPEQ => frame.binary_branch(|a, b| a == b, opcode)?,
+        IF_ICMPNE => frame.binary_branch(|a, b| a != b, opcode)?,
+        IF_ICMPLT => frame.binary_branch(|a, b| a < b, opcode)?,
+        IF_ICMPLE => frame.binary_branch(|a, b| a <= b, opcode)?,
+        IF_ICMPGT => frame.binary_branch(|a, b| a > b, opcode)?,
+        IF_ICMPGE => frame.binary_branch(|a, b| a >= b, opcode)?,
+
+        IF_ACMPEQ => frame.reference_branch(true, opcode)?,
+        IF_ACMPNE => frame.reference_branch(false, opcode)?,
 
         _ => unreachable!("Tried to perform comparation with {code} code"),
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmError,
+        interpreter::{InterpreterError, StackFrame, execute},
+        runtime::heap::{Instance, with_mut_heap},
+    };
+
+    /// Runs `opcode` over `a` and `b`, checking that only the two doubles were consumed.
+    fn compare_doubles(opcode: Opcode, a: f64, b: f64) -> i32 {
+        let frame = StackFrame::from_bytecode(&[opcode as u8], 0, 6);
+        let mut frames = StackFrames::from(vec![frame]);
+
+        let frame = frames.last_mut().unwrap();
+        frame.push(7i32).unwrap();
+        frame.push(a).unwrap();
+        frame.push(b).unwrap();
+
+        process(opcode as u8, &mut frames).unwrap();
+
+        let frame = frames.last_mut().unwrap();
+        let result = frame.pop::<i32>().unwrap();
+        assert_eq!(frame.pop::<i32>(), Some(7));
+        assert_eq!(frame.pop::<i32>(), None);
+
+        result
+    }
+
+    #[test]
+    fn double_compare_nan_ordering() {
+        assert_eq!(compare_doubles(DCMPG, f64::NAN, 1.0), 1);
+        assert_eq!(compare_doubles(DCMPG, 1.0, f64::NAN), 1);
+        assert_eq!(compare_doubles(DCMPL, f64::NAN, 1.0), -1);
+        assert_eq!(compare_doubles(DCMPL, 1.0, f64::NAN), -1);
+    }
+
+    #[test]
+    fn double_compare_agrees_without_nan() {
+        // values that only differ beyond `f32` precision
+        let (small, large) = (1.0, 1.0 + f64::EPSILON);
+
+        for opcode in [DCMPG, DCMPL] {
+            assert_eq!(compare_doubles(opcode, small, large), -1);
+            assert_eq!(compare_doubles(opcode, large, small), 1);
+            assert_eq!(compare_doubles(opcode, large, large), 0);
+            assert_eq!(compare_doubles(opcode, -0.0, 0.0), 0);
+        }
+    }
+
+    /// Runs `opcode` over `a` and `b`, checking that only the two floats were consumed.
+    fn compare_floats(opcode: Opcode, a: f32, b: f32) -> i32 {
+        let frame = StackFrame::from_bytecode(&[opcode as u8], 0, 3);
+        let mut frames = StackFrames::from(vec![frame]);
+
+        let frame = frames.last_mut().unwrap();
+        frame.push(7i32).unwrap();
+        frame.push(a).unwrap();
+        frame.push(b).unwrap();
+
+        process(opcode as u8, &mut frames).unwrap();
+
+        let frame = frames.last_mut().unwrap();
+        let result = frame.pop::<i32>().unwrap();
+        assert_eq!(frame.pop::<i32>(), Some(7));
+        assert_eq!(frame.pop::<i32>(), None);
+
+        result
+    }
+
+    #[test]
+    fn float_compare_matrix() {
+        let nan = f32::NAN;
+        // (a, b, FCMPL result, FCMPG result)
+        let cases = [
+            (nan, 1.0, -1, 1),
+            (1.0, nan, -1, 1),
+            (nan, nan, -1, 1),
+            (nan, f32::INFINITY, -1, 1),
+            (-0.0, 0.0, 0, 0),
+            (0.0, -0.0, 0, 0),
+            (1.0, 2.0, -1, -1),
+            (2.0, 1.0, 1, 1),
+            (1.5, 1.5, 0, 0),
+            (f32::NEG_INFINITY, f32::MIN, -1, -1),
+            (f32::INFINITY, f32::INFINITY, 0, 0),
+        ];
+
+        for (a, b, fcmpl, fcmpg) in cases {
+            assert_eq!(compare_floats(FCMPL, a, b), fcmpl, "FCMPL {a} {b}");
+            assert_eq!(compare_floats(FCMPG, a, b), fcmpg, "FCMPG {a} {b}");
+        }
+    }
+
+    #[test]
+    fn backward_branch() -> Result<()> {
+        // IFEQ -5 and IF_ICMPLT -5 at pc 5, whose offset bytes both have the top bit set
+        for (opcode, operands) in [(IFEQ, &[0][..]), (IF_ICMPLT, &[1, 2][..])] {
+            let code = [
+                NOP as u8,
+                NOP as u8,
+                NOP as u8,
+                NOP as u8,
+                NOP as u8,
+                opcode as u8,
+                0xFF,
+                0xFB,
+            ];
+            let mut frame = StackFrame::from_bytecode(&code, 0, 2);
+            frame.pc = 5;
+            for &operand in operands {
+                frame.push(operand)?;
+            }
+
+            let mut frames = StackFrames::from(vec![frame]);
+            process(opcode as u8, &mut frames)?;
+            assert_eq!(frames.last_mut().unwrap().pc, 0);
+        }
+
+        Ok(())
+    }
+
+    /// Runs `if (a == b) return 1; return 0;`, comparing with `opcode`.
+    fn compare_references(opcode: Opcode, a: i32, b: i32) -> Result<Vec<i32>> {
+        let code = [
+            ALOAD_0 as u8,
+            ALOAD_1 as u8,
+            opcode as u8,
+            0x00,
+            0x05,
+            ICONST_0 as u8,
+            IRETURN as u8,
+            ICONST_1 as u8,
+            IRETURN as u8,
+        ];
+        let mut frame = StackFrame::from_bytecode(&code, 2, 2);
+        frame.set(0, a)?;
+        frame.set(1, b)?;
+
+        execute(frame)
+    }
+
+    #[test]
+    fn acmp_compares_identity() -> Result<()> {
+        let (first, second) = with_mut_heap(|heap| {
+            let first = heap.allocate_instance(Instance::without_fields("java/lang/Object"))?;
+            let second = heap.allocate_instance(Instance::without_fields("java/lang/Object"))?;
+            Ok::<_, VmError>((first, second))
+        })?;
+
+        assert_eq!(compare_references(IF_ACMPEQ, first, first)?, [1]);
+        assert_eq!(compare_references(IF_ACMPEQ, first, second)?, [0]);
+        assert_eq!(compare_references(IF_ACMPEQ, 0, 0)?, [1]);
+        assert_eq!(compare_references(IF_ACMPNE, first, second)?, [1]);
+        assert_eq!(compare_references(IF_ACMPNE, second, second)?, [0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn acmp_rejects_category_two_values() {
+        let mut frame = StackFrame::from_bytecode(&[IF_ACMPEQ as u8, 0x00, 0x03], 0, 3);
+        frame.push(1i32).unwrap();
+        frame.push(2i64).unwrap();
+        let mut frames = StackFrames::from(vec![frame]);
+
+        assert!(matches!(
+            process(IF_ACMPEQ as u8, &mut frames),
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::NotAReference(_)
+            )))
+        ));
+    }
+}
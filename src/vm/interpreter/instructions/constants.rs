@@ -3,9 +3,21 @@ use tracing::trace;
 use super::opcode::Opcode::{self, *};
 use crate::vm::{
     Result,
-    interpreter::stack::{StackError, StackFrames, StackValue},
+    interpreter::{
+        InterpreterError, StackFrame,
+        stack::{StackError, StackFrames, StackValue},
+    },
+    runtime::{
+        RuntimeError,
+        constant_pool::Constant,
+        heap::{Instance, with_mut_heap},
+        method_area::with_method_area,
+    },
 };
 
+const METHOD_TYPE: &str = "java/lang/invoke/MethodType";
+const METHOD_HANDLE: &str = "java/lang/invoke/MethodHandle";
+
 pub(in crate::vm::interpreter::instructions) fn process(
     code: u8,
     classname: &str,
@@ -21,6 +33,7 @@ pub(in crate::vm::interpreter::instructions) fn process(
         }
 
         ACONST_NULL => frame.push_const::<i32>(0, code),
+        ICONST_M1 => frame.push_const::<i32>(-1, code),
         ICONST_0 => frame.push_const::<i32>(0, code),
         ICONST_1 => frame.push_const::<i32>(1, code),
         ICONST_2 => frame.push_const::<i32>(2, code),
@@ -37,9 +50,187 @@ pub(in crate::vm::interpreter::instructions) fn process(
 
         DCONST_0 => frame.push_const::<f64>(0.0, code),
         DCONST_1 => frame.push_const::<f64>(1.0, code),
-        _ => todo!(
-            "constant operation not yet handled: {code}",
-            code = code as u8
-        ),
+
+        BIPUSH => {
+            let byte = frame.get_next_byte() as i8;
+            frame.push_const::<i32>(byte.into(), code)
+        }
+        SIPUSH => {
+            let short = frame.get_next_short() as i16;
+            frame.push_const::<i32>(short.into(), code)
+        }
+
+        LDC => {
+            let index = frame.get_next_byte() as u16;
+            load_constant(frame, classname, index, code)
+        }
+        LDC_W | LDC2_W => {
+            let index = frame.get_next_short();
+            load_constant(frame, classname, index, code)
+        }
+        _ => unreachable!("Tried to push constant with {code} code", code = code as u8),
+    }
+}
+
+/// Pushes the loadable constant at `index` of the current class constant pool, resolving symbolic
+/// references into heap objects as needed.
+fn load_constant(frame: &mut StackFrame, classname: &str, index: u16, code: Opcode) -> Result<()> {
+    let class = with_method_area(|area| area.get(classname))?;
+    let pool = class.constant_pool();
+    let constant = pool.get(index)?;
+
+    let unloadable = |reason: String| InterpreterError::UnloadableConstant { index, reason };
+    let wide = matches!(constant, Constant::Long(_) | Constant::Double(_));
+    if wide != (code == LDC2_W) {
+        return Err(unloadable(format!("{code} cannot load {constant:?}")).into());
+    }
+
+    match constant {
+        Constant::Integer(int) => frame.push_const(*int, code),
+        Constant::Float(float) => frame.push_const(*float, code),
+        Constant::Long(long) => frame.push_const(*long, code),
+        Constant::Double(double) => frame.push_const(*double, code),
+
+        Constant::Class(_) => {
+            let name = pool.get_classname(index)?;
+            let reference = with_method_area(|area| area.class_object(name))?;
+            frame.push_const(reference, code)
+        }
+        Constant::MethodType(descriptor) => {
+            let descriptor = pool.get_utf8(*descriptor)?;
+            if !descriptor.starts_with('(') {
+                return Err(unloadable(format!("{descriptor} is not a method descriptor")).into());
+            }
+
+            let reference = pool.resolve_with(index, || allocate(METHOD_TYPE))?;
+            frame.push_const(reference, code)
+        }
+        Constant::MethodHandle(kind, member) => {
+            let is_member = matches!(
+                pool.get(*member)?,
+                Constant::FieldRef(..) | Constant::MethodRef(..) | Constant::InterfaceMethodRef(..)
+            );
+            if !(1..=9).contains(kind) || !is_member {
+                return Err(RuntimeError::InvalidConstant(index).into());
+            }
+
+            let reference = pool.resolve_with(index, || allocate(METHOD_HANDLE))?;
+            frame.push_const(reference, code)
+        }
+        Constant::Dynamic(bootstrap, _) => {
+            let method = pool.bootstrap_method(*bootstrap)?;
+            Err(unloadable(format!(
+                "dynamic constants require invoking bootstrap method handle #{}",
+                method.method_ref
+            ))
+            .into())
+        }
+        Constant::String(utf8) => {
+            let string = pool.get_utf8(*utf8)?;
+            let reference =
+                pool.resolve_with(index, || with_mut_heap(|heap| heap.intern_string(string)))?;
+            frame.push_const(reference, code)
+        }
+
+        _ => Err(RuntimeError::InvalidConstant(index).into()),
+    }
+}
+
+fn allocate(classname: &str) -> Result<i32> {
+    with_mut_heap(|heap| heap.allocate_instance(Instance::without_fields(classname)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::runtime::{
+        constant_pool::RuntimeConstantPool,
+        heap::with_heap,
+        method_area::{Class, MethodArea},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn iconst_m1() -> Result<()> {
+        let frame = StackFrame::from_bytecode(&[ICONST_M1 as u8], 0, 1);
+        let mut frames = StackFrames::from(vec![frame]);
+        process(ICONST_M1 as u8, "Test", &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        assert_eq!(frame.pop::<i32>(), Some(-1));
+        assert_eq!(frame.pc, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn push_sign_extends() -> Result<()> {
+        let code = [BIPUSH as u8, 0x80, SIPUSH as u8, 0xFF, 0xFE];
+        let frame = StackFrame::from_bytecode(&code, 0, 2);
+        let mut frames = StackFrames::from(vec![frame]);
+        process(BIPUSH as u8, "Test", &mut frames)?;
+        process(SIPUSH as u8, "Test", &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        assert_eq!(frame.pop::<i32>(), Some(-2));
+        assert_eq!(frame.pop::<i32>(), Some(-128));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ldc_class_constant() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let pool =
+            RuntimeConstantPool::new([Constant::Utf8("[I".into()), Constant::Class(1)], vec![]);
+        let class = Class::with_classname("LdcClass").with_constant_pool(pool);
+        with_method_area(|area| area.insert(class));
+
+        let code = [LDC as u8, 2, LDC as u8, 2];
+        let frame = StackFrame::new(0, 2, Arc::from(code), Arc::from("LdcClass"));
+        let mut frames = StackFrames::from(vec![frame]);
+        process(LDC as u8, "LdcClass", &mut frames)?;
+        process(LDC as u8, "LdcClass", &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        let second = frame.pop::<i32>().unwrap();
+        let first = frame.pop::<i32>().unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first, with_method_area(|area| area.class_object("[I"))?);
+        assert_eq!(frame.pc, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ldc_interns_strings() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        // two distinct constants with the same contents, as separate classes would have
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("interned".into()),
+                Constant::String(1),
+                Constant::String(1),
+            ],
+            vec![],
+        );
+        let class = Class::with_classname("LdcString").with_constant_pool(pool);
+        with_method_area(|area| area.insert(class));
+
+        let code = [LDC as u8, 2, LDC as u8, 3];
+        let frame = StackFrame::new(0, 2, Arc::from(code), Arc::from("LdcString"));
+        let mut frames = StackFrames::from(vec![frame]);
+        process(LDC as u8, "LdcString", &mut frames)?;
+        process(LDC as u8, "LdcString", &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        let second = frame.pop::<i32>().unwrap();
+        let first = frame.pop::<i32>().unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(with_heap(|heap| heap.get_string(first))?, "interned");
+
+        Ok(())
     }
 }
@@ -0,0 +1,318 @@
+use crate::vm::{
+    Result,
+    interpreter::{
+        InterpreterError, StackFrames,
+        instructions::opcode::Opcode::{self, *},
+        stack::{Category, StackError},
+    },
+    runtime::method_area::with_method_area,
+};
+use tracing::trace;
+
+pub(in crate::vm::interpreter::instructions) fn process(
+    code: u8,
+    frames: &mut StackFrames,
+) -> Result<()> {
+    let opcode = Opcode::from(code);
+    match opcode {
+        GOTO => {
+            let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+            let offset = frame.branch_offset();
+            frame.step_pc(offset)?;
+
+            trace!("GOTO -> {offset}");
+            Ok(())
+        }
+
+        JSR => {
+            let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+            ensure_subroutines(&frame.current_classname, opcode)?;
+
+            let offset = frame.branch_offset();
+            frame.jump_subroutine(offset as isize, 3, opcode)?;
+            Ok(())
+        }
+        RET => {
+            let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+            ensure_subroutines(&frame.current_classname, opcode)?;
+
+            let index = frame.get_next_byte();
+            frame.return_from_subroutine(index as usize, opcode)?;
+            Ok(())
+        }
+
+        IRETURN | FRETURN | ARETURN => return_value(frames, Category::One, opcode),
+        LRETURN | DRETURN => return_value(frames, Category::Two, opcode),
+        RETURN => {
+            frames.quit_frame().ok_or(StackError::EmptyStack)?;
+
+            trace!("RETURN");
+            Ok(())
+        }
+
+        TABLESWITCH | LOOKUPSWITCH => switch(frames, opcode),
+
+        _ => unreachable!("Tried to process control operation with {code} code"),
+    }
+}
+
+/// Branches to the target the switch instruction has for the int on top of the operand stack, or
+/// to its default one if there's none. The operands follow up to three bytes of padding, which
+/// aligns them to a multiple of four from the start of the code.
+fn switch(frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    let key: i32 = frame.pop().ok_or(StackError::StackUnderflow)?;
+
+    let operands = (frame.pc + 4) & !3;
+    let int = |at: usize| i32::from_be_bytes([0, 1, 2, 3].map(|i| frame.get_byte(at + i)));
+    let default = int(operands);
+    let offset = match code {
+        TABLESWITCH => {
+            let (low, high) = (int(operands + 4), int(operands + 8));
+            match (low..=high).contains(&key) {
+                true => int(operands + 12 + (key as i64 - low as i64) as usize * 4),
+                false => default,
+            }
+        }
+        // match-offset pairs sorted by key
+        _ => (0..int(operands + 4).max(0) as usize)
+            .map(|pair| operands + 8 + pair * 8)
+            .find(|&at| int(at) == key)
+            .map_or(default, |at| int(at + 4)),
+    };
+    frame.jump(offset as isize)?;
+
+    trace!("{code} {key} -> {offset}");
+    Ok(())
+}
+
+/// Leaves the current frame, handing its return value over to the caller operand stack or, for
+/// the outermost frame, as the result of the execution.
+fn return_value(frames: &mut StackFrames, category: Category, code: Opcode) -> Result<()> {
+    let size = match category {
+        Category::One => 1,
+        Category::Two => 2,
+    };
+
+    let mut frame = frames.quit_frame().ok_or(StackError::EmptyStack)?;
+    let slots = frame.pop_slots(size)?;
+
+    match frames.last_mut() {
+        Some(caller) => caller.push_slots(&slots, category)?,
+        None => frames.set_result(slots.to_vec()),
+    }
+
+    trace!("{code} -> {slots:?}");
+    Ok(())
+}
+
+/// Last class file version where `jsr` and `ret` may appear, as they were superseded by the
+/// `StackMapTable` based verification (JVMS 4.9.1).
+const LAST_SUBROUTINE_VERSION: u16 = 50;
+
+/// Fails if the class `classname` is too recent to make use of subroutines.
+pub(in crate::vm::interpreter::instructions) fn ensure_subroutines(
+    classname: &str,
+    code: Opcode,
+) -> Result<()> {
+    let version = with_method_area(|area| area.get(classname))?.major_version();
+    match version {
+        Some(version) if version > LAST_SUBROUTINE_VERSION => {
+            Err(InterpreterError::UnsupportedInstruction {
+                opcode: code.to_string(),
+                version,
+            }
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmError,
+        interpreter::{StackFrame, execute},
+        runtime::method_area::{Class, MethodArea},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn goto_loop() -> Result<()> {
+        // int i = 0; while (i < 5) { i++; } return i;
+        let code = [
+            ICONST_0 as u8,
+            ISTORE_0 as u8,
+            ILOAD_0 as u8,
+            ICONST_5 as u8,
+            IF_ICMPGE as u8,
+            0x00,
+            0x09,
+            IINC as u8,
+            0x00,
+            0x01,
+            GOTO as u8,
+            0xFF,
+            0xF8,
+            ILOAD_0 as u8,
+            IRETURN as u8,
+        ];
+        let frame = StackFrame::from_bytecode(&code, 1, 2);
+
+        assert_eq!(execute(frame)?, [5]);
+        Ok(())
+    }
+
+    #[test]
+    fn goto_before_bytecode_start() {
+        // GOTO -5
+        let code = [GOTO as u8, 0xFF, 0xFB];
+        let frame = StackFrame::from_bytecode(&code, 0, 0);
+        let mut frames = StackFrames::from(vec![frame]);
+
+        let result = process(GOTO as u8, &mut frames);
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::InvalidBranch(-5)
+            )))
+        ));
+    }
+
+    /// Runs a switch over the int argument `key`, whose `code` starts with `ILOAD_0` followed by
+    /// the switch instruction and its padding.
+    fn run_switch(code: &[u8], key: i32) -> Result<Vec<i32>> {
+        let mut frame = StackFrame::from_bytecode(code, 1, 1);
+        frame.set(0, key)?;
+        execute(frame)
+    }
+
+    /// Switch cases returning 1, 2 and 3, in that order, followed by a default one returning -1.
+    const CASES: [u8; 11] = [
+        BIPUSH as u8,
+        1,
+        IRETURN as u8,
+        BIPUSH as u8,
+        2,
+        IRETURN as u8,
+        BIPUSH as u8,
+        3,
+        IRETURN as u8,
+        ICONST_M1 as u8,
+        IRETURN as u8,
+    ];
+
+    #[test]
+    fn tableswitch() -> Result<()> {
+        // switch (key) { case 1: return 1; case 2: return 2; case 3: return 3; default: -1 }
+        let mut code = vec![ILOAD_0 as u8, TABLESWITCH as u8, 0, 0];
+        // default, low and high, then the offset of each case from the TABLESWITCH
+        for int in [36, 1, 3, 27, 30, 33] {
+            code.extend(i32::to_be_bytes(int));
+        }
+        code.extend(CASES);
+
+        for (key, value) in [(1, 1), (2, 2), (3, 3), (0, -1), (4, -1), (i32::MIN, -1)] {
+            assert_eq!(run_switch(&code, key)?, [value]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lookupswitch() -> Result<()> {
+        // switch (key) { case -100: return 1; case 7: return 2; case 1000: return 3; default: -1 }
+        let mut code = vec![ILOAD_0 as u8, LOOKUPSWITCH as u8, 0, 0];
+        // default and the number of pairs, then each key with the offset of its case
+        for int in [44, 3, -100, 35, 7, 38, 1000, 41] {
+            code.extend(i32::to_be_bytes(int));
+        }
+        code.extend(CASES);
+
+        for (key, value) in [(-100, 1), (7, 2), (1000, 3), (0, -1), (i32::MAX, -1)] {
+            assert_eq!(run_switch(&code, key)?, [value]);
+        }
+        Ok(())
+    }
+
+    /// Frame running `code` as a method of a class defined by a class file of `major_version`.
+    fn versioned_frame(code: &[u8], major_version: u16) -> StackFrame {
+        let _ = MethodArea::initialise(".");
+        let classname = format!("Legacy{major_version}");
+        with_method_area(|area| {
+            area.insert(Class::with_classname(&classname).with_major_version(major_version))
+        });
+
+        StackFrame::new(2, 1, Arc::from(code), Arc::from(classname))
+    }
+
+    #[test]
+    fn jsr_ret_subroutine() -> Result<()> {
+        // int i = 0; try { return i; } finally { i += 5; } compiled into a subroutine
+        let code = [
+            JSR as u8,
+            0x00,
+            0x05,
+            ILOAD_0 as u8,
+            IRETURN as u8,
+            ASTORE_1 as u8,
+            IINC as u8,
+            0x00,
+            0x05,
+            RET as u8,
+            0x01,
+        ];
+
+        let mut frames = StackFrames::from(vec![versioned_frame(&code, 49)]);
+        process(JSR as u8, &mut frames)?;
+        let frame = frames.last_mut().unwrap();
+        assert_eq!(frame.pc, 5);
+        assert!(frame.is_return_address(0)?);
+        assert_eq!(frame.operand_stack(), [3]);
+
+        assert_eq!(execute(versioned_frame(&code, 49))?, [5]);
+        Ok(())
+    }
+
+    #[test]
+    fn return_addresses_are_not_references() {
+        // the return address stored by the subroutine, then loaded back as a reference
+        let code = [
+            JSR as u8,
+            0x00,
+            0x04,
+            RETURN as u8,
+            ASTORE_1 as u8,
+            ALOAD_1 as u8,
+            ARETURN as u8,
+        ];
+        assert!(matches!(
+            execute(versioned_frame(&code, 49)),
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::NotAReference(opcode)
+            ))) if opcode == "ALOAD"
+        ));
+
+        // returning to a reference
+        let code = [ACONST_NULL as u8, ASTORE_1 as u8, RET as u8, 0x01];
+        assert!(matches!(
+            execute(versioned_frame(&code, 49)),
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::NotAReturnAddress(opcode)
+            ))) if opcode == "RET"
+        ));
+    }
+
+    #[test]
+    fn jsr_rejected_by_recent_classes() {
+        let code = [JSR as u8, 0x00, 0x03, RETURN as u8];
+        let result = execute(versioned_frame(&code, 51));
+
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(
+                InterpreterError::UnsupportedInstruction { version: 51, .. }
+            ))
+        ));
+    }
+}
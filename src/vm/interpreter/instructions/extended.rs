@@ -0,0 +1,314 @@
+use crate::vm::{
+    Result, VmException,
+    interpreter::{
+        InterpreterError, StackFrame, StackFrames,
+        instructions::control::ensure_subroutines,
+        instructions::opcode::Opcode::{self, *},
+        stack::{Reference, StackError},
+    },
+    runtime::{
+        heap::{Heap, with_mut_heap},
+        method_area::with_method_area,
+    },
+};
+use tracing::trace;
+
+pub(in crate::vm::interpreter::instructions) fn process(
+    code: u8,
+    frames: &mut StackFrames,
+) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+
+    let opcode = Opcode::from(code);
+    match opcode {
+        WIDE => {
+            let modified = Opcode::from(frame.get_next_byte());
+            let index = frame.get_next_short();
+
+            match modified {
+                ILOAD => frame.load::<i32, _>(index, modified),
+                ALOAD => frame.load::<Reference, _>(index, modified),
+                LLOAD => frame.load::<i64, _>(index, modified),
+                FLOAD => frame.load::<f32, _>(index, modified),
+                DLOAD => frame.load::<f64, _>(index, modified),
+
+                ISTORE => frame.store::<i32, _>(index, modified),
+                ASTORE => frame.store_reference_local(index, modified),
+                LSTORE => frame.store::<i64, _>(index, modified),
+                FSTORE => frame.store::<f32, _>(index, modified),
+                DSTORE => frame.store::<f64, _>(index, modified),
+
+                IINC => {
+                    let constant = frame.get_next_short() as i16;
+                    frame.increment(index, constant, modified)
+                }
+                RET => {
+                    ensure_subroutines(&frame.current_classname, modified)?;
+                    Ok(frame.return_from_subroutine(index as usize, modified)?)
+                }
+
+                _ => Err(InterpreterError::InvalidWideOperand(modified.to_string()).into()),
+            }
+        }
+
+        MULTIANEWARRAY => multi_array(frame, opcode),
+
+        IFNULL => Ok(frame.null_branch(true, opcode)?),
+        IFNONNULL => Ok(frame.null_branch(false, opcode)?),
+
+        GOTO_W => {
+            let offset = wide_offset(frame);
+            frame.jump(offset as isize)?;
+
+            trace!("{opcode} -> {offset}");
+            Ok(())
+        }
+        JSR_W => {
+            ensure_subroutines(&frame.current_classname, opcode)?;
+
+            let offset = wide_offset(frame);
+            Ok(frame.jump_subroutine(offset as isize, 5, opcode)?)
+        }
+
+        _ => unreachable!("Tried to process extended operation with {code} code"),
+    }
+}
+
+/// Signed 32-bit branch offset following the current instruction.
+fn wide_offset(frame: &StackFrame) -> i32 {
+    let pc = frame.pc;
+    i32::from_be_bytes([1, 2, 3, 4].map(|at| frame.get_byte(pc + at)))
+}
+
+/// Pushes a new array of the type referenced by the current instruction, with as many
+/// dimensions allocated as the instruction gives, popping the length of each one, outermost
+/// first. Deeper dimensions are left null.
+fn multi_array(frame: &mut StackFrame, code: Opcode) -> Result<()> {
+    let index = frame.get_next_short();
+    let dimensions = frame.get_next_byte() as usize;
+    frame.next_pc();
+    if dimensions == 0 {
+        let reason = format!("{code} needs at least one dimension");
+        return Err(InterpreterError::UnloadableConstant { index, reason }.into());
+    }
+
+    let class = with_method_area(|area| area.get(&frame.current_classname))?;
+    let name = class.constant_pool().get_classname(index)?.to_string();
+
+    let mut lengths = (0..dimensions)
+        .map(|_| frame.pop::<i32>().ok_or(StackError::StackUnderflow))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    lengths.reverse();
+    if let Some(length) = lengths.iter().find(|&&length| length < 0) {
+        let message = length.to_string();
+        return Err(VmException::new("java/lang/NegativeArraySizeException", message).into());
+    }
+
+    let array = with_mut_heap(|heap| allocate_dimensions(heap, &name, &lengths))?;
+    frame.push(array)?;
+
+    trace!("{code} -> {name}{lengths:?} @{array}");
+    Ok(())
+}
+
+/// Allocates an array of `name` whose elements are, recursively, arrays of the next `lengths`.
+fn allocate_dimensions(heap: &mut Heap, name: &str, lengths: &[i32]) -> Result<i32> {
+    let array = heap.allocate_array(name, lengths[0])?;
+    if lengths.len() > 1 {
+        for index in 0..lengths[0] {
+            let element = allocate_dimensions(heap, &name[1..], &lengths[1..])?;
+            heap.set_array_value(array, index, &[element])?;
+        }
+    }
+
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmError,
+        interpreter::execute,
+        runtime::{
+            constant_pool::{Constant, RuntimeConstantPool},
+            heap::with_heap,
+            method_area::{Class, MethodArea},
+        },
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn wide_iinc() -> Result<()> {
+        // WIDE IINC 300, -1000
+        let [hi, lo] = (-1000i16).to_be_bytes();
+        let code = [WIDE as u8, IINC as u8, 0x01, 0x2C, hi, lo];
+        let mut frame = StackFrame::from_bytecode(&code, 301, 0);
+        frame.set(300, 1500)?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        process(WIDE as u8, &mut frames)?;
+
+        let frame = frames.last().unwrap();
+        assert_eq!(frame.get::<i32>(300)?, 500);
+        assert_eq!(frame.pc, code.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn wide_reference_locals() -> Result<()> {
+        // Object copy = original, both in locals past 255; return copy == original ? 1 : 0;
+        let code = [
+            WIDE as u8,
+            ALOAD as u8,
+            0x01,
+            0x00,
+            WIDE as u8,
+            ASTORE as u8,
+            0x01,
+            0x01,
+            WIDE as u8,
+            ALOAD as u8,
+            0x01,
+            0x01,
+            WIDE as u8,
+            ALOAD as u8,
+            0x01,
+            0x00,
+            IF_ACMPEQ as u8,
+            0x00,
+            0x05,
+            ICONST_0 as u8,
+            IRETURN as u8,
+            ICONST_1 as u8,
+            IRETURN as u8,
+        ];
+        let original = with_mut_heap(|heap| heap.allocate_array("[I", 1))?;
+        let mut frame = StackFrame::from_bytecode(&code, 258, 2);
+        frame.set(256, Reference(original))?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        process(WIDE as u8, &mut frames)?;
+        process(WIDE as u8, &mut frames)?;
+        // the stored reference is tagged as such
+        assert!(
+            frames
+                .last()
+                .unwrap()
+                .debug_state()
+                .ends_with(&format!("@{original}]"))
+        );
+
+        let mut frame = StackFrame::from_bytecode(&code, 258, 2);
+        frame.set(256, Reference(original))?;
+        assert_eq!(execute(frame)?, [1]);
+        Ok(())
+    }
+
+    #[test]
+    fn wide_rejects_other_instructions() {
+        let code = [WIDE as u8, NOP as u8, 0x00, 0x00];
+        let mut frames = StackFrames::from(vec![StackFrame::from_bytecode(&code, 1, 0)]);
+
+        assert!(matches!(
+            process(WIDE as u8, &mut frames),
+            Err(VmError::Interpreter(InterpreterError::InvalidWideOperand(opcode))) if opcode == "NOP"
+        ));
+    }
+
+    #[test]
+    fn null_branches() -> Result<()> {
+        // return reference == null ? 1 : 0;
+        let is_null = |opcode: Opcode, reference: i32| {
+            let code = [
+                ALOAD_0 as u8,
+                opcode as u8,
+                0x00,
+                0x05,
+                ICONST_0 as u8,
+                IRETURN as u8,
+                ICONST_1 as u8,
+                IRETURN as u8,
+            ];
+            let mut frame = StackFrame::from_bytecode(&code, 1, 1);
+            frame.set(0, reference)?;
+            execute(frame)
+        };
+
+        assert_eq!(is_null(IFNULL, 0)?, [1]);
+        assert_eq!(is_null(IFNULL, 7)?, [0]);
+        assert_eq!(is_null(IFNONNULL, 0)?, [0]);
+        assert_eq!(is_null(IFNONNULL, 7)?, [1]);
+        Ok(())
+    }
+
+    #[test]
+    fn goto_w() -> Result<()> {
+        // GOTO_W +7 over a return of 0, then GOTO_W -3 back to a return of 1
+        let code = [
+            GOTO_W as u8,
+            0x00,
+            0x00,
+            0x00,
+            0x07,
+            ICONST_1 as u8,
+            IRETURN as u8,
+            GOTO_W as u8,
+            0xFF,
+            0xFF,
+            0xFF,
+            0xFE,
+            ICONST_0 as u8,
+            IRETURN as u8,
+        ];
+
+        assert_eq!(execute(StackFrame::from_bytecode(&code, 0, 1))?, [1]);
+        Ok(())
+    }
+
+    /// Runs `new int[first][second]` from a class whose constant pool holds `[[I`.
+    fn int_matrix(first: i32, second: i32) -> Result<i32> {
+        let _ = MethodArea::initialise(".");
+        let pool =
+            RuntimeConstantPool::new([Constant::Utf8("[[I".into()), Constant::Class(1)], vec![]);
+        with_method_area(|area| {
+            area.insert(Class::with_classname("MultiArray").with_constant_pool(pool))
+        });
+
+        let code = [MULTIANEWARRAY as u8, 0x00, 0x02, 0x02];
+        let mut frame = StackFrame::new(0, 2, Arc::from(code), Arc::from("MultiArray"));
+        frame.push(first)?;
+        frame.push(second)?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        process(MULTIANEWARRAY as u8, &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        assert_eq!(frame.pc, code.len());
+        Ok(frame.pop::<i32>().unwrap())
+    }
+
+    #[test]
+    fn multianewarray() -> Result<()> {
+        let matrix = int_matrix(2, 3)?;
+        with_heap(|heap| {
+            assert_eq!(heap.classname(matrix), Some("[[I"));
+            assert_eq!(heap.array_length(matrix)?, 2);
+
+            let rows = (0..2).map(|index| Ok(heap.get_array_value(matrix, index)?[0]));
+            for row in rows.collect::<Result<Vec<_>>>()? {
+                assert_eq!(heap.classname(row), Some("[I"));
+                assert_eq!(heap.array_length(row)?, 3);
+            }
+            Ok::<_, VmError>(())
+        })?;
+
+        assert!(matches!(
+            int_matrix(0, -1),
+            Err(VmError::Exception(VmException { class, .. }))
+                if class == "java/lang/NegativeArraySizeException"
+        ));
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use crate::vm::{
     interpreter::{
         StackFrames,
         instructions::opcode::Opcode::{self, *},
-        stack::StackError,
+        stack::{Reference, StackError},
     },
 };
 
@@ -15,7 +15,8 @@ pub(in crate::vm::interpreter::instructions) fn process(
 
     let opcode = Opcode::from(code);
     match opcode {
-        ILOAD | ALOAD => frame.positional_load::<i32>(opcode),
+        ILOAD => frame.positional_load::<i32>(opcode),
+        ALOAD => frame.positional_load::<Reference>(opcode),
         LLOAD => frame.positional_load::<i64>(opcode),
         FLOAD => frame.positional_load::<f32>(opcode),
         DLOAD => frame.positional_load::<f64>(opcode),
@@ -28,7 +29,9 @@ pub(in crate::vm::interpreter::instructions) fn process(
 
         DLOAD_0 | DLOAD_1 | DLOAD_2 | DLOAD_3 => frame.load::<f64, _>(code - DLOAD_0 as u8, opcode),
 
-        ALOAD_0 | ALOAD_1 | ALOAD_2 | ALOAD_3 => frame.load::<i32, _>(code - ALOAD_0 as u8, opcode),
+        ALOAD_0 | ALOAD_1 | ALOAD_2 | ALOAD_3 => {
+            frame.load::<Reference, _>(code - ALOAD_0 as u8, opcode)
+        }
 
         IALOAD | AALOAD | BALOAD | CALOAD | SALOAD => frame.load_array::<i32>(opcode),
         LALOAD => frame.load_array::<i64>(opcode),
@@ -38,3 +41,25 @@ pub(in crate::vm::interpreter::instructions) fn process(
         _ => unreachable!("Tried to load with {code} code"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmError,
+        interpreter::{InterpreterError, StackFrame},
+    };
+
+    #[test]
+    fn load_out_of_range_local() {
+        let code = [ILOAD as u8, 99];
+        let frame = StackFrame::from_bytecode(&code, 2, 1);
+        let mut frames = StackFrames::from(vec![frame]);
+
+        let err = process(ILOAD as u8, &mut frames).unwrap_err();
+        assert!(matches!(
+            err,
+            VmError::Interpreter(InterpreterError::Stack(StackError::InvalidLocal(99)))
+        ));
+    }
+}
@@ -1,39 +1,45 @@
-use std::ops::{Mul, Neg};
+use std::{
+    fmt::Display,
+    ops::{Mul, Neg},
+};
 
 use crate::vm::{
-    Result,
+    Result, VmException,
     interpreter::{
-        StackFrames,
+        ArithmeticMode, InterpreterError, StackFrame, StackFrames,
         instructions::opcode::Opcode::{self, *},
         stack::{StackError, StackValue},
     },
 };
 use tracing::trace;
 
-/// Binary mask stated by JVM spec for handling bit operations.
+/// Binary masks stated by JVM spec for the shift distance of bit operations: only the low five
+/// bits are used for `int` shifts, and the low six bits for `long` shifts.
 /// Reference: https://docs.oracle.com/javase/specs/jvms/se24/html/jvms-6.html
-const MASK: u32 = 0x3f;
+const INT_SHIFT_MASK: u32 = 0x1f;
+const LONG_SHIFT_MASK: u32 = 0x3f;
 
 pub(in crate::vm::interpreter::instructions) fn process(
     code: u8,
     frames: &mut StackFrames,
 ) -> Result<()> {
+    let mode = frames.arithmetic_mode();
     let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
 
     let opcode = Opcode::from(code);
     match opcode {
-        IADD => frame.binary_op(|a: i32, b| a.wrapping_add(b), opcode),
-        LADD => frame.binary_op(|a: i64, b| a.wrapping_add(b), opcode),
+        IADD => integer_op(frame, mode, i32::wrapping_add, i32::checked_add, opcode),
+        LADD => integer_op(frame, mode, i64::wrapping_add, i64::checked_add, opcode),
         FADD => frame.binary_op(|a: f32, b: f32| a + b, opcode),
         DADD => frame.binary_op(|a: f64, b: f64| a + b, opcode),
 
-        ISUB => frame.binary_op(|a: i32, b| a.wrapping_sub(b), opcode),
-        LSUB => frame.binary_op(|a: i64, b| a.wrapping_sub(b), opcode),
+        ISUB => integer_op(frame, mode, i32::wrapping_sub, i32::checked_sub, opcode),
+        LSUB => integer_op(frame, mode, i64::wrapping_sub, i64::checked_sub, opcode),
         FSUB => frame.binary_op(|a: f32, b: f32| a - b, opcode),
         DSUB => frame.binary_op(|a: f64, b: f64| a - b, opcode),
 
-        IMUL => frame.binary_op(|a: i32, b: i32| a.wrapping_mul(b), opcode),
-        LMUL => frame.binary_op(|a: i64, b: i64| a.wrapping_mul(b), opcode),
+        IMUL => integer_op(frame, mode, i32::wrapping_mul, i32::checked_mul, opcode),
+        LMUL => integer_op(frame, mode, i64::wrapping_mul, i64::checked_mul, opcode),
         FMUL => frame.binary_op(|a: f32, b: f32| a.mul(b), opcode),
         DMUL => frame.binary_op(|a: f64, b: f64| a.mul(b), opcode),
 
@@ -47,15 +53,16 @@ pub(in crate::vm::interpreter::instructions) fn process(
         FREM => frame.binary_op(|a: f32, b: f32| a % b, opcode),
         DREM => frame.binary_op(|a: f64, b: f64| a % b, opcode),
 
-        LSHL => frame.binary_op(|a: i64, b: i32| a << (b as u32 & MASK), opcode),
-        LSHR => frame.binary_op(|a: i64, b: i32| a >> (b as u32 & MASK), opcode),
-        ISHR => frame.binary_op(|a: i32, b: i32| a >> (b as u32 & MASK), opcode),
+        ISHL => frame.binary_op(|a: i32, b: i32| a << (b as u32 & INT_SHIFT_MASK), opcode),
+        LSHL => frame.binary_op(|a: i64, b: i32| a << (b as u32 & LONG_SHIFT_MASK), opcode),
+        ISHR => frame.binary_op(|a: i32, b: i32| a >> (b as u32 & INT_SHIFT_MASK), opcode),
+        LSHR => frame.binary_op(|a: i64, b: i32| a >> (b as u32 & LONG_SHIFT_MASK), opcode),
         IUSHR => frame.binary_op(
-            |a: i32, b: i32| (a as u32 >> (b as u32 & MASK)) as i32,
+            |a: i32, b: i32| (a as u32 >> (b as u32 & INT_SHIFT_MASK)) as i32,
             opcode,
         ),
         LUSHR => frame.binary_op(
-            |a: i64, b: i32| (a as u64 >> (b as u32 & MASK)) as i64,
+            |a: i64, b: i32| (a as u64 >> (b as u32 & LONG_SHIFT_MASK)) as i64,
             opcode,
         ),
 
@@ -71,11 +78,206 @@ pub(in crate::vm::interpreter::instructions) fn process(
         IXOR => frame.binary_op(|a: i32, b: i32| a ^ b, opcode),
         LXOR => frame.binary_op(|a: i64, b: i64| a ^ b, opcode),
 
-        IINC => frame.increment(
-            |f| f.get_next_byte() as usize,
-            |f| f.get_next_byte() as i8 as i32,
-            opcode,
-        ),
+        IINC => {
+            // the local index comes first, followed by the signed constant
+            let index = frame.get_next_byte();
+            let constant = frame.get_next_byte() as i8;
+
+            frame.increment(index, constant, opcode)
+        }
         _ => unreachable!("Tried perform math operation with {code} code"),
     }
 }
+
+/// Applies an integer operation that may overflow, either wrapping around or throwing
+/// `ArithmeticException` depending on the arithmetic `mode`.
+fn integer_op<T: StackValue + Display>(
+    frame: &mut StackFrame,
+    mode: ArithmeticMode,
+    wrapping: fn(T, T) -> T,
+    checked: fn(T, T) -> Option<T>,
+    code: Opcode,
+) -> Result<()> {
+    if mode == ArithmeticMode::Wrapping {
+        return frame.binary_op(wrapping, code);
+    }
+
+    let b: T = frame.pop().ok_or(StackError::EmptyStack)?;
+    let a: T = frame.pop().ok_or(StackError::EmptyStack)?;
+    let value = checked(a, b).ok_or_else(|| {
+        // same messages as `Math.addExact` and friends
+        let message = match size_of::<T>() {
+            8 => "long overflow",
+            _ => "integer overflow",
+        };
+        VmException::new("java/lang/ArithmeticException", message)
+    })?;
+
+    frame.push(value)?;
+    frame.next_pc();
+
+    trace!("{code} -> ({a}, {b}) -> {value}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::VmError;
+
+    fn binary_op<T: StackValue>(opcode: Opcode, a: T, b: T) -> Result<T> {
+        let mut frame = StackFrame::from_bytecode(&[opcode as u8], 0, 4);
+        frame.push(a)?;
+        frame.push(b)?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        process(opcode as u8, &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        Ok(frame.pop::<T>().unwrap())
+    }
+
+    /// Shifts `value` by `distance`, which is always an `int` regardless of the shifted type.
+    fn shift<T: StackValue>(opcode: Opcode, value: T, distance: i32) -> Result<T> {
+        let mut frame = StackFrame::from_bytecode(&[opcode as u8], 0, 3);
+        frame.push(value)?;
+        frame.push(distance)?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        process(opcode as u8, &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        Ok(frame.pop::<T>().unwrap())
+    }
+
+    #[test]
+    fn iadd() -> Result<()> {
+        assert_eq!(binary_op(IADD, 2, 3)?, 5);
+        assert_eq!(binary_op(IADD, -2, 3)?, 1);
+        assert_eq!(binary_op(IADD, i32::MAX, 1)?, i32::MIN);
+
+        Ok(())
+    }
+
+    #[test]
+    fn isub() -> Result<()> {
+        // the second operand is the one on top of the stack
+        assert_eq!(binary_op(ISUB, 2, 3)?, -1);
+        assert_eq!(binary_op(ISUB, 3, 2)?, 1);
+        assert_eq!(binary_op(ISUB, i32::MIN, 1)?, i32::MAX);
+
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_wraps_by_default() -> Result<()> {
+        assert_eq!(binary_op(IADD, i32::MAX, 1)?, i32::MIN);
+        assert_eq!(binary_op(LMUL, i64::MAX, 2)?, -2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_traps_in_checked_mode() -> Result<()> {
+        let checked = |opcode: Opcode, a: i32, b: i32| {
+            let mut frame = StackFrame::from_bytecode(&[opcode as u8], 0, 2);
+            frame.push(a)?;
+            frame.push(b)?;
+
+            let mut frames =
+                StackFrames::from(vec![frame]).with_arithmetic_mode(ArithmeticMode::Checked);
+            process(opcode as u8, &mut frames)?;
+            Ok::<_, VmError>(frames.last_mut().unwrap().pop::<i32>().unwrap())
+        };
+
+        let overflow = VmException::new("java/lang/ArithmeticException", "integer overflow");
+        assert!(matches!(
+            checked(IADD, i32::MAX, 1),
+            Err(VmError::Exception(exception)) if exception == overflow
+        ));
+        assert!(matches!(
+            checked(ISUB, i32::MIN, 1),
+            Err(VmError::Exception(exception)) if exception == overflow
+        ));
+        assert_eq!(checked(IMUL, 6, 7)?, 42);
+
+        Ok(())
+    }
+
+    #[test]
+    fn frem() -> Result<()> {
+        // the result takes the sign of the dividend, as C's `fmod` does
+        assert_eq!(binary_op(FREM, 5.5f32, 2.0)?, 1.5);
+        assert_eq!(binary_op(FREM, -5.5f32, 2.0)?, -1.5);
+        assert_eq!(binary_op(FREM, 5.5f32, -2.0)?, 1.5);
+
+        assert!(binary_op(FREM, f32::NAN, 2.0)?.is_nan());
+        assert!(binary_op(FREM, f32::INFINITY, 2.0)?.is_nan());
+        assert!(binary_op(FREM, 5.0f32, 0.0)?.is_nan());
+
+        // a finite dividend is left untouched by an infinite divisor
+        assert_eq!(binary_op(FREM, 5.0f32, f32::INFINITY)?, 5.0);
+        let zero = binary_op(FREM, -0.0f32, 2.0)?;
+        assert!(zero == 0.0 && zero.is_sign_negative());
+
+        Ok(())
+    }
+
+    #[test]
+    fn drem() -> Result<()> {
+        assert_eq!(binary_op(DREM, 7.0f64, 3.0)?, 1.0);
+        assert_eq!(binary_op(DREM, -7.0f64, 3.0)?, -1.0);
+        assert_eq!(binary_op(DREM, 7.0f64, -3.0)?, 1.0);
+
+        assert!(binary_op(DREM, f64::NEG_INFINITY, 3.0)?.is_nan());
+        assert!(binary_op(DREM, 7.0f64, 0.0)?.is_nan());
+        assert!(binary_op(DREM, 7.0f64, -0.0)?.is_nan());
+
+        assert_eq!(binary_op(DREM, -7.0f64, f64::INFINITY)?, -7.0);
+        let zero = binary_op(DREM, 0.0f64, -3.0)?;
+        assert!(zero == 0.0 && zero.is_sign_positive());
+
+        Ok(())
+    }
+
+    #[test]
+    fn int_shifts_mask_five_bits() -> Result<()> {
+        // 32 & 0x1f == 0, so nothing is shifted
+        assert_eq!(shift(ISHL, 1i32, 32)?, 1);
+        assert_eq!(shift(ISHL, 1i32, 33)?, 2);
+        assert_eq!(shift(ISHR, -8i32, 34)?, -2);
+        assert_eq!(shift(IUSHR, -1i32, 60)?, 0xf);
+        assert_eq!(shift(ISHL, 1i32, -1)?, i32::MIN);
+
+        Ok(())
+    }
+
+    #[test]
+    fn long_shifts_mask_six_bits() -> Result<()> {
+        assert_eq!(shift(LSHL, 1i64, 32)?, 1 << 32);
+        assert_eq!(shift(LSHL, 1i64, 64)?, 1);
+        assert_eq!(shift(LSHR, -8i64, 66)?, -2);
+        assert_eq!(shift(LUSHR, -1i64, 60)?, 0xf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn iinc_wraps_and_reads_index_first() -> Result<()> {
+        // IINC 0, -1
+        let code = [IINC as u8, 0x00, 0xFF];
+        let mut frame = StackFrame::from_bytecode(&code, 2, 0);
+        frame.set(0, i32::MIN)?;
+        frame.set(1, 7)?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        process(IINC as u8, &mut frames)?;
+
+        let frame = frames.last().unwrap();
+        assert_eq!(frame.get::<i32>(0)?, i32::MAX);
+        assert_eq!(frame.get::<i32>(1)?, 7);
+        assert_eq!(frame.pc, 3);
+
+        Ok(())
+    }
+}
@@ -1,26 +1,111 @@
 //! Java byte code instructions definition and processing.
 
-use crate::vm::{Result, interpreter::StackFrames};
+use crate::vm::{
+    Result,
+    interpreter::{InterpreterError, StackFrames},
+};
 
 mod comparisons;
 mod constants;
+mod control;
 mod conversions;
+mod extended;
 mod loads;
 mod math;
+mod references;
 mod stack;
 mod stores;
 
 pub(super) mod opcode;
 
-pub(super) fn process(code: u8, classname: &str, frames: &mut StackFrames) -> Result<()> {
-    match code {
-        0..=20 => constants::process(code, classname, frames),
-        21..=53 => loads::process(code, frames),
-        54..=86 => stores::process(code, frames),
-        87..=95 => stack::process(code, frames),
-        96..=132 => math::process(code, frames),
-        133..=147 => conversions::process(code, frames),
-        148..=166 => comparisons::process(code, frames),
-        _ => unreachable!("Tried to process: {code} code"),
+/// Groups of instructions as laid out by JVMS (7), each handled by the module of the same name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(in crate::vm) enum InstructionCategory {
+    Constants,
+    Loads,
+    Stores,
+    Stack,
+    Math,
+    Conversions,
+    Comparisons,
+    Control,
+    References,
+    Extended,
+}
+
+pub(super) fn process(
+    category: InstructionCategory,
+    code: u8,
+    classname: &str,
+    frames: &mut StackFrames,
+) -> Result<()> {
+    match category {
+        InstructionCategory::Constants => constants::process(code, classname, frames),
+        InstructionCategory::Loads => loads::process(code, frames),
+        InstructionCategory::Stores => stores::process(code, frames),
+        InstructionCategory::Stack => stack::process(code, frames),
+        InstructionCategory::Math => math::process(code, frames),
+        InstructionCategory::Conversions => conversions::process(code, frames),
+        InstructionCategory::Comparisons => comparisons::process(code, frames),
+        InstructionCategory::Control => control::process(code, frames),
+        InstructionCategory::References => references::process(code, classname, frames),
+        InstructionCategory::Extended => extended::process(code, frames),
+    }
+}
+
+impl InstructionCategory {
+    const ALL: [Self; 10] = [
+        Self::Constants,
+        Self::Loads,
+        Self::Stores,
+        Self::Stack,
+        Self::Math,
+        Self::Conversions,
+        Self::Comparisons,
+        Self::Control,
+        Self::References,
+        Self::Extended,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Constants => "constants",
+            Self::Loads => "loads",
+            Self::Stores => "stores",
+            Self::Stack => "stack",
+            Self::Math => "math",
+            Self::Conversions => "conversions",
+            Self::Comparisons => "comparisons",
+            Self::Control => "control",
+            Self::References => "references",
+            Self::Extended => "extended",
+        }
+    }
+
+    /// Category named `name`, as given by [`InstructionCategory::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.name() == name)
+    }
+}
+
+impl TryFrom<u8> for InstructionCategory {
+    type Error = InterpreterError;
+
+    fn try_from(code: u8) -> std::result::Result<Self, Self::Error> {
+        match code {
+            0..=20 => Ok(Self::Constants),
+            21..=53 => Ok(Self::Loads),
+            54..=86 => Ok(Self::Stores),
+            87..=95 => Ok(Self::Stack),
+            96..=132 => Ok(Self::Math),
+            133..=147 => Ok(Self::Conversions),
+            148..=166 => Ok(Self::Comparisons),
+            167..=177 => Ok(Self::Control),
+            178..=195 => Ok(Self::References),
+            196..=201 => Ok(Self::Extended),
+            _ => Err(InterpreterError::UnknownOpcode(code)),
+        }
     }
 }
@@ -1,9 +1,9 @@
 //! Java bytecode opcode [instructions](https://docs.oracle.com/javase/specs/jvms/se24/html/jvms-6.html) definition.
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, num_enum::FromPrimitive)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, num_enum::FromPrimitive)]
 #[repr(u8)]
-#[allow(non_camel_case_types)]
-pub(crate) enum Opcode {
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum Opcode {
     // constants-related instructions
     /// Do nothing; execution proceeds to the next instruction.
     #[default]
@@ -238,6 +238,82 @@ pub(crate) enum Opcode {
     IF_ICMPLE,
     IF_ACMPEQ,
     IF_ACMPNE,
+
+    // control
+    /// Branch unconditionally to the signed 16-bit offset.
+    GOTO,
+    /// Jump to a subroutine, pushing the `returnAddress` of the next instruction onto the operand stack.
+    JSR,
+    /// Return from a subroutine to the address held in a local variable.
+    RET,
+    /// Access a jump table by index and jump.
+    TABLESWITCH,
+    /// Access a jump table by key match and jump.
+    LOOKUPSWITCH,
+    /// Return an `int` from the current method.
+    IRETURN,
+    /// Return a `long` from the current method.
+    LRETURN,
+    /// Return a `float` from the current method.
+    FRETURN,
+    /// Return a `double` from the current method.
+    DRETURN,
+    /// Return an object reference from the current method.
+    ARETURN,
+    /// Return `void` from the current method.
+    RETURN,
+
+    // references
+    /// Get a `static` field from a class.
+    GETSTATIC,
+    /// Set a `static` field in a class.
+    PUTSTATIC,
+    /// Fetch a field from an object.
+    GETFIELD,
+    /// Set a field in an object.
+    PUTFIELD,
+    /// Invoke an instance method, dispatching on the class of the receiver.
+    INVOKEVIRTUAL,
+    /// Invoke an instance method directly, used for constructors, private and superclass methods.
+    INVOKESPECIAL,
+    /// Invoke a class (`static`) method.
+    INVOKESTATIC,
+    /// Invoke an interface method.
+    INVOKEINTERFACE,
+    /// Invoke a dynamically-computed call site.
+    INVOKEDYNAMIC,
+    /// Create a new object.
+    NEW,
+    /// Create a new array of a primitive type.
+    NEWARRAY,
+    /// Create a new array of references.
+    ANEWARRAY,
+    /// Get the length of an array.
+    ARRAYLENGTH,
+    /// Throw an exception or error.
+    ATHROW,
+    /// Check whether an object is of the given type.
+    CHECKCAST,
+    /// Determine if an object is of the given type.
+    INSTANCEOF,
+    /// Enter the monitor of an object.
+    MONITORENTER,
+    /// Exit the monitor of an object.
+    MONITOREXIT,
+
+    // extended
+    /// Extend a local variable index (and, for `iinc`, the constant) by additional bytes.
+    WIDE,
+    /// Create a new multidimensional array.
+    MULTIANEWARRAY,
+    /// Branch if the reference is `null`.
+    IFNULL,
+    /// Branch if the reference is not `null`.
+    IFNONNULL,
+    /// Branch unconditionally to the signed 32-bit offset.
+    GOTO_W,
+    /// Jump to a subroutine using a signed 32-bit offset.
+    JSR_W,
 }
 
 impl std::fmt::Display for Opcode {
@@ -423,6 +499,47 @@ impl std::fmt::Display for Opcode {
             Opcode::IF_ICMPLE => write!(f, "IF_ICMPLE"),
             Opcode::IF_ACMPEQ => write!(f, "IF_ACMPEQ"),
             Opcode::IF_ACMPNE => write!(f, "IF_ACMPNE"),
+
+            // control
+            Opcode::GOTO => write!(f, "GOTO"),
+            Opcode::JSR => write!(f, "JSR"),
+            Opcode::RET => write!(f, "RET"),
+            Opcode::TABLESWITCH => write!(f, "TABLESWITCH"),
+            Opcode::LOOKUPSWITCH => write!(f, "LOOKUPSWITCH"),
+            Opcode::IRETURN => write!(f, "IRETURN"),
+            Opcode::LRETURN => write!(f, "LRETURN"),
+            Opcode::FRETURN => write!(f, "FRETURN"),
+            Opcode::DRETURN => write!(f, "DRETURN"),
+            Opcode::ARETURN => write!(f, "ARETURN"),
+            Opcode::RETURN => write!(f, "RETURN"),
+
+            // references
+            Opcode::GETSTATIC => write!(f, "GETSTATIC"),
+            Opcode::PUTSTATIC => write!(f, "PUTSTATIC"),
+            Opcode::GETFIELD => write!(f, "GETFIELD"),
+            Opcode::PUTFIELD => write!(f, "PUTFIELD"),
+            Opcode::INVOKEVIRTUAL => write!(f, "INVOKEVIRTUAL"),
+            Opcode::INVOKESPECIAL => write!(f, "INVOKESPECIAL"),
+            Opcode::INVOKESTATIC => write!(f, "INVOKESTATIC"),
+            Opcode::INVOKEINTERFACE => write!(f, "INVOKEINTERFACE"),
+            Opcode::INVOKEDYNAMIC => write!(f, "INVOKEDYNAMIC"),
+            Opcode::NEW => write!(f, "NEW"),
+            Opcode::NEWARRAY => write!(f, "NEWARRAY"),
+            Opcode::ANEWARRAY => write!(f, "ANEWARRAY"),
+            Opcode::ARRAYLENGTH => write!(f, "ARRAYLENGTH"),
+            Opcode::ATHROW => write!(f, "ATHROW"),
+            Opcode::CHECKCAST => write!(f, "CHECKCAST"),
+            Opcode::INSTANCEOF => write!(f, "INSTANCEOF"),
+            Opcode::MONITORENTER => write!(f, "MONITORENTER"),
+            Opcode::MONITOREXIT => write!(f, "MONITOREXIT"),
+
+            // extended
+            Opcode::WIDE => write!(f, "WIDE"),
+            Opcode::MULTIANEWARRAY => write!(f, "MULTIANEWARRAY"),
+            Opcode::IFNULL => write!(f, "IFNULL"),
+            Opcode::IFNONNULL => write!(f, "IFNONNULL"),
+            Opcode::GOTO_W => write!(f, "GOTO_W"),
+            Opcode::JSR_W => write!(f, "JSR_W"),
         }
     }
 }
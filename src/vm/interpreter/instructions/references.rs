@@ -0,0 +1,1305 @@
+use crate::vm::{
+    Result, VmException,
+    interpreter::{
+        InterpreterError, StackFrame, StackFrames, ValueRef,
+        exception::throw_object,
+        instructions::opcode::Opcode::{self, *},
+        native::{PRINT_STREAM, natives},
+        stack::{Category, StackError, Value},
+        static_method::Static,
+        verifier::method_sizes,
+    },
+    runtime::{
+        RuntimeError,
+        heap::{with_heap, with_mut_heap},
+        method_area::{FieldValue, Method, with_method_area},
+    },
+};
+use std::sync::Arc;
+use tracing::trace;
+
+const JAVA_LANG_THROWABLE: &str = "java/lang/Throwable";
+
+pub(in crate::vm::interpreter::instructions) fn process(
+    code: u8,
+    classname: &str,
+    frames: &mut StackFrames,
+) -> Result<()> {
+    let opcode = Opcode::from(code);
+    match opcode {
+        GETSTATIC | PUTSTATIC => access_static(classname, frames, opcode),
+        GETFIELD | PUTFIELD => access_field(classname, frames, opcode),
+        INVOKEVIRTUAL | INVOKESPECIAL | INVOKEINTERFACE => invoke(classname, frames, opcode, true),
+        INVOKESTATIC => invoke(classname, frames, opcode, false),
+        // bootstrap methods aren't carried over from the class file yet
+        INVOKEDYNAMIC => Err(InterpreterError::UnimplementedInstruction(opcode.to_string()).into()),
+        NEW => new_instance(classname, frames, opcode),
+        NEWARRAY => new_primitive_array(frames, opcode),
+        ANEWARRAY => new_reference_array(classname, frames, opcode),
+        CHECKCAST => check_cast(classname, frames, opcode),
+        INSTANCEOF => instance_of(classname, frames, opcode),
+        MONITORENTER | MONITOREXIT => monitor(frames, opcode),
+        ATHROW => throw(frames, opcode),
+        ARRAYLENGTH => array_length(frames, opcode),
+
+        _ => unreachable!("Tried to process reference operation with {code} code"),
+    }
+}
+
+/// Pushes a frame for the method referenced by the current instruction, with its arguments, the
+/// `receiver` first for instance methods, moved from the caller operand stack into the callee
+/// local variables. Native methods run right away instead, through the implementation registered
+/// for them, as do the print methods of the VM's own `System.out` and `System.err`.
+fn invoke(classname: &str, frames: &mut StackFrames, code: Opcode, receiver: bool) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    frame.store_ex_pc();
+    let index = frame.get_next_short();
+    if code == INVOKEINTERFACE {
+        // the count of argument slots and a zero byte, both redundant with the descriptor
+        frame.get_next_short();
+    }
+    frame.next_pc();
+
+    let class = with_method_area(|area| area.get(classname))?;
+    let (owner, name, descriptor) = class.constant_pool().resolve_ref(index)?;
+    let signature = format!("{name}:{descriptor}");
+    let (args, _) = method_sizes(descriptor);
+    let slots = frame.pop_slots(args + receiver as usize)?;
+
+    let receiver_class = match receiver {
+        true => match reference_class(slots[0])? {
+            Some(class) => Some(class),
+            None => {
+                let message = format!("cannot invoke {owner}.{name} on null");
+                return Err(VmException::null_pointer(message).into());
+            }
+        },
+        false => None,
+    };
+
+    // the standard streams are allocated by the VM, so the JDK's `PrintStream` can't run on them
+    if receiver
+        && natives().get(PRINT_STREAM, &signature).is_some()
+        && with_method_area(|area| area.standard_stream_of(slots[0])).is_some()
+    {
+        invoke_native(
+            frame,
+            PRINT_STREAM,
+            &signature,
+            descriptor,
+            &slots,
+            receiver,
+        )?;
+
+        trace!("{code} -> standard stream {PRINT_STREAM}.{signature}");
+        return Ok(());
+    }
+
+    let method = match receiver_class {
+        Some(_) if code == INVOKESPECIAL => special_method(classname, owner, name, &signature)?,
+        Some(class) => virtual_method(owner, &class, &signature)?,
+        None => {
+            Static::initialise(owner)?;
+            with_method_area(|area| area.get(owner))?.get_method(&signature)?
+        }
+    };
+    if method.is_abstract() {
+        let message = format!("{owner}.{signature}");
+        return Err(VmException::new("java/lang/AbstractMethodError", message).into());
+    }
+    if method.is_native() {
+        let (classname, signature) = (method.classname(), method.signature());
+        invoke_native(frame, classname, signature, descriptor, &slots, receiver)?;
+
+        trace!("{code} -> native {classname}.{signature}");
+        return Ok(());
+    }
+    let mut callee = method.new_frame()?;
+    for (local, value) in slots.into_iter().enumerate() {
+        callee.store_variable(local, value)?;
+    }
+
+    frames.add_frame(callee)?;
+
+    trace!("{code} -> {owner}.{signature}");
+    Ok(())
+}
+
+/// Runs the native registered for `classname.signature` with the argument `slots`, the `receiver`
+/// first for instance methods, pushing its result, if any, onto the operand stack of `frame`.
+fn invoke_native(
+    frame: &mut StackFrame,
+    classname: &str,
+    signature: &str,
+    descriptor: &str,
+    slots: &[ValueRef],
+    receiver: bool,
+) -> Result<()> {
+    let mut arguments = match receiver {
+        true => vec![Value::Int(slots[0])],
+        false => vec![],
+    };
+    arguments.extend(native_arguments(descriptor, &slots[receiver as usize..]));
+
+    if let Some(value) = natives().invoke(classname, signature, frame, &arguments)? {
+        let category = match value {
+            Value::Long(_) | Value::Double(_) => Category::Two,
+            Value::Int(_) | Value::Float(_) => Category::One,
+        };
+        frame.push_slots(&value.to_slots(), category)?;
+    }
+
+    Ok(())
+}
+
+/// Selects the method `INVOKESPECIAL` runs from within `classname`, see JVMS (6.5). When the
+/// class has `ACC_SUPER` and the referenced method is neither a constructor nor declared by the
+/// class itself but by one of its superclasses, the lookup starts at its direct superclass, so
+/// overrides between the two are honoured. Otherwise, the referenced method runs as is.
+fn special_method(
+    classname: &str,
+    owner: &str,
+    name: &str,
+    signature: &str,
+) -> Result<Arc<Method>> {
+    with_method_area(|area| {
+        let class = area.get(classname)?;
+        match class.parent() {
+            Some(parent)
+                if class.is_super()
+                    && name != "<init>"
+                    && owner != classname
+                    && area.is_assignable(classname, owner)? =>
+            {
+                area.resolve_virtual(parent, signature)
+            }
+            _ => area.resolve_method(owner, signature),
+        }
+    })
+}
+
+/// Selects the method `INVOKEVIRTUAL` or `INVOKEINTERFACE` runs on an instance of `class`, see
+/// JVMS (5.4.6). A private method of `owner`, which javac also calls this way, runs as is, any
+/// other goes through the virtual method table of `class`.
+fn virtual_method(owner: &str, class: &str, signature: &str) -> Result<Arc<Method>> {
+    with_method_area(|area| {
+        let declared = area
+            .get(owner)
+            .and_then(|owner| owner.get_method(signature));
+        match declared {
+            Ok(method) if !method.is_virtual() => Ok(method),
+            _ => area.resolve_virtual(class, signature),
+        }
+    })
+}
+
+/// Pushes the value of the static field referenced by the current instruction or, for
+/// `PUTSTATIC`, pops a new value into it, once the class declaring the field is initialised.
+fn access_static(classname: &str, frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    frame.store_ex_pc();
+    let index = frame.get_next_short();
+    frame.next_pc();
+
+    let class = with_method_area(|area| area.get(classname))?;
+    let (owner, name, descriptor) = class.constant_pool().resolve_ref(index)?;
+    let (declaring, field) = find_static(owner, name)?;
+    Static::initialise(&declaring)?;
+
+    let category = match descriptor {
+        "J" | "D" => Category::Two,
+        _ => Category::One,
+    };
+    match code {
+        PUTSTATIC => {
+            let size = if category == Category::Two { 2 } else { 1 };
+            field.set(frame.pop_slots(size)?.to_vec())?;
+        }
+        _ => frame.push_slots(&field.value()?, category)?,
+    }
+
+    trace!("{code} -> {declaring}.{name}");
+    Ok(())
+}
+
+/// Pushes the value of the instance field referenced by the current instruction, read from the
+/// object on top of the stack or, for `PUTFIELD`, pops a new value into it along with the object.
+fn access_field(classname: &str, frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    frame.store_ex_pc();
+    let index = frame.get_next_short();
+    frame.next_pc();
+
+    let class = with_method_area(|area| area.get(classname))?;
+    let (owner, name, descriptor) = class.constant_pool().resolve_ref(index)?;
+
+    let category = match descriptor {
+        "J" | "D" => Category::Two,
+        _ => Category::One,
+    };
+    match code {
+        PUTFIELD => {
+            let size = if category == Category::Two { 2 } else { 1 };
+            let value = frame.pop_slots(size)?.to_vec();
+            let object: i32 = frame.pop().ok_or(StackError::StackUnderflow)?;
+            with_mut_heap(|heap| heap.set_field_value(object, owner, name, value))?;
+        }
+        _ => {
+            let object: i32 = frame.pop().ok_or(StackError::StackUnderflow)?;
+            let value = with_heap(|heap| heap.get_field_value(object, owner, name))?;
+            frame.push_slots(&value, category)?;
+        }
+    }
+
+    trace!("{code} -> {owner}.{name}");
+    Ok(())
+}
+
+/// Static field `name` of `classname` or of its closest superclass declaring it, along with the
+/// name of that class.
+fn find_static(classname: &str, name: &str) -> Result<(String, Arc<FieldValue>)> {
+    let mut current = Some(classname.to_string());
+    while let Some(classname) = current {
+        let class = with_method_area(|area| area.get(&classname))?;
+        if let Some(field) = class.get_static(name) {
+            return Ok((classname, field));
+        }
+
+        current = class.parent().map(String::from);
+    }
+
+    Err(RuntimeError::InvalidObjectAcess {
+        classname: classname.to_string(),
+        field: name.to_string(),
+    }
+    .into())
+}
+
+/// Pushes a new instance, with its fields set to their default values, of the class referenced
+/// by the current instruction, which gets initialised first.
+fn new_instance(classname: &str, frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    frame.store_ex_pc();
+    let target = target_class(classname, frame.get_next_short())?;
+    frame.next_pc();
+
+    Static::initialise(&target)?;
+    let instance = with_method_area(|area| area.create_instance_with_default(&target))?;
+    let reference = with_mut_heap(|heap| heap.allocate_instance(instance))?;
+    frame.push(reference)?;
+
+    trace!("{code} -> {target}@{reference}");
+    Ok(())
+}
+
+/// Pushes a new zeroed array of the primitive type given by the current instruction, popping its
+/// length.
+fn new_primitive_array(frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    frame.store_ex_pc();
+    let atype = frame.get_next_byte();
+    frame.next_pc();
+
+    // array type codes, from `T_BOOLEAN` to `T_LONG`
+    let name = match atype {
+        4 => "[Z",
+        5 => "[C",
+        6 => "[F",
+        7 => "[D",
+        8 => "[B",
+        9 => "[S",
+        10 => "[I",
+        11 => "[J",
+        _ => return Err(InterpreterError::InvalidArrayType(atype).into()),
+    };
+    let length: i32 = frame.pop().ok_or(StackError::StackUnderflow)?;
+    let array = with_mut_heap(|heap| heap.allocate_array(name, length))?;
+    frame.push(array)?;
+
+    trace!("{code} -> {name} of {length}@{array}");
+    Ok(())
+}
+
+/// Pushes a new array of null references to the class, interface or array type referenced by the
+/// current instruction, popping its length.
+fn new_reference_array(classname: &str, frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    frame.store_ex_pc();
+    let component = target_class(classname, frame.get_next_short())?;
+    frame.next_pc();
+
+    let name = match component.starts_with('[') {
+        true => format!("[{component}"),
+        false => format!("[L{component};"),
+    };
+    let length: i32 = frame.pop().ok_or(StackError::StackUnderflow)?;
+    let array = with_mut_heap(|heap| heap.allocate_array(&name, length))?;
+    frame.push(array)?;
+
+    trace!("{code} -> {name} of {length}@{array}");
+    Ok(())
+}
+
+/// Rebuilds the arguments of a method of `descriptor` from the `slots` they were passed in.
+fn native_arguments(descriptor: &str, slots: &[ValueRef]) -> Vec<Value> {
+    let params = descriptor
+        .trim_start_matches('(')
+        .split_once(')')
+        .map_or("", |(params, _)| params);
+
+    let mut args = Vec::new();
+    let mut slots = slots;
+    let mut chars = params.chars();
+    while let Some(ty) = chars.next() {
+        // only the kind of a reference matters, not the class it names
+        match ty {
+            'L' => _ = chars.by_ref().find(|&c| c == ';'),
+            '[' => {
+                let mut element = chars.next();
+                while element == Some('[') {
+                    element = chars.next();
+                }
+                if element == Some('L') {
+                    chars.by_ref().find(|&c| c == ';');
+                }
+            }
+            _ => {}
+        }
+
+        let Some(value) = Value::from_slots(ty, slots) else {
+            break;
+        };
+        slots = &slots[value.to_slots().len()..];
+        args.push(value);
+    }
+
+    args
+}
+
+/// Leaves the reference on top of the stack untouched if it is null or assignable to the class
+/// referenced by the current instruction, failing with a `ClassCastException` otherwise.
+fn check_cast(classname: &str, frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    let target = target_class(classname, frame.get_next_short())?;
+    frame.next_pc();
+
+    let reference: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+    if let Some(class) = reference_class(reference)?
+        && !with_method_area(|area| area.is_assignable(&class, &target))?
+    {
+        let message = format!("{class} cannot be cast to {target}");
+        return Err(VmException::new("java/lang/ClassCastException", message).into());
+    }
+    frame.push(reference)?;
+
+    trace!("{code} -> {reference} as {target}");
+    Ok(())
+}
+
+/// Replaces the reference on top of the stack by 1 if it is a non-null instance of the class
+/// referenced by the current instruction, or by 0 otherwise.
+fn instance_of(classname: &str, frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    let target = target_class(classname, frame.get_next_short())?;
+    frame.next_pc();
+
+    let reference: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+    let is_instance = match reference_class(reference)? {
+        Some(class) => with_method_area(|area| area.is_assignable(&class, &target))?,
+        None => false,
+    };
+    frame.push(is_instance as i32)?;
+
+    trace!("{code} -> {reference} instanceof {target} = {is_instance}");
+    Ok(())
+}
+
+/// Enters or exits the monitor of the reference on top of the stack. As there is a single thread,
+/// no locking actually happens, but the reference still must not be null.
+fn monitor(frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    let reference: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+    if reference == 0 {
+        return Err(VmException::null_pointer(format!("cannot {code} on null")).into());
+    }
+    frame.next_pc();
+
+    trace!("{code} -> {reference}");
+    Ok(())
+}
+
+/// Replaces the array reference on top of the stack by its length.
+fn array_length(frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    let array: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+    let length = with_heap(|heap| heap.array_length(array))?;
+    frame.push(length)?;
+    frame.next_pc();
+
+    trace!("{code} -> {array} has {length} elements");
+    Ok(())
+}
+
+/// Throws the exception object on top of the stack, which must be a `java/lang/Throwable`.
+fn throw(frames: &mut StackFrames, code: Opcode) -> Result<()> {
+    let frame = frames.last_mut().ok_or(StackError::EmptyStack)?;
+    let pc = frame.pc;
+    let reference: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+
+    let Some(class) = reference_class(reference)? else {
+        return Err(VmException::null_pointer(format!("cannot {code} null")).into());
+    };
+    if !with_method_area(|area| area.is_assignable(&class, JAVA_LANG_THROWABLE))? {
+        return Err(InterpreterError::NotThrowable(class).into());
+    }
+
+    trace!("{code} -> {reference} of {class}");
+    throw_object(frames, pc, reference, VmException::new(&class, ""))
+}
+
+/// Name of the class at `index` of the constant pool of `classname`.
+fn target_class(classname: &str, index: u16) -> Result<String> {
+    let class = with_method_area(|area| area.get(classname))?;
+    let target = class.constant_pool().get_classname(index)?;
+
+    Ok(target.to_string())
+}
+
+/// Class of the object or array at `reference`, or `None` for null.
+fn reference_class(reference: i32) -> Result<Option<String>> {
+    if reference == 0 {
+        return Ok(None);
+    }
+
+    with_heap(|heap| heap.classname(reference).map(String::from))
+        .map(Some)
+        .ok_or(RuntimeError::InvalidReference(reference).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::MethodFlags;
+    use crate::vm::runtime::heap::{Instance, with_mut_heap};
+    use crate::vm::{
+        VmError,
+        interpreter::{InterpreterError, StackFrame, execute},
+        runtime::{
+            constant_pool::{Constant, RuntimeConstantPool},
+            method_area::{Class, Method, MethodArea},
+        },
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn invoke_static_passes_arguments() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Adder".into()),
+                Constant::Class(1),
+                Constant::Utf8("add".into()),
+                Constant::Utf8("(IJ)J".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+            ],
+            vec![],
+        );
+        // static long add(int a, long b) { return a + b; }
+        let add = [
+            ILOAD_0 as u8,
+            I2L as u8,
+            LLOAD_1 as u8,
+            LADD as u8,
+            LRETURN as u8,
+        ];
+        let class = Class::with_classname("Adder")
+            .with_constant_pool(pool)
+            .with_method(Method::new("Adder", "add:(IJ)J", 4, 3, &add));
+        with_method_area(|area| area.insert(class));
+
+        let code = [
+            ICONST_2 as u8,
+            LCONST_1 as u8,
+            INVOKESTATIC as u8,
+            0,
+            6,
+            LRETURN as u8,
+        ];
+        let frame = StackFrame::new(0, 3, Arc::from(code), Arc::from("Adder"));
+
+        // a long is returned as its low word followed by its high word
+        assert_eq!(execute(frame)?, [3, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_native_only_when_declared() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Linked".into()),
+                Constant::Class(1),
+                Constant::Utf8("answer".into()),
+                Constant::Utf8("()I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+                Constant::Utf8("unlinked".into()),
+                Constant::NameAndType(7, 4),
+                Constant::MethodRef(2, 8),
+            ],
+            vec![],
+        );
+        let answer = [ICONST_2 as u8, IRETURN as u8];
+        let class = Class::with_classname("Linked")
+            .with_constant_pool(pool)
+            .with_method(Method::new("Linked", "answer:()I", 1, 0, &answer))
+            .with_method(Method::without_code("Linked", "unlinked:()I", true));
+        with_method_area(|area| area.insert(class));
+        // never consulted, as the method has bytecode
+        natives().register("Linked", "answer:()I", |_: &mut StackFrame, _: &[Value]| {
+            Ok(Some(Value::Int(42)))
+        });
+
+        let call = |index: u8| {
+            let code = [INVOKESTATIC as u8, 0, index, IRETURN as u8];
+            execute(StackFrame::new(0, 1, Arc::from(code), Arc::from("Linked")))
+        };
+        assert_eq!(call(6)?, [2]);
+        assert!(matches!(
+            call(9),
+            Err(VmError::Exception(VmException { class, message }))
+                if class == "java/lang/UnsatisfiedLinkError" && message == "Linked.unlinked:()I"
+        ));
+
+        Ok(())
+    }
+
+    fn particle_pool() -> RuntimeConstantPool {
+        RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Particle".into()),
+                Constant::Class(1),
+                Constant::Utf8("mass".into()),
+                Constant::Utf8("I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::FieldRef(2, 5),
+                Constant::Utf8("energy".into()),
+                Constant::Utf8("J".into()),
+                Constant::NameAndType(7, 8),
+                Constant::FieldRef(2, 9),
+            ],
+            vec![],
+        )
+    }
+
+    /// Runs `code` as a method of `Particle` taking the particle, or null, as its argument.
+    fn with_particle(code: &[u8], particle: Option<i32>) -> Result<Vec<i32>> {
+        let _ = MethodArea::initialise(".");
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Particle")
+                    .with_constant_pool(particle_pool())
+                    .with_field("mass", vec![0])
+                    .with_field("energy", vec![0, 0]),
+            )
+        });
+        let particle = match particle {
+            Some(particle) => particle,
+            None => {
+                let instance =
+                    with_method_area(|area| area.create_instance_with_default("Particle"))?;
+                with_mut_heap(|heap| heap.allocate_instance(instance))?
+            }
+        };
+
+        let mut frame = StackFrame::new(1, 4, Arc::from(code), Arc::from("Particle"));
+        frame.set(0, particle)?;
+        execute(frame)
+    }
+
+    #[test]
+    fn instance_fields() -> Result<()> {
+        // mass = 9; energy = 1L; return energy + mass;
+        let code = [
+            ALOAD_0 as u8,
+            BIPUSH as u8,
+            9,
+            PUTFIELD as u8,
+            0,
+            6,
+            ALOAD_0 as u8,
+            LCONST_1 as u8,
+            PUTFIELD as u8,
+            0,
+            10,
+            ALOAD_0 as u8,
+            GETFIELD as u8,
+            0,
+            10,
+            ALOAD_0 as u8,
+            GETFIELD as u8,
+            0,
+            6,
+            I2L as u8,
+            LADD as u8,
+            LRETURN as u8,
+        ];
+        assert_eq!(with_particle(&code, None)?, [10, 0]);
+
+        // a fresh particle holds the default values
+        let code = [ALOAD_0 as u8, GETFIELD as u8, 0, 10, LRETURN as u8];
+        assert_eq!(with_particle(&code, None)?, [0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn field_access_on_null() {
+        let get = [ALOAD_0 as u8, GETFIELD as u8, 0, 6, IRETURN as u8];
+        assert!(matches!(
+            with_particle(&get, Some(0)),
+            Err(VmError::Exception(VmException { class, message }))
+                if class == "java/lang/NullPointerException"
+                    && message == "cannot read field Particle.mass of null"
+        ));
+
+        let put = [
+            ALOAD_0 as u8,
+            LCONST_1 as u8,
+            PUTFIELD as u8,
+            0,
+            10,
+            RETURN as u8,
+        ];
+        assert!(matches!(
+            with_particle(&put, Some(0)),
+            Err(VmError::Exception(VmException { class, message }))
+                if class == "java/lang/NullPointerException"
+                    && message == "cannot assign field Particle.energy of null"
+        ));
+    }
+
+    /// Runs `code`, which leaves an array on the stack, and returns the array class and length.
+    fn new_array(code: &[u8]) -> Result<(String, i32)> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("java/lang/String".into()),
+                Constant::Class(1),
+                Constant::Utf8("[I".into()),
+                Constant::Class(3),
+            ],
+            vec![],
+        );
+        with_method_area(|area| {
+            area.insert(Class::with_classname("Arrays").with_constant_pool(pool))
+        });
+
+        let frame = StackFrame::new(0, 1, Arc::from(code), Arc::from("Arrays"));
+        let array = execute(frame)?[0];
+        let classname = with_heap(|heap| heap.classname(array).map(String::from)).unwrap();
+        let length = with_heap(|heap| heap.array_length(array))?;
+
+        Ok((classname, length))
+    }
+
+    #[test]
+    fn array_creation() -> Result<()> {
+        let primitive = |atype| [ICONST_3 as u8, NEWARRAY as u8, atype, ARETURN as u8];
+        assert_eq!(new_array(&primitive(10))?, ("[I".into(), 3));
+        assert_eq!(new_array(&primitive(4))?, ("[Z".into(), 3));
+        assert_eq!(new_array(&primitive(11))?, ("[J".into(), 3));
+        assert!(matches!(
+            new_array(&primitive(12)),
+            Err(VmError::Interpreter(InterpreterError::InvalidArrayType(12)))
+        ));
+
+        let reference = |index| [ICONST_2 as u8, ANEWARRAY as u8, 0, index, ARETURN as u8];
+        assert_eq!(new_array(&reference(2))?, ("[Ljava/lang/String;".into(), 2));
+        assert_eq!(new_array(&reference(4))?, ("[[I".into(), 2));
+        Ok(())
+    }
+
+    #[test]
+    fn invokedynamic_is_unimplemented() {
+        let code = [INVOKEDYNAMIC as u8, 0, 1, 0, 0, RETURN as u8];
+        let result = execute(StackFrame::from_bytecode(&code, 0, 1));
+
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(InterpreterError::UnimplementedInstruction(opcode)))
+                if opcode == "INVOKEDYNAMIC"
+        ));
+    }
+
+    #[test]
+    fn recursion_overflows_the_frame_stack() {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Recursive".into()),
+                Constant::Class(1),
+                Constant::Utf8("recurse".into()),
+                Constant::Utf8("()V".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+            ],
+            vec![],
+        );
+        // static void recurse() { recurse(); }
+        let code = [INVOKESTATIC as u8, 0, 6, RETURN as u8];
+        let class = Class::with_classname("Recursive")
+            .with_constant_pool(pool)
+            .with_method(Method::new("Recursive", "recurse:()V", 0, 0, &code));
+        with_method_area(|area| area.insert(class));
+
+        let frame = StackFrame::new(0, 0, Arc::from(code), Arc::from("Recursive"));
+        let result = execute(frame);
+
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::StackOverflow(1024)
+            )))
+        ));
+    }
+
+    /// Invokes `Greeting.greet:()I` through `INVOKEINTERFACE` on a new instance of `classname`.
+    fn greet(classname: &str) -> Result<Vec<i32>> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Greeting".into()),
+                Constant::Class(1),
+                Constant::Utf8("greet".into()),
+                Constant::Utf8("()I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::InterfaceMethodRef(2, 5),
+            ],
+            vec![],
+        );
+        // interface Greeting extends Salute { default int greet() { return bow() + 1; } }
+        // interface Salute { default int bow() { return 6; } }
+        let bow_ref = [
+            Constant::Utf8("Salute".into()),
+            Constant::Class(1),
+            Constant::Utf8("bow".into()),
+            Constant::Utf8("()I".into()),
+            Constant::NameAndType(3, 4),
+            Constant::InterfaceMethodRef(2, 5),
+        ];
+        let greet = [
+            ALOAD_0 as u8,
+            INVOKEINTERFACE as u8,
+            0,
+            6,
+            1,
+            0,
+            ICONST_1 as u8,
+            IADD as u8,
+            IRETURN as u8,
+        ];
+        let bow = [BIPUSH as u8, 6, IRETURN as u8];
+        // class Loud implements Greeting { public int greet() { return 9; } }
+        let loud = [BIPUSH as u8, 9, IRETURN as u8];
+
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Greeting")
+                    .with_interface("Salute")
+                    .with_constant_pool(RuntimeConstantPool::new(bow_ref, vec![]))
+                    .with_method(Method::new("Greeting", "greet:()I", 2, 1, &greet)),
+            );
+            area.insert(
+                Class::with_classname("Salute")
+                    .with_method(Method::new("Salute", "bow:()I", 1, 1, &bow)),
+            );
+            area.insert(Class::with_classname("Polite").with_interface("Greeting"));
+            area.insert(Class::with_classname("Formal").with_parent("Polite"));
+            area.insert(
+                Class::with_classname("Loud")
+                    .with_interface("Greeting")
+                    .with_method(Method::new("Loud", "greet:()I", 1, 1, &loud)),
+            );
+            area.insert(Class::with_classname("Caller").with_constant_pool(pool));
+        });
+
+        let receiver =
+            with_mut_heap(|heap| heap.allocate_instance(Instance::without_fields(classname)))?;
+        let code = [
+            ALOAD_0 as u8,
+            INVOKEINTERFACE as u8,
+            0,
+            6,
+            1,
+            0,
+            IRETURN as u8,
+        ];
+        let mut frame = StackFrame::new(1, 1, Arc::from(code), Arc::from("Caller"));
+        frame.set(0, receiver)?;
+
+        execute(frame)
+    }
+
+    #[test]
+    fn invokeinterface_default_method() -> Result<()> {
+        assert_eq!(greet("Polite")?, [7]);
+        // inherited through the superclass, which implements the interface
+        assert_eq!(greet("Formal")?, [7]);
+        assert_eq!(greet("Loud")?, [9]);
+
+        Ok(())
+    }
+
+    /// Result of `Child.describe`, invoking `Base.describe` through `INVOKESPECIAL`, where
+    /// `Base`, `Middle` and `Child` extend each other and all of them override the method.
+    fn describe_super(super_flag: bool) -> Result<Vec<i32>> {
+        let _ = MethodArea::initialise(".");
+        let [base, middle, child] =
+            ["Base", "Middle", "Child"].map(|name| format!("{name}{super_flag}"));
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8(base.as_str().into()),
+                Constant::Class(1),
+                Constant::Utf8("describe".into()),
+                Constant::Utf8("()I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+            ],
+            vec![],
+        );
+        let describe = |value: u8| [BIPUSH as u8, value, IRETURN as u8];
+
+        let mut class = Class::with_classname(&child)
+            .with_parent(&middle)
+            .with_constant_pool(pool)
+            .with_method(Method::new(&child, "describe:()I", 1, 1, &describe(3)));
+        if super_flag {
+            class = class.with_super_flag();
+        }
+        with_method_area(|area| {
+            area.insert(Class::with_classname(&base).with_method(Method::new(
+                &base,
+                "describe:()I",
+                1,
+                1,
+                &describe(1),
+            )));
+            area.insert(
+                Class::with_classname(&middle)
+                    .with_parent(&base)
+                    .with_method(Method::new(&middle, "describe:()I", 1, 1, &describe(2))),
+            );
+            area.insert(class);
+        });
+
+        let receiver =
+            with_mut_heap(|heap| heap.allocate_instance(Instance::without_fields(&child)))?;
+        let code = [ALOAD_0 as u8, INVOKESPECIAL as u8, 0, 6, IRETURN as u8];
+        let mut frame = StackFrame::new(1, 1, Arc::from(code), Arc::from(child));
+        frame.set(0, receiver)?;
+
+        execute(frame)
+    }
+
+    #[test]
+    fn invokespecial_super_method() -> Result<()> {
+        // the lookup starts from the direct superclass, which overrides the referenced method
+        assert_eq!(describe_super(true)?, [2]);
+        // without `ACC_SUPER`, the referenced method runs as is
+        assert_eq!(describe_super(false)?, [1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invokevirtual_private_method() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Secretive".into()),
+                Constant::Class(1),
+                Constant::Utf8("secret".into()),
+                Constant::Utf8("()I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+            ],
+            vec![],
+        );
+        let secret = |value: u8| [BIPUSH as u8, value, IRETURN as u8];
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Secretive")
+                    .with_constant_pool(pool)
+                    .with_method(
+                        Method::new("Secretive", "secret:()I", 1, 1, &secret(1))
+                            .with_flags(MethodFlags::PRIVATE),
+                    ),
+            );
+            area.insert(
+                Class::with_classname("Nosy")
+                    .with_parent("Secretive")
+                    .with_method(Method::new("Nosy", "secret:()I", 1, 1, &secret(2))),
+            );
+        });
+
+        // javac calls private methods through `INVOKEVIRTUAL`, which must not dispatch them
+        let receiver =
+            with_mut_heap(|heap| heap.allocate_instance(Instance::without_fields("Nosy")))?;
+        let code = [ALOAD_0 as u8, INVOKEVIRTUAL as u8, 0, 6, IRETURN as u8];
+        let mut frame = StackFrame::new(1, 1, Arc::from(code), Arc::from("Secretive"));
+        frame.set(0, receiver)?;
+        assert_eq!(execute(frame)?, [1]);
+
+        Ok(())
+    }
+
+    /// Pool of the `Config` class, referencing its `LIMIT` and `RUNS` static fields.
+    fn config_pool() -> RuntimeConstantPool {
+        RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Config".into()),
+                Constant::Class(1),
+                Constant::Utf8("LIMIT".into()),
+                Constant::Utf8("I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::FieldRef(2, 5),
+                Constant::Utf8("RUNS".into()),
+                Constant::NameAndType(7, 4),
+                Constant::FieldRef(2, 8),
+            ],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn static_initialiser_runs_once_on_first_use() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        // static { RUNS++; LIMIT = 42; }
+        let clinit = [
+            GETSTATIC as u8,
+            0,
+            9,
+            ICONST_1 as u8,
+            IADD as u8,
+            PUTSTATIC as u8,
+            0,
+            9,
+            BIPUSH as u8,
+            42,
+            PUTSTATIC as u8,
+            0,
+            6,
+            RETURN as u8,
+        ];
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Config")
+                    .with_constant_pool(config_pool())
+                    .with_static_field("LIMIT", vec![0])
+                    .with_static_field("RUNS", vec![0])
+                    .with_method(Method::new("Config", "<clinit>:()V", 2, 0, &clinit)),
+            )
+        });
+
+        // return LIMIT + LIMIT + RUNS;
+        let code = [
+            GETSTATIC as u8,
+            0,
+            6,
+            GETSTATIC as u8,
+            0,
+            6,
+            IADD as u8,
+            GETSTATIC as u8,
+            0,
+            9,
+            IADD as u8,
+            IRETURN as u8,
+        ];
+        let frame = StackFrame::new(0, 2, Arc::from(code), Arc::from("Config"));
+        assert_eq!(execute(frame)?, [85]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn new_initialises_the_class() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        // static { READY = 1; }
+        let clinit = [ICONST_1 as u8, PUTSTATIC as u8, 0, 6, RETURN as u8];
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Eager".into()),
+                Constant::Class(1),
+                Constant::Utf8("READY".into()),
+                Constant::Utf8("Z".into()),
+                Constant::NameAndType(3, 4),
+                Constant::FieldRef(2, 5),
+            ],
+            vec![],
+        );
+        let class = with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Eager")
+                    .with_constant_pool(pool)
+                    .with_static_field("READY", vec![0])
+                    .with_method(Method::new("Eager", "<clinit>:()V", 1, 0, &clinit)),
+            )
+        });
+
+        let code = [NEW as u8, 0, 2, ARETURN as u8];
+        let frame = StackFrame::new(0, 1, Arc::from(code), Arc::from("Eager"));
+        let [instance] = execute(frame)?[..] else {
+            panic!("expected a single reference");
+        };
+
+        assert_eq!(
+            with_heap(|heap| heap.classname(instance).map(String::from)),
+            Some("Eager".into())
+        );
+        assert_eq!(class.get_static("READY").unwrap().value()?, [1]);
+        Ok(())
+    }
+
+    #[test]
+    fn failed_initialisation_is_not_retried() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        // static { VALUE = ((int[]) null).length; }
+        let clinit = [
+            ACONST_NULL as u8,
+            ARRAYLENGTH as u8,
+            PUTSTATIC as u8,
+            0,
+            6,
+            RETURN as u8,
+        ];
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("Faulty".into()),
+                Constant::Class(1),
+                Constant::Utf8("VALUE".into()),
+                Constant::Utf8("I".into()),
+                Constant::NameAndType(3, 4),
+                Constant::FieldRef(2, 5),
+            ],
+            vec![],
+        );
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Faulty")
+                    .with_constant_pool(pool)
+                    .with_static_field("VALUE", vec![0])
+                    .with_method(Method::new("Faulty", "<clinit>:()V", 1, 0, &clinit)),
+            )
+        });
+
+        // return VALUE;
+        let read = || {
+            let code = [GETSTATIC as u8, 0, 6, IRETURN as u8];
+            execute(StackFrame::new(0, 1, Arc::from(code), Arc::from("Faulty")))
+        };
+        assert!(matches!(
+            read(),
+            Err(VmError::Exception(VmException { class, message }))
+                if class == "java/lang/ExceptionInInitializerError"
+                    && message.starts_with("java/lang/NullPointerException")
+        ));
+        assert!(matches!(
+            read(),
+            Err(VmError::Exception(VmException { class, .. }))
+                if class == "java/lang/NoClassDefFoundError"
+        ));
+
+        Ok(())
+    }
+
+    /// Runs `opcode` against the `Fruit` class over `reference`, returning the resulting value.
+    fn type_check(opcode: Opcode, reference: Option<&str>) -> Result<i32> {
+        let _ = MethodArea::initialise(".");
+        let pool =
+            RuntimeConstantPool::new([Constant::Utf8("Fruit".into()), Constant::Class(1)], vec![]);
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Fruit")
+                    .with_parent("java/lang/Object")
+                    .with_constant_pool(pool),
+            );
+            area.insert(Class::with_classname("Apple").with_parent("Fruit"));
+            area.insert(Class::with_classname("Stone").with_parent("java/lang/Object"));
+        });
+
+        let reference = reference.map_or(Ok(0), |class| {
+            with_mut_heap(|heap| heap.allocate_instance(Instance::without_fields(class)))
+        })?;
+
+        let code = [opcode as u8, 0, 2];
+        let mut frame = StackFrame::new(0, 1, Arc::from(code), Arc::from("Fruit"));
+        frame.push(reference)?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        process(opcode as u8, "Fruit", &mut frames)?;
+
+        let frame = frames.last_mut().unwrap();
+        Ok(frame.pop().unwrap())
+    }
+
+    #[test]
+    fn checkcast_subclass() -> Result<()> {
+        assert_ne!(type_check(CHECKCAST, Some("Apple"))?, 0);
+        assert_eq!(type_check(CHECKCAST, None)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn checkcast_unrelated_class() {
+        let result = type_check(CHECKCAST, Some("Stone"));
+        assert!(matches!(
+            result,
+            Err(VmError::Exception(VmException { class, message }))
+                if class == "java/lang/ClassCastException" && message == "Stone cannot be cast to Fruit"
+        ));
+    }
+
+    #[test]
+    fn instanceof() -> Result<()> {
+        assert_eq!(type_check(INSTANCEOF, Some("Apple"))?, 1);
+        assert_eq!(type_check(INSTANCEOF, Some("Fruit"))?, 1);
+        assert_eq!(type_check(INSTANCEOF, Some("Stone"))?, 0);
+        assert_eq!(type_check(INSTANCEOF, None)?, 0);
+        Ok(())
+    }
+
+    /// Runs `synchronized (lock) { return 3; }`, with `lock` held by the first local.
+    fn synchronized(lock: i32) -> Result<Vec<i32>> {
+        let code = [
+            ALOAD_0 as u8,
+            DUP as u8,
+            ASTORE_1 as u8,
+            MONITORENTER as u8,
+            ICONST_3 as u8,
+            ALOAD_1 as u8,
+            MONITOREXIT as u8,
+            IRETURN as u8,
+        ];
+        let mut frame = StackFrame::from_bytecode(&code, 2, 2);
+        frame.set(0, lock)?;
+
+        execute(frame)
+    }
+
+    #[test]
+    fn synchronized_block() -> Result<()> {
+        let lock = with_mut_heap(|heap| heap.allocate_instance(Instance::without_fields("Lock")))?;
+        assert_eq!(synchronized(lock)?, [3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn synchronized_on_null() {
+        assert!(matches!(
+            synchronized(0),
+            Err(VmError::Exception(exception))
+                if exception.class == "java/lang/NullPointerException"
+        ));
+    }
+
+    #[test]
+    fn reference_array_instructions() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let array = with_mut_heap(|heap| heap.allocate_array("[Ljava/lang/Object;", 3))?;
+        let value = allocate("java/lang/Object")?;
+
+        // array[1] = value; return array[1];
+        let code = [
+            ALOAD_0 as u8,
+            ICONST_1 as u8,
+            ALOAD_1 as u8,
+            AASTORE as u8,
+            ALOAD_0 as u8,
+            ICONST_1 as u8,
+            AALOAD as u8,
+            ARETURN as u8,
+        ];
+        let mut frame = StackFrame::from_bytecode(&code, 2, 3);
+        frame.set(0, array)?;
+        frame.set(1, value)?;
+        assert_eq!(execute(frame)?, [value]);
+
+        let code = [ALOAD_0 as u8, ARRAYLENGTH as u8, IRETURN as u8];
+        let mut frame = StackFrame::from_bytecode(&code, 1, 1);
+        frame.set(0, array)?;
+        assert_eq!(execute(frame)?, [3]);
+
+        let code = [ACONST_NULL as u8, ARRAYLENGTH as u8, IRETURN as u8];
+        assert!(matches!(
+            execute(StackFrame::from_bytecode(&code, 0, 1)),
+            Err(VmError::Exception(VmException { class, .. }))
+                if class == "java/lang/NullPointerException"
+        ));
+
+        Ok(())
+    }
+
+    /// Runs `throw (Object) exception;` and returns what was thrown if caught by `catch_type`.
+    fn athrow(exception: i32, catch_type: &str) -> Result<Vec<i32>> {
+        use crate::vm::runtime::method_area::ExceptionHandler;
+
+        let _ = MethodArea::initialise(".");
+        with_method_area(|area| {
+            area.insert(Class::with_classname("Failure").with_parent("java/lang/RuntimeException"));
+            area.insert(Class::with_classname("Pebble").with_parent("java/lang/Object"));
+        });
+
+        let code = [ALOAD_0 as u8, ATHROW as u8, ARETURN as u8];
+        let handlers = [ExceptionHandler {
+            start_pc: 0,
+            end_pc: 2,
+            handler_pc: 2,
+            catch_type: Some(catch_type.to_string()),
+        }];
+        let mut frame =
+            StackFrame::from_bytecode(&code, 1, 1).with_exception_handlers(Arc::from(handlers));
+        frame.set(0, exception)?;
+
+        execute(frame)
+    }
+
+    fn allocate(classname: &str) -> Result<i32> {
+        with_mut_heap(|heap| heap.allocate_instance(Instance::without_fields(classname)))
+    }
+
+    #[test]
+    fn athrow_hands_the_object_over() -> Result<()> {
+        let failure = allocate("Failure")?;
+        assert_eq!(athrow(failure, "java/lang/Exception")?, [failure]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn athrow_rejects_non_throwables() -> Result<()> {
+        let pebble = allocate("Pebble")?;
+        assert!(matches!(
+            athrow(pebble, "java/lang/Throwable"),
+            Err(VmError::Interpreter(InterpreterError::NotThrowable(class))) if class == "Pebble"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn athrow_null() {
+        assert!(matches!(
+            athrow(0, "Failure"),
+            Err(VmError::Exception(VmException { class, .. }))
+                if class == "java/lang/NullPointerException"
+        ));
+
+        // the NullPointerException is thrown from the athrow, within the handler range
+        let caught = athrow(0, "java/lang/NullPointerException").unwrap();
+        assert_ne!(caught, [0]);
+    }
+}
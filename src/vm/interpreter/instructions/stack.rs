@@ -3,7 +3,7 @@ use crate::vm::{
     interpreter::{
         StackFrames,
         instructions::opcode::Opcode::{self, *},
-        stack::{StackError, StackValue},
+        stack::{Category, StackError, StackValue},
     },
 };
 use tracing::trace;
@@ -16,114 +16,269 @@ pub(in crate::vm::interpreter::instructions) fn process(
 
     let opcode = Opcode::from(code);
     match opcode {
-        POP => {
-            let value: i32 = frame.pop().unwrap();
-            frame.next_pc();
-
-            trace!("POP -> {value}");
-        }
-
-        POP2 => {
-            let value: i32 = frame.pop().unwrap();
-            let sec_value: i32 = frame.pop().unwrap();
-            frame.next_pc();
+        POP => frame.discard(1, opcode)?,
+        // either a single category 2 value or two category 1 values
+        POP2 => frame.discard(2, opcode)?,
 
-            trace!("POP2 -> ({value}, {sec_value})");
-        }
+        DUP => frame.duplicate(1, 0, opcode)?,
+        DUP_X1 => frame.duplicate(1, 1, opcode)?,
+        // either three category 1 values, or a category 1 value over a category 2 one
+        DUP_X2 => frame.duplicate(1, 2, opcode)?,
 
-        DUP => {
-            let value: i32 = frame.pop().unwrap();
-            frame.push(value)?;
-            frame.push(value)?;
+        // the two topmost slots may hold a single `long` or `double`, which is duplicated whole
+        DUP2 => frame.duplicate(2, 0, opcode)?,
+        DUP2_X1 => frame.duplicate(2, 1, opcode)?,
+        // the two topmost slots and the two below them may each hold one category 2 value or
+        // two category 1 values, making up the four forms of the instruction
+        DUP2_X2 => frame.duplicate(2, 2, opcode)?,
 
-            frame.next_pc();
-            trace!("DUP -> {value}");
-        }
+        SWAP => {
+            // swapping would split a `long` or `double` apart
+            if frame.category(0)? == Category::Two || frame.category(1)? == Category::Two {
+                return Err(StackError::InvalidCategory(opcode.to_string()).into());
+            }
 
-        DUP_X1 => {
             let value: i32 = frame.pop().unwrap();
             let sec_value: i32 = frame.pop().unwrap();
 
             frame.push(value)?;
             frame.push(sec_value)?;
-            frame.push(value)?;
 
             frame.next_pc();
-            trace!("DUP_X1 -> ({value}, {sec_value})");
+            trace!("SWAP -> ({value}, {sec_value})");
         }
 
-        DUP_X2 => {
-            let value: i32 = frame.pop().unwrap();
-            let sec_value: i32 = frame.pop().unwrap();
-            let trd_value: i32 = frame.pop().unwrap();
+        _ => unreachable!("Tried to manipulate stack with {code} code"),
+    }
 
-            frame.push(value)?;
-            frame.push(trd_value)?;
-            frame.push(sec_value)?;
-            frame.push(value)?;
+    Ok(())
+}
 
-            frame.next_pc();
-            trace!("DUP_X2 -> ({value}, {sec_value}, {trd_value})");
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmError,
+        interpreter::{InterpreterError, StackFrame},
+    };
 
-        DUP2 => {
-            let value: i32 = frame.pop().unwrap();
-            let sec_value: i32 = frame.pop().unwrap();
+    #[test]
+    fn swap_ints() -> Result<()> {
+        let mut frame = StackFrame::from_bytecode(&[SWAP as u8], 0, 2);
+        frame.push(1)?;
+        frame.push(2)?;
 
-            frame.push(sec_value)?;
-            frame.push(value)?;
-            frame.push(sec_value)?;
-            frame.push(value)?;
+        let mut frames = StackFrames::from(vec![frame]);
+        process(SWAP as u8, &mut frames)?;
 
-            frame.next_pc();
-            trace!("DUP2 -> ({value}, {sec_value})");
-        }
+        let frame = frames.last_mut().unwrap();
+        assert_eq!(frame.pop::<i32>(), Some(1));
+        assert_eq!(frame.pop::<i32>(), Some(2));
 
-        DUP2_X1 => {
-            let value: i32 = frame.pop().unwrap();
-            let sec_value: i32 = frame.pop().unwrap();
-            let trd_value: i32 = frame.pop().unwrap();
+        Ok(())
+    }
 
-            frame.push(sec_value)?;
-            frame.push(value)?;
-            frame.push(trd_value)?;
-            frame.push(sec_value)?;
-            frame.push(value)?;
+    /// Runs `opcode` over a stack built by `setup`, returning the resulting operand stack.
+    fn run(
+        opcode: Opcode,
+        setup: impl FnOnce(&mut StackFrame) -> std::result::Result<(), StackError>,
+    ) -> Result<StackFrame> {
+        let mut frame = StackFrame::from_bytecode(&[opcode as u8], 0, 8);
+        setup(&mut frame)?;
 
-            frame.next_pc();
-            trace!("DUP2_X1 -> ({value}, {sec_value}, {trd_value})");
-        }
+        let mut frames = StackFrames::from(vec![frame]);
+        process(opcode as u8, &mut frames)?;
 
-        DUP2_X2 => {
-            let value: i32 = frame.pop().unwrap();
-            let sec_value: i32 = frame.pop().unwrap();
-            let trd_value: i32 = frame.pop().unwrap();
-            let frth_value: i32 = frame.pop().unwrap();
+        Ok(frames.quit_frame().unwrap())
+    }
 
-            frame.push(sec_value)?;
-            frame.push(value)?;
-            frame.push(frth_value)?;
-            frame.push(trd_value)?;
-            frame.push(sec_value)?;
-            frame.push(value)?;
+    #[test]
+    fn dup2_double() -> Result<()> {
+        let mut frame = run(DUP2, |frame| frame.push(-1.5f64))?;
 
-            frame.next_pc();
-            trace!("DUP2_X2 -> ({value}, {sec_value}, {trd_value}, {frth_value})");
-        }
+        assert_eq!(frame.category(0)?, Category::Two);
+        assert_eq!(frame.pop::<f64>(), Some(-1.5));
+        assert_eq!(frame.category(0)?, Category::Two);
+        assert_eq!(frame.pop::<f64>(), Some(-1.5));
+        assert_eq!(frame.pop::<f64>(), None);
+        Ok(())
+    }
 
-        SWAP => {
-            let value: i32 = frame.pop().unwrap();
-            let sec_value: i32 = frame.pop().unwrap();
+    #[test]
+    fn dup_rejects_category_two() -> Result<()> {
+        let result = run(DUP, |frame| frame.push(4i64));
 
-            frame.push(value)?;
-            frame.push(sec_value)?;
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::InvalidCategory(_)
+            )))
+        ));
+        Ok(())
+    }
 
-            frame.next_pc();
-            trace!("SWAP -> ({value}, {sec_value})");
-        }
+    #[test]
+    fn pop2_long() -> Result<()> {
+        let frame = run(POP2, |frame| {
+            frame.push(1)?;
+            frame.push(9i64)
+        })?;
+        assert_eq!(frame.operand_stack(), [1]);
 
-        _ => unreachable!("Tried to manipulate stack with {code} code"),
+        let result = run(POP2, |frame| {
+            frame.push(9i64)?;
+            frame.push(1)
+        });
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::InvalidCategory(_)
+            )))
+        ));
+        Ok(())
     }
 
-    Ok(())
+    #[test]
+    fn dup_x2_category_one() -> Result<()> {
+        let frame = run(DUP_X2, |frame| {
+            frame.push(1)?;
+            frame.push(2)?;
+            frame.push(3)
+        })?;
+
+        assert_eq!(frame.operand_stack(), [3, 1, 2, 3]);
+        assert_eq!(frame.pc, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn dup_x2_int_past_long() -> Result<()> {
+        let mut frame = run(DUP_X2, |frame| {
+            frame.push(10i64)?;
+            frame.push(3)
+        })?;
+
+        assert_eq!(frame.pop::<i32>(), Some(3));
+        assert_eq!(frame.pop::<i64>(), Some(10));
+        assert_eq!(frame.category(0)?, Category::One);
+        assert_eq!(frame.pop::<i32>(), Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn dup_x2_rejects_category_two_on_top() -> Result<()> {
+        let result = run(DUP_X2, |frame| {
+            frame.push(1)?;
+            frame.push(2.5f64)
+        });
+
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::InvalidCategory(_)
+            )))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn dup2_x2_all_category_one() -> Result<()> {
+        // form 1
+        let frame = run(DUP2_X2, |frame| {
+            frame.push(1)?;
+            frame.push(2)?;
+            frame.push(3)?;
+            frame.push(4)
+        })?;
+
+        assert_eq!(frame.operand_stack(), [3, 4, 1, 2, 3, 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn dup2_x2_double_past_ints() -> Result<()> {
+        // form 2
+        let mut frame = run(DUP2_X2, |frame| {
+            frame.push(1)?;
+            frame.push(2)?;
+            frame.push(0.5f64)
+        })?;
+
+        assert_eq!(frame.pop::<f64>(), Some(0.5));
+        assert_eq!(frame.pop::<i32>(), Some(2));
+        assert_eq!(frame.pop::<i32>(), Some(1));
+        assert_eq!(frame.category(0)?, Category::Two);
+        assert_eq!(frame.pop::<f64>(), Some(0.5));
+        Ok(())
+    }
+
+    #[test]
+    fn dup2_x2_ints_past_long() -> Result<()> {
+        // form 3
+        let mut frame = run(DUP2_X2, |frame| {
+            frame.push(-7i64)?;
+            frame.push(1)?;
+            frame.push(2)
+        })?;
+
+        assert_eq!(frame.pop::<i32>(), Some(2));
+        assert_eq!(frame.pop::<i32>(), Some(1));
+        assert_eq!(frame.pop::<i64>(), Some(-7));
+        assert_eq!(frame.pop::<i32>(), Some(2));
+        assert_eq!(frame.pop::<i32>(), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn dup2_x2_long_past_double() -> Result<()> {
+        // form 4
+        let mut frame = run(DUP2_X2, |frame| {
+            frame.push(0.25f64)?;
+            frame.push(i64::MAX)
+        })?;
+
+        assert_eq!(frame.pop::<i64>(), Some(i64::MAX));
+        assert_eq!(frame.pop::<f64>(), Some(0.25));
+        assert_eq!(frame.pop::<i64>(), Some(i64::MAX));
+        assert_eq!(frame.pop::<i64>(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn dup2_x2_rejects_split_long() -> Result<()> {
+        // the cut two slots below the top falls in the middle of the long
+        let result = run(DUP2_X2, |frame| {
+            frame.push(1)?;
+            frame.push(5i64)?;
+            frame.push(2)
+        });
+
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::InvalidCategory(_)
+            )))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn swap_rejects_category_two() -> Result<()> {
+        let mut frame = StackFrame::from_bytecode(&[SWAP as u8], 0, 3);
+        frame.push(1)?;
+        frame.push(2.5f64)?;
+
+        let mut frames = StackFrames::from(vec![frame]);
+        let err = process(SWAP as u8, &mut frames).unwrap_err();
+        assert!(matches!(
+            err,
+            VmError::Interpreter(InterpreterError::Stack(StackError::InvalidCategory(ref op))) if op == "SWAP"
+        ));
+
+        // the double is left untouched
+        let frame = frames.last_mut().unwrap();
+        assert_eq!(frame.pop::<f64>(), Some(2.5));
+
+        Ok(())
+    }
 }
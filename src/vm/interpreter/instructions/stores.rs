@@ -1,11 +1,17 @@
 use crate::vm::{
-    Result,
+    Result, VmException,
     interpreter::{
-        StackFrames,
+        StackFrame, StackFrames,
         instructions::opcode::{Opcode, Opcode::*},
         stack::StackError,
     },
+    runtime::{
+        RuntimeError,
+        heap::{with_heap, with_mut_heap},
+        method_area::with_method_area,
+    },
 };
+use tracing::trace;
 
 pub(in crate::vm::interpreter::instructions) fn process(
     code: u8,
@@ -15,7 +21,11 @@ pub(in crate::vm::interpreter::instructions) fn process(
 
     let opcode = Opcode::from(code);
     match opcode {
-        ISTORE | ASTORE => frame.positional_store::<i32>(opcode),
+        ISTORE => frame.positional_store::<i32>(opcode),
+        ASTORE => {
+            let position = frame.get_next_byte();
+            frame.store_reference_local(position, opcode)
+        }
         LSTORE => frame.positional_store::<i64>(opcode),
         FSTORE => frame.positional_store::<f32>(opcode),
         DSTORE => frame.positional_store::<f64>(opcode),
@@ -37,10 +47,11 @@ pub(in crate::vm::interpreter::instructions) fn process(
         }
 
         ASTORE_0 | ASTORE_1 | ASTORE_2 | ASTORE_3 => {
-            frame.store::<i32, _>(code - ASTORE_0 as u8, opcode)
+            frame.store_reference_local(code - ASTORE_0 as u8, opcode)
         }
 
-        IALOAD | AASTORE | BASTORE | CASTORE | SASTORE => frame.store_array::<i32>(opcode),
+        AASTORE => store_reference(frame, opcode),
+        IASTORE | BASTORE | CASTORE | SASTORE => frame.store_array::<i32>(opcode),
         LASTORE => frame.store_array::<i64>(opcode),
         FASTORE => frame.store_array::<f32>(opcode),
         DASTORE => frame.store_array::<f64>(opcode),
@@ -48,3 +59,126 @@ pub(in crate::vm::interpreter::instructions) fn process(
         _ => unreachable!("Tried to store {code} code"),
     }
 }
+
+/// Stores a reference into a reference array, making sure it is assignable to the array element
+/// type, as the array may be a subtype of the one the bytecode was verified against.
+fn store_reference(frame: &mut StackFrame, code: Opcode) -> Result<()> {
+    let value: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+    let idx: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+    let array_idx: i32 = frame.pop().ok_or(StackError::EmptyStack)?;
+    if array_idx == 0 {
+        return Err(VmException::null_pointer(format!("cannot {code} into a null array")).into());
+    }
+
+    let classnames = with_heap(|heap| {
+        let element = heap.array_element_name(array_idx)?.to_string();
+        let value = heap.classname(value).map(String::from);
+        Some((element, value))
+    });
+    let Some((element, value_class)) = classnames else {
+        return Err(RuntimeError::InvalidArrayAccess(idx as usize).into());
+    };
+
+    // null can be stored into any reference array
+    if let Some(value_class) = value_class
+        && !with_method_area(|area| area.is_assignable(&value_class, &element))?
+    {
+        return Err(VmException::new("java/lang/ArrayStoreException", value_class).into());
+    }
+
+    with_mut_heap(|heap| heap.set_array_reference(array_idx, idx, value))?;
+    frame.next_pc();
+
+    trace!("{code} -> array_idx={array_idx}, index={idx}, value={value}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmError,
+        interpreter::{InterpreterError, execute, stack::Reference},
+        runtime::{
+            heap::Instance,
+            method_area::{Class, MethodArea},
+        },
+    };
+
+    fn aastore(array: &str, value: &str) -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        with_method_area(|area| {
+            area.insert(Class::with_classname("Animal").with_parent("java/lang/Object"));
+            area.insert(Class::with_classname("Dog").with_parent("Animal"));
+            area.insert(Class::with_classname("Brick").with_parent("java/lang/Object"));
+        });
+
+        let (array, value) = with_mut_heap(|heap| {
+            let array = heap.allocate_array(array, 2)?;
+            let value = heap.allocate_instance(Instance::without_fields(value))?;
+            Ok::<_, VmError>((array, value))
+        })?;
+
+        let mut frame = StackFrame::from_bytecode(&[AASTORE as u8], 0, 3);
+        frame.push(array)?;
+        frame.push(1)?;
+        frame.push(value)?;
+
+        process(AASTORE as u8, &mut StackFrames::from(vec![frame]))
+    }
+
+    #[test]
+    fn aastore_subclass() -> Result<()> {
+        aastore("[LAnimal;", "Dog")
+    }
+
+    #[test]
+    fn aastore_incompatible_element() {
+        let result = aastore("[LAnimal;", "Brick");
+        assert!(matches!(
+            result,
+            Err(VmError::Exception(VmException { class, message }))
+                if class == "java/lang/ArrayStoreException" && message == "Brick"
+        ));
+    }
+
+    #[test]
+    fn reference_local_round_trip() -> Result<()> {
+        // Object copy = original; return copy == original ? 1 : 0;
+        let code = [
+            ALOAD_0 as u8,
+            ASTORE_1 as u8,
+            ALOAD_1 as u8,
+            ALOAD_0 as u8,
+            IF_ACMPEQ as u8,
+            0x00,
+            0x05,
+            ICONST_0 as u8,
+            IRETURN as u8,
+            ICONST_1 as u8,
+            IRETURN as u8,
+        ];
+        let original = with_mut_heap(|heap| {
+            heap.allocate_instance(Instance::without_fields("java/lang/Object"))
+        })?;
+
+        let mut frame = StackFrame::from_bytecode(&code, 2, 2);
+        frame.set(0, Reference(original))?;
+        assert_eq!(execute(frame)?, [1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn astore_rejects_category_two_values() {
+        let mut frame = StackFrame::from_bytecode(&[ASTORE_0 as u8], 2, 2);
+        frame.push(1i64).unwrap();
+
+        assert!(matches!(
+            process(ASTORE_0 as u8, &mut StackFrames::from(vec![frame])),
+            Err(VmError::Interpreter(InterpreterError::Stack(
+                StackError::NotAReference(_)
+            )))
+        ));
+    }
+}
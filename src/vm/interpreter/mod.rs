@@ -1,30 +1,220 @@
 use thiserror::Error;
+use tracing::{Level, trace, trace_span};
+use tracing_subscriber::filter::Directive;
 
 use crate::vm::{
-    Result, VmError,
-    interpreter::stack::{StackError, StackFrames, ValueRef},
+    ExitStatus, Result, VmError,
+    interpreter::{
+        executor::Executor,
+        instructions::InstructionCategory,
+        native::natives,
+        stack::{StackError, StackFrames, ValueRef},
+    },
+    runtime::{
+        RuntimeError,
+        heap::with_mut_heap,
+        method_area::{MethodArea, with_method_area},
+    },
 };
 
 pub(in crate::vm) use stack::StackFrame;
+pub use stack::Value;
+pub use trace::{ExecutionTrace, TraceEntry};
 
+pub mod disassembler;
+mod exception;
 mod executor;
 mod instructions;
+mod native;
 mod stack;
 pub mod static_method;
+#[cfg(test)]
+mod testing;
+mod trace;
+pub(in crate::vm) mod verifier;
 
 #[derive(Error, Debug)]
 pub enum InterpreterError {
     #[error(transparent)]
     Stack(#[from] stack::StackError),
+
+    #[error(transparent)]
+    Verify(#[from] verifier::VerifyError),
+
+    #[error("Native method {0} was invoked with unexpected arguments")]
+    NativeArguments(String),
+
+    #[error("Constant pool entry {index} cannot be loaded: {reason}")]
+    UnloadableConstant { index: u16, reason: String },
+
+    #[error("Execution did not complete within {0} instructions")]
+    InstructionBudgetExceeded(usize),
+
+    #[error("{opcode} is not allowed in class files of version {version}")]
+    UnsupportedInstruction { opcode: String, version: u16 },
+
+    #[error("Unknown instruction category {0} to trace")]
+    UnknownTraceCategory(String),
+
+    #[error("{0} is not a subclass of java/lang/Throwable and cannot be thrown")]
+    NotThrowable(String),
+
+    #[error("Unknown opcode {0:#04x}")]
+    UnknownOpcode(u8),
+
+    #[error("WIDE cannot modify {0}")]
+    InvalidWideOperand(String),
+
+    #[error("NEWARRAY cannot create arrays of type {0}")]
+    InvalidArrayType(u8),
+
+    #[error("{0} is not implemented yet")]
+    UnimplementedInstruction(String),
+}
+
+/// How integer `add`, `sub` and `mul` instructions deal with overflow.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ArithmeticMode {
+    /// Wraps around on overflow, as mandated by the JVMS.
+    #[default]
+    Wrapping,
+    /// Throws `java/lang/ArithmeticException` on overflow, like `Math.addExact` does, which the
+    /// program may catch. Useful to spot unintended overflows when analysing a program.
+    Checked,
+}
+
+/// Filter directives tracing the execution of the instructions of the comma-separated
+/// `categories` only, e.g. `references,control` for invocations and branches.
+pub(in crate::vm) fn trace_directives(categories: &str) -> Result<Vec<Directive>> {
+    categories
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let category = InstructionCategory::from_name(name)
+                .ok_or_else(|| InterpreterError::UnknownTraceCategory(name.to_string()))?;
+            let directive = format!("[instruction{{category={}}}]=trace", category.name());
+
+            Ok(directive
+                .parse()
+                .expect("directives of categories are well-formed"))
+        })
+        .collect()
+}
+
+/// Signature of the entry point of a Java program.
+const MAIN_METHOD: &str = "main:([Ljava/lang/String;)V";
+
+/// Runs the `main` method of `classname` without arguments, until it either returns, which is a
+/// successful exit, or calls `System.exit`.
+/// Integer overflows are handled according to `mode`.
+pub(in crate::vm) fn run_main(classname: &str, mode: ArithmeticMode) -> Result<ExitStatus> {
+    let method = with_method_area(|area| area.get(classname))?.get_method(MAIN_METHOD)?;
+    let args = with_mut_heap(|heap| heap.allocate_array("[Ljava/lang/String;", 0))?;
+
+    let mut frame = method.new_frame()?;
+    frame.store_variable(0, args)?;
+
+    match execute_with_arithmetic(frame, mode) {
+        Ok(_) => Ok(ExitStatus(0)),
+        Err(VmError::Exit(status)) => Ok(ExitStatus(status)),
+        Err(error) => Err(error),
+    }
+}
+
+/// Runs the method `name` of `classname` with the given `descriptor`, passing `args`, which
+/// start with the receiver for instance methods, and returns its result, if not `void`. The
+/// execution is bounded by `max_instructions`, if any, as in [`execute_bounded`], and integer
+/// overflows are handled according to `mode`.
+pub(in crate::vm) fn invoke(
+    classname: &str,
+    name: &str,
+    descriptor: &str,
+    args: &[Value],
+    max_instructions: Option<usize>,
+    trace: Option<&mut ExecutionTrace>,
+    mode: ArithmeticMode,
+) -> Result<Option<Value>> {
+    if !MethodArea::is_initialised() {
+        return Err(RuntimeError::MethodAreaUninitialised.into());
+    }
+
+    let signature = format!("{name}:{descriptor}");
+    let slots = Executor::execute(classname, &signature, args, max_instructions, trace, mode)?;
+    let return_type = descriptor
+        .rsplit_once(')')
+        .and_then(|(_, return_type)| return_type.chars().next());
+
+    Ok(return_type.and_then(|ty| Value::from_slots(ty, &slots)))
+}
+
+/// Registers `method` as the implementation of the native method `classname.signature`, which is
+/// given the marshalled arguments only, as frames of the interpreter aren't exposed.
+pub(in crate::vm) fn register_native(
+    classname: &str,
+    signature: &str,
+    method: impl Fn(&[Value]) -> Result<Option<Value>> + Send + Sync + 'static,
+) {
+    natives().register(
+        classname,
+        signature,
+        move |_: &mut StackFrame, args: &[Value]| method(args),
+    );
 }
 
 pub(in crate::vm::interpreter) fn execute(frame: StackFrame) -> Result<Vec<ValueRef>> {
-    let mut frames = StackFrames::from(vec![frame]);
-    let mut last = vec![];
+    run(StackFrames::from(vec![frame]), None, None)
+}
+
+/// Same as [`execute`], with integer overflows handled according to `mode`.
+pub(in crate::vm::interpreter) fn execute_with_arithmetic(
+    frame: StackFrame,
+    mode: ArithmeticMode,
+) -> Result<Vec<ValueRef>> {
+    let frames = StackFrames::from(vec![frame]).with_arithmetic_mode(mode);
+    run(frames, None, None)
+}
+
+/// Same as [`execute`], but gives up with [`InterpreterError::InstructionBudgetExceeded`] once
+/// `max_instructions` instructions were dispatched without the execution completing.
+pub(in crate::vm::interpreter) fn execute_bounded(
+    frame: StackFrame,
+    max_instructions: usize,
+) -> Result<Vec<ValueRef>> {
+    run(StackFrames::from(vec![frame]), None, Some(max_instructions))
+}
+
+/// Same as [`execute`], also recording every dispatched instruction into the returned
+/// [`ExecutionTrace`].
+pub(in crate::vm::interpreter) fn execute_traced(
+    frame: StackFrame,
+) -> Result<(Vec<ValueRef>, ExecutionTrace)> {
+    let mut trace = ExecutionTrace::default();
+    let result = run(StackFrames::from(vec![frame]), Some(&mut trace), None)?;
+
+    Ok((result, trace))
+}
+
+fn run(
+    mut frames: StackFrames,
+    mut trace: Option<&mut ExecutionTrace>,
+    max_instructions: Option<usize>,
+) -> Result<Vec<ValueRef>> {
+    let mut dispatched = 0;
 
     while !frames.is_empty() {
+        if let Some(max) = max_instructions
+            && dispatched >= max
+        {
+            return Err(InterpreterError::InstructionBudgetExceeded(max).into());
+        }
+        dispatched += 1;
+
         let (classname, code, pc) = {
             let frame = frames.last().ok_or(StackError::EmptyStack)?;
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.record(frame);
+            }
 
             (
                 frame.current_classname.to_string(),
@@ -33,10 +223,24 @@ pub(in crate::vm::interpreter) fn execute(frame: StackFrame) -> Result<Vec<Value
             )
         };
 
-        instructions::process(code, &classname, &mut frames)?
+        // per-instruction events can be filtered by category, e.g. `[instruction{category=math}]`
+        let category = InstructionCategory::try_from(code)?;
+        let _span = trace_span!("instruction", category = category.name()).entered();
+
+        match instructions::process(category, code, &classname, &mut frames) {
+            Err(VmError::Exception(exception)) => exception::throw(&mut frames, pc, exception)?,
+            result => result?,
+        }
+
+        // formatting the frame isn't free, so only do it if someone is listening
+        if tracing::enabled!(Level::TRACE)
+            && let Some(frame) = frames.last()
+        {
+            trace!("{classname} {}", frame.debug_state());
+        }
     }
 
-    Ok(last)
+    Ok(frames.take_result())
 }
 
 impl From<StackError> for VmError {
@@ -44,3 +248,364 @@ impl From<StackError> for VmError {
         Self::Interpreter(InterpreterError::Stack(value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        VmException,
+        interpreter::{
+            instructions::opcode::Opcode::{self, *},
+            testing::Output,
+        },
+        runtime::{
+            constant_pool::{Constant, RuntimeConstantPool},
+            method_area::{Class, ExceptionHandler, Method, MethodArea},
+        },
+    };
+    use std::sync::Arc;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    #[test]
+    fn invoke_static_method() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let add = [ILOAD_0 as u8, ILOAD_1 as u8, IADD as u8, IRETURN as u8];
+        let nothing = [RETURN as u8];
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Calculator")
+                    .with_method(Method::new("Calculator", "add:(II)I", 2, 2, &add))
+                    .with_method(Method::new("Calculator", "reset:()V", 0, 0, &nothing)),
+            )
+        });
+
+        let args = [Value::Int(3), Value::Int(4)];
+        let result = crate::vm::invoke("Calculator", "add", "(II)I", &args)?;
+        assert_eq!(result, Some(Value::Int(7)));
+        assert_eq!(crate::vm::invoke("Calculator", "reset", "()V", &[])?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_with_checked_arithmetic() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let add = [ILOAD_0 as u8, ILOAD_1 as u8, IADD as u8, IRETURN as u8];
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Overflowing").with_method(Method::new(
+                    "Overflowing",
+                    "add:(II)I",
+                    2,
+                    2,
+                    &add,
+                )),
+            )
+        });
+
+        let args = [Value::Int(i32::MAX), Value::Int(1)];
+        let invoke =
+            |mode| crate::vm::invoke_with_arithmetic("Overflowing", "add", "(II)I", &args, mode);
+        assert_eq!(
+            invoke(ArithmeticMode::Wrapping)?,
+            Some(Value::Int(i32::MIN))
+        );
+        assert!(matches!(
+            invoke(ArithmeticMode::Checked),
+            Err(VmError::Exception(VmException { class, .. }))
+                if class == "java/lang/ArithmeticException"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_registered_native() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let twice = Method::without_code("Registered", "twice:(I)I", true);
+        with_method_area(|area| {
+            area.insert(Class::with_classname("Registered").with_method(twice))
+        });
+
+        crate::vm::register_native("Registered", "twice:(I)I", |args| match args {
+            [Value::Int(value)] => Ok(Some(Value::Int(value * 2))),
+            _ => Err(InterpreterError::NativeArguments("twice".into()).into()),
+        });
+        let result = crate::vm::invoke("Registered", "twice", "(I)I", &[Value::Int(21)])?;
+        assert_eq!(result, Some(Value::Int(42)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_within_budget() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        // static void spin() { while (true); }
+        let spin = [GOTO as u8, 0x00, 0x00];
+        let answer = [BIPUSH as u8, 42, IRETURN as u8];
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Spinner")
+                    .with_method(Method::new("Spinner", "spin:()V", 0, 0, &spin))
+                    .with_method(Method::new("Spinner", "answer:()I", 1, 0, &answer)),
+            )
+        });
+
+        let result = crate::vm::invoke_bounded("Spinner", "spin", "()V", &[], 1000);
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(
+                InterpreterError::InstructionBudgetExceeded(1000)
+            ))
+        ));
+
+        let answer = crate::vm::invoke_bounded("Spinner", "answer", "()I", &[], 2)?;
+        assert_eq!(answer, Some(Value::Int(42)));
+        Ok(())
+    }
+
+    #[test]
+    fn trace_records_opcode_sequence() -> Result<()> {
+        // return 2 + 3
+        let code = [ICONST_2 as u8, ICONST_3 as u8, IADD as u8, IRETURN as u8];
+        let frame = StackFrame::from_bytecode(&code, 0, 2);
+
+        let (result, trace) = execute_traced(frame)?;
+        assert_eq!(result, [5]);
+
+        let opcodes: Vec<Opcode> = trace.opcodes().collect();
+        assert_eq!(opcodes, [ICONST_2, ICONST_3, IADD, IRETURN]);
+
+        let stacks: Vec<&[ValueRef]> = trace.entries().iter().map(|e| &e.stack[..]).collect();
+        assert_eq!(stacks, [&[][..], &[2], &[2, 3], &[5]]);
+        assert_eq!(&*trace.entries()[0].classname, "Test");
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_traced() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let add = [ILOAD_0 as u8, ILOAD_1 as u8, IADD as u8, IRETURN as u8];
+        with_method_area(|area| {
+            area.insert(Class::with_classname("Traced").with_method(Method::new(
+                "Traced",
+                "add:(II)I",
+                2,
+                2,
+                &add,
+            )))
+        });
+
+        let args = [Value::Int(3), Value::Int(4)];
+        let (result, trace) = crate::vm::invoke_traced("Traced", "add", "(II)I", &args)?;
+        assert_eq!(result, Some(Value::Int(7)));
+
+        let opcodes: Vec<Opcode> = trace.opcodes().collect();
+        assert_eq!(opcodes, [ILOAD_0, ILOAD_1, IADD, IRETURN]);
+        assert!(trace.entries().iter().all(|e| &*e.classname == "Traced"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn trace_prints_stack_snapshots() -> Result<()> {
+        let output = Output::default();
+        let writer = output.clone();
+        let _guard = tracing_subscriber::fmt()
+            .with_max_level(Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish()
+            .set_default();
+
+        // return 1 + 1
+        let code = [ICONST_1 as u8, DUP as u8, IADD as u8, IRETURN as u8];
+        let frame = StackFrame::from_bytecode(&code, 1, 2);
+        assert_eq!(execute(frame)?, [2]);
+
+        let output = output.contents();
+        let snapshots: Vec<&str> = output
+            .lines()
+            .filter_map(|line| line.split_once("Test ").map(|(_, state)| state))
+            .collect();
+        assert_eq!(
+            snapshots,
+            [
+                "stack=[1] locals=[0]",
+                "stack=[1, 1] locals=[0]",
+                "stack=[2] locals=[0]",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn trace_filtered_by_category() -> Result<()> {
+        let mut filter = tracing_subscriber::EnvFilter::new("off");
+        for directive in trace_directives("math")? {
+            filter = filter.add_directive(directive);
+        }
+
+        let output = Output::default();
+        let writer = output.clone();
+        let _guard = tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish()
+            .set_default();
+
+        // return i + 1
+        let code = [ILOAD_0 as u8, ICONST_1 as u8, IADD as u8, IRETURN as u8];
+        let mut frame = StackFrame::from_bytecode(&code, 1, 2);
+        frame.set(0, 4)?;
+        assert_eq!(execute(frame)?, [5]);
+
+        let output = output.contents();
+        assert!(output.contains("IADD -> (4, 1) -> 5"), "{output}");
+        assert!(!output.contains("ILOAD"), "{output}");
+        assert!(!output.contains("ICONST"), "{output}");
+
+        assert!(matches!(
+            trace_directives("math,jumps"),
+            Err(VmError::Interpreter(InterpreterError::UnknownTraceCategory(name))) if name == "jumps"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn main_exit_status() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8("java/lang/Shutdown".into()),
+                Constant::Class(1),
+                Constant::Utf8("halt0".into()),
+                Constant::Utf8("(I)V".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+                Constant::Utf8("Exiting".into()),
+                Constant::Class(7),
+                Constant::Utf8("quit".into()),
+                Constant::Utf8("()V".into()),
+                Constant::NameAndType(9, 10),
+                Constant::MethodRef(8, 11),
+            ],
+            vec![],
+        );
+        // static void quit() { Shutdown.halt0(3); }, where System.exit ends up
+        let quit = [ICONST_3 as u8, INVOKESTATIC as u8, 0, 6, RETURN as u8];
+        // public static void main(String[] args) { quit(); }
+        let main = [INVOKESTATIC as u8, 0, 12, RETURN as u8];
+        with_method_area(|area| {
+            area.insert(Class::with_classname("java/lang/Shutdown").with_method(
+                Method::without_code("java/lang/Shutdown", "halt0:(I)V", true),
+            ));
+            area.insert(
+                Class::with_classname("Exiting")
+                    .with_constant_pool(pool)
+                    .with_method(Method::new("Exiting", "quit:()V", 1, 0, &quit))
+                    .with_method(Method::new("Exiting", MAIN_METHOD, 0, 1, &main)),
+            );
+            area.insert(Class::with_classname("Returning").with_method(Method::new(
+                "Returning",
+                MAIN_METHOD,
+                0,
+                1,
+                &[RETURN as u8],
+            )));
+        });
+
+        let status = run_main("Exiting", ArithmeticMode::Wrapping)?;
+        assert_eq!(status.code(), 3);
+        assert!(!status.success());
+
+        assert!(run_main("Returning", ArithmeticMode::Wrapping)?.success());
+        Ok(())
+    }
+
+    #[test]
+    fn budget_stops_infinite_loop() {
+        // GOTO 0
+        let code = [GOTO as u8, 0x00, 0x00];
+        let frame = StackFrame::from_bytecode(&code, 0, 0);
+
+        let result = execute_bounded(frame, 100);
+        assert!(matches!(
+            result,
+            Err(VmError::Interpreter(
+                InterpreterError::InstructionBudgetExceeded(100)
+            ))
+        ));
+    }
+
+    #[test]
+    fn unknown_opcodes_are_rejected() {
+        let frame = StackFrame::from_bytecode(&[ICONST_1 as u8, 0xFE], 0, 1);
+        assert!(matches!(
+            execute(frame),
+            Err(VmError::Interpreter(InterpreterError::UnknownOpcode(0xFE)))
+        ));
+    }
+
+    #[test]
+    fn arithmetic_mode_applies_to_the_whole_run() -> Result<()> {
+        // return 32767 * 32767 * 4
+        let code = [
+            SIPUSH as u8,
+            0x7F,
+            0xFF,
+            SIPUSH as u8,
+            0x7F,
+            0xFF,
+            IMUL as u8,
+            ICONST_4 as u8,
+            IMUL as u8,
+            IRETURN as u8,
+        ];
+
+        let frame = StackFrame::from_bytecode(&code, 0, 2);
+        let wrapped = execute_with_arithmetic(frame, ArithmeticMode::Wrapping)?;
+        assert_eq!(wrapped, [32767i32.wrapping_mul(32767).wrapping_mul(4)]);
+
+        let frame = StackFrame::from_bytecode(&code, 0, 2);
+        let result = execute_with_arithmetic(frame, ArithmeticMode::Checked);
+        assert!(matches!(
+            result,
+            Err(VmError::Exception(VmException { class, message }))
+                if class == "java/lang/ArithmeticException" && message == "integer overflow"
+        ));
+
+        // catch (ArithmeticException e) { return -1; }
+        let _ = MethodArea::initialise(".");
+        let mut code = code.to_vec();
+        code.extend([POP as u8, ICONST_M1 as u8, IRETURN as u8]);
+        let handlers = [ExceptionHandler {
+            start_pc: 0,
+            end_pc: 10,
+            handler_pc: 10,
+            catch_type: Some("java/lang/ArithmeticException".into()),
+        }];
+        let frame =
+            StackFrame::from_bytecode(&code, 0, 2).with_exception_handlers(Arc::from(handlers));
+        assert_eq!(
+            execute_with_arithmetic(frame, ArithmeticMode::Checked)?,
+            [-1]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn budget_allows_completion() -> Result<()> {
+        let code = [ICONST_2 as u8, ICONST_3 as u8, IADD as u8, IRETURN as u8];
+        let frame = StackFrame::from_bytecode(&code, 0, 2);
+
+        // exactly the four dispatched instructions
+        assert_eq!(execute_bounded(frame, 4)?, [5]);
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+//! Registry of native methods, i.e. methods declared `native` in Java whose implementation is
+//! provided by the VM itself rather than by bytecode.
+
+use crate::vm::{
+    Result, VmError, VmException,
+    interpreter::{InterpreterError, StackFrame, stack::Value},
+    runtime::{heap::with_heap, method_area::with_method_area},
+};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+pub(super) const PRINT_STREAM: &str = "java/io/PrintStream";
+
+/// Methods of `java/io/PrintStream` the VM implements for its own `System.out` and `System.err`,
+/// which are allocated without fields, so the ones from the JDK can't run on them.
+const PRINT_STREAM_METHODS: [&str; 7] = [
+    "print:(I)V",
+    "print:(J)V",
+    "print:(Ljava/lang/String;)V",
+    "println:(I)V",
+    "println:(J)V",
+    "println:(Ljava/lang/String;)V",
+    "println:()V",
+];
+
+static NATIVES: Lazy<NativeRegistry> = Lazy::new(NativeRegistry::with_builtins);
+
+/// Implementation of a native method.
+///
+/// It receives the frame of the invocation and the already marshalled arguments, the receiver
+/// being the first one for instance methods, and returns the value produced by the method, if any.
+pub(super) trait NativeMethod: Send + Sync {
+    fn invoke(&self, frame: &mut StackFrame, args: &[Value]) -> Result<Option<Value>>;
+}
+
+/// Native methods keyed by their owning class and signature, e.g. `java/lang/Object.hashCode:()I`.
+pub(super) struct NativeRegistry {
+    methods: DashMap<String, Arc<dyn NativeMethod>>,
+}
+
+impl<F> NativeMethod for F
+where
+    F: Fn(&mut StackFrame, &[Value]) -> Result<Option<Value>> + Send + Sync,
+{
+    fn invoke(&self, frame: &mut StackFrame, args: &[Value]) -> Result<Option<Value>> {
+        self(frame, args)
+    }
+}
+
+pub(super) fn natives() -> &'static NativeRegistry {
+    &NATIVES
+}
+
+impl NativeRegistry {
+    fn with_builtins() -> Self {
+        let registry = Self {
+            methods: DashMap::new(),
+        };
+
+        registry.register("java/lang/Object", "registerNatives:()V", register_natives);
+        registry.register("java/lang/System", "registerNatives:()V", register_natives);
+        registry.register("java/lang/Object", "hashCode:()I", hash_code);
+        // shutdown hooks and finalizers aren't supported, so the VM exits right away
+        registry.register("java/lang/Shutdown", "halt0:(I)V", exit);
+        for signature in PRINT_STREAM_METHODS {
+            // the type of the only parameter, if any, e.g. `I` for `print:(I)V`
+            let ty = signature
+                .split_once('(')
+                .and_then(|(_, params)| params.chars().next())
+                .filter(|&ty| ty != ')');
+            let newline = signature.starts_with("println");
+            registry.register(PRINT_STREAM, signature, print(ty, newline));
+        }
+        registry.register(
+            "java/lang/Float",
+            "floatToRawIntBits:(F)I",
+            float_to_raw_int_bits,
+        );
+        registry.register(
+            "java/lang/Double",
+            "doubleToRawLongBits:(D)J",
+            double_to_raw_long_bits,
+        );
+
+        registry
+    }
+
+    /// Registers `method` as the implementation of `classname.signature`, replacing any
+    /// previously registered one.
+    pub fn register(&self, classname: &str, signature: &str, method: impl NativeMethod + 'static) {
+        self.methods
+            .insert(Self::key(classname, signature), Arc::new(method));
+    }
+
+    pub fn get(&self, classname: &str, signature: &str) -> Option<Arc<dyn NativeMethod>> {
+        self.methods
+            .get(&Self::key(classname, signature))
+            .map(|method| Arc::clone(method.value()))
+    }
+
+    /// Invokes the native registered for `classname.signature`, raising `UnsatisfiedLinkError`
+    /// when there's none.
+    pub fn invoke(
+        &self,
+        classname: &str,
+        signature: &str,
+        frame: &mut StackFrame,
+        args: &[Value],
+    ) -> Result<Option<Value>> {
+        // the entry is cloned out so the map isn't locked while the native runs
+        let method = self.get(classname, signature).ok_or_else(|| {
+            VmException::new(
+                "java/lang/UnsatisfiedLinkError",
+                Self::key(classname, signature),
+            )
+        })?;
+
+        method.invoke(frame, args)
+    }
+
+    fn key(classname: &str, signature: &str) -> String {
+        format!("{classname}.{signature}")
+    }
+}
+
+fn register_natives(_: &mut StackFrame, _: &[Value]) -> Result<Option<Value>> {
+    Ok(None)
+}
+
+fn hash_code(_: &mut StackFrame, args: &[Value]) -> Result<Option<Value>> {
+    // the identity of an object is its heap reference
+    Ok(args.first().copied())
+}
+
+fn exit(_: &mut StackFrame, args: &[Value]) -> Result<Option<Value>> {
+    match args {
+        [Value::Int(status)] => Err(VmError::Exit(*status)),
+        _ => Err(InterpreterError::NativeArguments("exit".into()).into()),
+    }
+}
+
+/// `PrintStream.print` or `println`, given `newline`, for a value of the field descriptor type
+/// `ty`, or for no value at all if `None`. Only the print streams of the VM are supported.
+fn print(ty: Option<char>, newline: bool) -> impl NativeMethod {
+    move |_: &mut StackFrame, args: &[Value]| {
+        let invalid = || InterpreterError::NativeArguments("print".into());
+
+        let (stream, value) = match args {
+            [Value::Int(receiver), value @ ..] => {
+                let stream = with_method_area(|area| area.standard_stream_of(*receiver));
+                (stream.ok_or_else(invalid)?, value)
+            }
+            _ => return Err(invalid().into()),
+        };
+        let mut text = match (ty, value) {
+            (None, []) => String::new(),
+            (Some('I'), [Value::Int(int)]) => int.to_string(),
+            (Some('J'), [Value::Long(long)]) => long.to_string(),
+            (Some('L'), [Value::Int(0)]) => "null".to_string(),
+            (Some('L'), [Value::Int(string)]) => with_heap(|heap| heap.get_string(*string))?,
+            _ => return Err(invalid().into()),
+        };
+        if newline {
+            text.push('\n');
+        }
+
+        with_method_area(|area| area.print(stream, &text));
+        Ok(None)
+    }
+}
+
+fn float_to_raw_int_bits(_: &mut StackFrame, args: &[Value]) -> Result<Option<Value>> {
+    match args {
+        [Value::Float(value)] => Ok(Some(Value::Int(value.to_bits() as i32))),
+        _ => Err(InterpreterError::NativeArguments("floatToRawIntBits".into()).into()),
+    }
+}
+
+fn double_to_raw_long_bits(_: &mut StackFrame, args: &[Value]) -> Result<Option<Value>> {
+    match args {
+        [Value::Double(value)] => Ok(Some(Value::Long(value.to_bits() as i64))),
+        _ => Err(InterpreterError::NativeArguments("doubleToRawLongBits".into()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{
+        interpreter::{execute, instructions::opcode::Opcode::*, testing::Output},
+        runtime::{
+            constant_pool::{Constant, RuntimeConstantPool},
+            heap::{Instance, with_mut_heap},
+            method_area::{Class, MethodArea, Stream},
+        },
+    };
+
+    #[test]
+    fn register_and_invoke_native() -> Result<()> {
+        let mut frame = StackFrame::new(0, 0, Arc::default(), Arc::from("Fake"));
+        natives().register("Fake", "answer:()I", |_: &mut StackFrame, _: &[Value]| {
+            Ok(Some(Value::Int(42)))
+        });
+
+        let value = natives().invoke("Fake", "answer:()I", &mut frame, &[])?;
+        assert_eq!(value, Some(Value::Int(42)));
+
+        let bits = natives().invoke(
+            "java/lang/Float",
+            "floatToRawIntBits:(F)I",
+            &mut frame,
+            &[Value::Float(1.0)],
+        )?;
+        assert_eq!(bits, Some(Value::Int(0x3f80_0000)));
+
+        assert!(
+            natives()
+                .invoke("Fake", "missing:()V", &mut frame, &[])
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn println_writes_to_the_sink() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let pool = RuntimeConstantPool::new(
+            [
+                Constant::Utf8(PRINT_STREAM.into()),
+                Constant::Class(1),
+                Constant::Utf8("println".into()),
+                Constant::Utf8("(Ljava/lang/String;)V".into()),
+                Constant::NameAndType(3, 4),
+                Constant::MethodRef(2, 5),
+                Constant::Utf8("(J)V".into()),
+                Constant::NameAndType(3, 7),
+                Constant::MethodRef(2, 8),
+            ],
+            vec![],
+        );
+        with_method_area(|area| {
+            area.insert(Class::with_classname("Greeter").with_constant_pool(pool))
+        });
+
+        let output = Output::default();
+        with_method_area(|area| area.set_sink(Stream::Err, Box::new(output.clone())));
+        let err = with_method_area(|area| area.print_stream(Stream::Err))?;
+        let hello = with_mut_heap(|heap| heap.allocate_string("hello"))?;
+
+        // System.err.println("hello"); System.err.println(1L);
+        let code = [
+            ALOAD_0 as u8,
+            ALOAD_1 as u8,
+            INVOKEVIRTUAL as u8,
+            0,
+            6,
+            ALOAD_0 as u8,
+            LCONST_1 as u8,
+            INVOKEVIRTUAL as u8,
+            0,
+            9,
+            RETURN as u8,
+        ];
+        let mut frame = StackFrame::new(2, 3, Arc::from(code), Arc::from("Greeter"));
+        frame.set(0, err)?;
+        frame.set(1, hello)?;
+        execute(frame)?;
+        assert_eq!(output.contents(), "hello\n1\n");
+
+        // print streams created by the program run the JDK's methods, which aren't loaded here
+        let other = Instance::without_fields(PRINT_STREAM);
+        let other = with_mut_heap(|heap| heap.allocate_instance(other))?;
+        let mut frame = StackFrame::new(2, 3, Arc::from(code), Arc::from("Greeter"));
+        frame.set(0, other)?;
+        frame.set(1, hello)?;
+        assert!(execute(frame).is_err());
+        assert_eq!(output.contents(), "hello\n1\n");
+
+        Ok(())
+    }
+}
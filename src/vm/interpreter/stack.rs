@@ -1,7 +1,18 @@
 //! This module deals with operand stack, local-variables and stack frames.
 
-use crate::vm::{VmError, interpreter::instructions::opcode::Opcode, runtime::heap::with_heap};
-use std::{fmt::Display, sync::Arc};
+use crate::vm::{
+    VmError,
+    interpreter::{ArithmeticMode, instructions::opcode::Opcode},
+    runtime::{
+        heap::{with_heap, with_mut_heap},
+        method_area::{ExceptionHandler, with_method_area},
+    },
+};
+use smallvec::{SmallVec, smallvec};
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
 use thiserror::Error;
 use tracing::trace;
 
@@ -13,17 +24,62 @@ pub(in crate::vm) struct StackFrame {
     /// invoked method, this value is restored to the `pc` handle the exception.
     ex_pc: Option<usize>,
     /// Array of local variables for the current method.
-    variables: Box<[ValueRef]>,
+    variables: Box<[Local]>,
     /// The operand stack for the current method. It used to store intermediate values
     /// and to pass parameters to and receive results from other methods.
-    operand_stack: Stack<ValueRef>,
+    operand_stack: Stack<Slot>,
     /// Shared reference to the bytecode of the method associated with this frame.
     bytecode: Arc<[u8]>,
+    /// Exception table of the method associated with this frame.
+    exception_handlers: Arc<[ExceptionHandler]>,
     pub(super) current_classname: Arc<str>,
 }
 
 pub(super) struct StackFrames {
     frames: Vec<StackFrame>,
+    /// Maximum number of frames before invocations fail with a stack overflow.
+    max_depth: usize,
+    arithmetic_mode: ArithmeticMode,
+    /// Slots returned by the outermost frame once it completes.
+    result: Vec<ValueRef>,
+}
+
+/// A single operand stack entry, tagged with the computational type category of the value it
+/// belongs to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Slot {
+    value: ValueRef,
+    category: Category,
+    /// Whether the value is a `returnAddress` pushed by `jsr`, rather than a Java value.
+    return_address: bool,
+}
+
+/// A local variable, tagged with the kind of value last stored into it.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+struct Local {
+    value: ValueRef,
+    kind: Kind,
+}
+
+/// Kind of value held by a local variable, as far as the frame tells them apart.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+enum Kind {
+    /// A primitive value, half of a category 2 one, or a value of unknown type, such as the
+    /// arguments of the method.
+    #[default]
+    Value,
+    /// A reference stored by `astore`.
+    Reference,
+    /// The `returnAddress` of a subroutine, stored by `astore`, which only `ret` may read.
+    ReturnAddress,
+}
+
+/// Computational type category as defined by JVMS (2.11.1). `long` and `double` are category 2
+/// and take up two slots, every other type is category 1.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(in crate::vm::interpreter) enum Category {
+    One,
+    Two,
 }
 
 pub(super) struct Stack<T> {
@@ -41,31 +97,69 @@ pub enum StackError {
 
     #[error("Empty stack frame")]
     EmptyStack,
+
+    #[error("Access to out of range local variable: {0}")]
+    InvalidLocal(usize),
+
+    #[error("{0} cannot operate on a category 2 value")]
+    InvalidCategory(String),
+
+    #[error("{0} expects a reference operand")]
+    NotAReference(String),
+
+    #[error("{0} expects a return address operand")]
+    NotAReturnAddress(String),
+
+    #[error("A value needs {expected} slots but only {found} were given")]
+    MissingSlots { expected: usize, found: usize },
+
+    #[error("Branch to negative bytecode offset {0}")]
+    InvalidBranch(isize),
+
+    #[error("Exceeded the maximum depth of {0} frames")]
+    StackOverflow(usize),
 }
 
+/// A Java value of one of the primitive computational types. References, `boolean`, `byte`,
+/// `char` and `short` are all represented as `int`.
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub(super) enum Value {
+pub enum Value {
     Int(i32),
     Long(i64),
     Float(f32),
     Double(f64),
 }
 
+/// A `reference`, moving through locals and the operand stack like an `int` does, but which can
+/// never be made out of half of a category 2 value nor a `returnAddress`. Locals remember they
+/// hold one.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub(in crate::vm) struct Reference(pub ValueRef);
+
+/// The `returnAddress` pushed by `jsr`, which `astore` may move into a local for `ret` to return
+/// to, but which is no value for any other instruction.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub(in crate::vm) struct ReturnAddress(pub ValueRef);
+
 pub(super) type Result<T> = std::result::Result<T, StackError>;
 pub(super) type ValueRef = i32;
 
 pub(in crate::vm) trait StackValue: Sized + Default + Copy {
     /// Retrives the value at `index` from the stack frame.
-    fn get(index: usize, frame: &StackFrame) -> Self;
+    fn get(index: usize, frame: &StackFrame) -> Result<Self>;
     /// Set the value at `index` in the stack frame.
-    fn set(&self, index: usize, frame: &mut StackFrame);
+    fn set(&self, index: usize, frame: &mut StackFrame) -> Result<()>;
 
     /// Push the value onto the operand stack.
     fn push_onto(&self, frame: &mut StackFrame) -> Result<()>;
     /// Pop the value from the operand stack.
     fn pop_from(frame: &mut StackFrame) -> Result<Self>;
 
-    fn from_slice(value: &[ValueRef]) -> Self;
+    /// Rebuilds a value from the slots it occupies, failing if there are too few of them.
+    fn from_slice(value: &[ValueRef]) -> Result<Self>;
+    /// Splits the value into the slots [`StackValue::from_slice`] reads back, with the high half
+    /// first for `long` and `double`.
+    fn to_slice(&self) -> SmallVec<[ValueRef; 2]>;
 }
 
 macro_rules! maybe_nan {
@@ -100,14 +194,27 @@ impl StackFrame {
     ) -> Self {
         Self {
             bytecode,
+            exception_handlers: Arc::from([]),
             current_classname,
             pc: 0,
             ex_pc: None,
-            variables: vec![ValueRef::default(); variables_size].into_boxed_slice(),
+            variables: vec![Local::default(); variables_size].into_boxed_slice(),
             operand_stack: Stack::with_capacity(stack_size),
         }
     }
 
+    pub fn with_exception_handlers(mut self, exception_handlers: Arc<[ExceptionHandler]>) -> Self {
+        self.exception_handlers = exception_handlers;
+        self
+    }
+
+    /// Builds a frame straight from a bytecode snippet, for exercising instruction handlers
+    /// without a loaded class.
+    #[cfg(test)]
+    pub fn from_bytecode(code: &[u8], max_locals: usize, max_stack: usize) -> Self {
+        Self::new(max_locals, max_stack, Arc::from(code), Arc::from("Test"))
+    }
+
     pub fn push<V: StackValue>(&mut self, value: V) -> Result<()> {
         value.push_onto(self)
     }
@@ -141,7 +248,7 @@ impl StackFrame {
     where
         usize: From<Pos>,
     {
-        let value: V = self.get(position.into());
+        let value: V = self.get(position.into())?;
         self.push(value)?;
         self.next_pc();
 
@@ -154,11 +261,11 @@ impl StackFrame {
         &mut self,
         code: Opcode,
     ) -> super::Result<()> {
-        let idx: i32 = self.pop().unwrap();
-        let array_idx: i32 = self.pop().unwrap();
+        let idx: i32 = self.pop().ok_or(StackError::StackUnderflow)?;
+        let array_idx: i32 = self.pop().ok_or(StackError::StackUnderflow)?;
 
         let value = with_heap(|heap| heap.get_array_value(array_idx, idx))?;
-        let value: V = V::from_slice(&value);
+        let value: V = V::from_slice(&value)?;
 
         self.push(value)?;
         self.next_pc();
@@ -184,25 +291,39 @@ impl StackFrame {
     where
         usize: From<Pos>,
     {
-        let value: V = self.pop().unwrap();
-        self.set(position.into(), value);
+        let value = V::pop_from(self)?;
+        self.set(position.into(), value)?;
         self.next_pc();
 
         trace!("{code}{position} -> {value}");
         Ok(())
     }
 
+    /// `astore` into the local at `position`, which takes either a reference or a
+    /// `returnAddress`, keeping them apart.
+    pub(in crate::vm::interpreter) fn store_reference_local<Pos: Display + Copy>(
+        &mut self,
+        position: Pos,
+        code: Opcode,
+    ) -> super::Result<()>
+    where
+        usize: From<Pos>,
+    {
+        match self.is_return_address(0)? {
+            true => self.store::<ReturnAddress, _>(position, code),
+            false => self.store::<Reference, _>(position, code),
+        }
+    }
+
     pub(in crate::vm::interpreter) fn store_array<V: Display + StackValue>(
         &mut self,
         code: Opcode,
     ) -> super::Result<()> {
-        let idx = self.pop().unwrap();
-        let array_idx = self.pop().unwrap();
-        let value = with_heap(|heap| heap.get_array_value(array_idx, idx))?;
-
-        let value: V = V::from_slice(&value);
+        let value: V = self.pop().ok_or(StackError::EmptyStack)?;
+        let idx: i32 = self.pop().ok_or(StackError::EmptyStack)?;
+        let array_idx: i32 = self.pop().ok_or(StackError::EmptyStack)?;
 
-        self.push(value);
+        with_mut_heap(|heap| heap.set_array_value(array_idx, idx, &value.to_slice()))?;
         self.next_pc();
 
         trace!("{code} -> array_idx={array_idx}, index={idx}, value={value}");
@@ -223,6 +344,8 @@ impl StackFrame {
         let value = op(a, b);
 
         self.push(value)?;
+        self.next_pc();
+
         trace!("{code} -> ({a}, {b}) -> {value}");
         Ok(())
     }
@@ -232,30 +355,33 @@ impl StackFrame {
         op: impl Fn(V) -> V,
         code: Opcode,
     ) -> super::Result<()> {
-        let value: V = self.pop().unwrap();
+        let value: V = self.pop().ok_or(StackError::StackUnderflow)?;
         let res = op(value);
+        self.push(res)?;
         self.next_pc();
 
         trace!("{code} -> ({value} -> {res})");
         Ok(())
     }
 
+    /// Increments the `int` local at `index` by the sign-extended `constant`.
+    /// Both operands are expected to already have been read from the bytecode.
     pub(in crate::vm::interpreter) fn increment<I, C>(
         &mut self,
-        index: impl FnOnce(&mut Self) -> I,
-        constant: impl FnOnce(&mut Self) -> C,
+        index: I,
+        constant: C,
         code: Opcode,
     ) -> super::Result<()>
     where
         usize: From<I>,
         i32: From<C>,
     {
-        let index: usize = index(self).into();
-        let constant: i32 = constant(self).into();
+        let index: usize = index.into();
+        let constant: i32 = constant.into();
 
-        let curr: i32 = self.get(index);
+        let curr: i32 = self.get(index)?;
         let next = curr.wrapping_add(constant);
-        self.set(index, next);
+        self.set(index, next)?;
         self.next_pc();
 
         trace!("{code} -> {curr} + {constant} = {next}");
@@ -266,27 +392,77 @@ impl StackFrame {
         &mut self,
         op: impl Fn(ValueRef) -> bool,
         code: Opcode,
-    ) {
-        let value = self.pop().unwrap();
-        let offset =
-            (((self.get_byte(self.pc + 1) as i16) << 8) | self.get_byte(self.pc + 2) as i16);
+    ) -> Result<()> {
+        let value = self.pop().ok_or(StackError::StackUnderflow)?;
+        let offset = self.branch_offset();
 
-        self.step_pc(if op(value) { offset } else { 3 });
-        trace!("{code} -> {value}, {offset}")
+        self.step_pc(if op(value) { offset } else { 3 })?;
+        trace!("{code} -> {value}, {offset}");
+        Ok(())
     }
 
     pub(in crate::vm::interpreter) fn binary_branch(
         &mut self,
         op: impl Fn(ValueRef, ValueRef) -> bool,
         code: Opcode,
-    ) {
-        let value_sec = self.pop().unwrap();
-        let value = self.pop().unwrap();
-        let offset =
-            (((self.get_byte(self.pc + 1) as i16) << 8) | self.get_byte(self.pc + 2) as i16);
+    ) -> Result<()> {
+        let value_sec = self.pop().ok_or(StackError::StackUnderflow)?;
+        let value = self.pop().ok_or(StackError::StackUnderflow)?;
+        let offset = self.branch_offset();
+
+        self.step_pc(if op(value, value_sec) { offset } else { 3 })?;
+        trace!("{code} -> ({value}, {value_sec}), {offset}");
+        Ok(())
+    }
+
+    /// Branches if the two references on top of the stack point to the same object, or to
+    /// different ones when `same` is false. References are compared by identity, never by value.
+    pub(in crate::vm::interpreter) fn reference_branch(
+        &mut self,
+        same: bool,
+        code: Opcode,
+    ) -> Result<()> {
+        let value_sec = self.pop_reference(code)?;
+        let value = self.pop_reference(code)?;
+        let offset = self.branch_offset();
+
+        self.step_pc(if (value == value_sec) == same {
+            offset
+        } else {
+            3
+        })?;
+        trace!("{code} -> ({value}, {value_sec}), {offset}");
+        Ok(())
+    }
+
+    /// Branches if the reference on top of the stack is null, or if it isn't when `null` is false.
+    pub(in crate::vm::interpreter) fn null_branch(
+        &mut self,
+        null: bool,
+        code: Opcode,
+    ) -> Result<()> {
+        let value = self.pop_reference(code)?;
+        let offset = self.branch_offset();
 
-        self.step_pc(if op(value, value_sec) { offset } else { 3 });
-        trace!("{code} -> ({value}, {value_sec}), {offset}")
+        self.step_pc(if (value == 0) == null { offset } else { 3 })?;
+        trace!("{code} -> {value}, {offset}");
+        Ok(())
+    }
+
+    /// Pops a slot that may hold a reference, i.e. neither half of a category 2 value nor a
+    /// `returnAddress`.
+    fn pop_reference(&mut self, code: Opcode) -> Result<ValueRef> {
+        let slot = self.operand_stack.pop().ok_or(StackError::StackUnderflow)?;
+        if slot.category == Category::Two || slot.return_address {
+            return Err(StackError::NotAReference(code.to_string()));
+        }
+
+        Ok(slot.value)
+    }
+
+    /// Signed 16-bit branch offset following the current instruction.
+    pub(in crate::vm::interpreter) fn branch_offset(&self) -> i16 {
+        i16::from_be_bytes([self.get_byte(self.pc + 1), self.get_byte(self.pc + 2)])
     }
 
     pub(in crate::vm::interpreter) fn convert<
@@ -297,9 +473,9 @@ impl StackFrame {
         conversion: impl Fn(F) -> T,
         code: Opcode,
     ) -> super::Result<()> {
-        let from: F = self.pop().unwrap();
+        let from: F = self.pop().ok_or(StackError::StackUnderflow)?;
         let to = conversion(from);
-        self.push(to);
+        self.push(to)?;
         self.next_pc();
 
         trace!("{code} -> {from} -> {to}");
@@ -316,16 +492,19 @@ impl StackFrame {
     {
         use std::cmp::Ordering;
 
-        let value_sec: V = self.pop().unwrap();
-        let value: V = self.pop().unwrap();
+        let value_sec: V = self.pop().ok_or(StackError::StackUnderflow)?;
+        let value: V = self.pop().ok_or(StackError::StackUnderflow)?;
 
-        let result = match value.is_nan() || value_sec.is_nan() {
-            true => nan_ord,
-            _ => match value.partial_cmp(&value_sec).unwrap() {
-                Ordering::Greater => 1,
-                Ordering::Equal => 0,
-                Ordering::Less => -1,
-            },
+        // values are unordered only if either is NaN, in which case `nan_ord` is the result
+        let ordering = match value.is_nan() || value_sec.is_nan() {
+            true => None,
+            false => value.partial_cmp(&value_sec),
+        };
+        let result = match ordering {
+            Some(Ordering::Greater) => 1,
+            Some(Ordering::Equal) => 0,
+            Some(Ordering::Less) => -1,
+            None => nan_ord,
         };
 
         self.push(result)?;
@@ -336,14 +515,57 @@ impl StackFrame {
     }
 
     pub fn next_pc(&mut self) {
-        self.step_pc(1);
+        self.pc += 1;
     }
 
-    pub fn step_pc(&mut self, step: i16) {
-        match step >= 0 {
-            true => self.pc += step as usize,
-            false => self.pc -= (-step) as usize,
-        }
+    /// Moves the pc by a signed `step`, refusing to move it before the start of the bytecode.
+    pub fn step_pc(&mut self, step: i16) -> Result<()> {
+        self.jump(step as isize)
+    }
+
+    /// Moves the pc `offset` bytes away from the current instruction.
+    pub(in crate::vm::interpreter) fn jump(&mut self, offset: isize) -> Result<()> {
+        let target = self.pc as isize + offset;
+        self.pc = usize::try_from(target).map_err(|_| StackError::InvalidBranch(target))?;
+
+        Ok(())
+    }
+
+    /// Branches `offset` bytes away into a subroutine, pushing the `returnAddress` of the
+    /// instruction following the `length` bytes long jump.
+    pub(in crate::vm::interpreter) fn jump_subroutine(
+        &mut self,
+        offset: isize,
+        length: usize,
+        code: Opcode,
+    ) -> Result<()> {
+        let return_address = self.pc + length;
+        self.push(ReturnAddress(return_address as ValueRef))?;
+        self.jump(offset)?;
+
+        trace!("{code} -> {offset}, returning to {return_address}");
+        Ok(())
+    }
+
+    /// Returns from a subroutine to the `returnAddress` held by the local at `index`.
+    pub(in crate::vm::interpreter) fn return_from_subroutine(
+        &mut self,
+        index: usize,
+        code: Opcode,
+    ) -> Result<()> {
+        let ReturnAddress(return_address) = self.get(index)?;
+        self.pc = return_address as usize;
+
+        trace!("{code}{index} -> {return_address}");
+        Ok(())
+    }
+
+    /// Whether the operand stack slot `depth` positions below the top holds a `returnAddress`.
+    pub(in crate::vm::interpreter) fn is_return_address(&self, depth: usize) -> Result<bool> {
+        self.operand_stack
+            .peek(depth)
+            .map(|slot| slot.return_address)
+            .ok_or(StackError::StackUnderflow)
     }
 
     pub fn get_next_byte(&mut self) -> u8 {
@@ -351,6 +573,14 @@ impl StackFrame {
         self.current_byte()
     }
 
+    /// Reads the next two bytes of the bytecode as a big-endian `u16`.
+    pub fn get_next_short(&mut self) -> u16 {
+        let hi = self.get_next_byte();
+        let lo = self.get_next_byte();
+
+        u16::from_be_bytes([hi, lo])
+    }
+
     pub fn current_byte(&self) -> u8 {
         self.get_byte(self.pc)
     }
@@ -363,31 +593,236 @@ impl StackFrame {
         V::pop_from(self).ok()
     }
 
+    /// Reads the value on top of the operand stack without removing it.
+    pub fn peek<V: StackValue>(&self) -> Option<V> {
+        // the slots of the value, top first, which puts the high half of a `long` first
+        let slots = (0..V::default().to_slice().len())
+            .map(|depth| self.operand_stack.peek(depth).map(|slot| slot.value))
+            .collect::<Option<SmallVec<[ValueRef; 2]>>>()?;
+
+        V::from_slice(&slots).ok()
+    }
+
+    /// Unchecked access to the local at `index`, meant for already verified code.
     pub fn get_variable(&self, index: usize) -> ValueRef {
-        self.variables[index]
+        self.variables[index].value
+    }
+
+    /// Bounds-checked access to the local at `index`.
+    pub fn load_variable(&self, index: usize) -> Result<ValueRef> {
+        self.local(index).map(|local| local.value)
+    }
+
+    fn local(&self, index: usize) -> Result<Local> {
+        self.variables
+            .get(index)
+            .copied()
+            .ok_or(StackError::InvalidLocal(index))
     }
 
-    pub fn get<V: StackValue>(&self, index: usize) -> V {
+    pub fn get<V: StackValue>(&self, index: usize) -> Result<V> {
         V::get(index, self)
     }
 
+    /// Unchecked write to the local at `index`, meant for already verified code.
     pub fn set_variable(&mut self, index: usize, value: ValueRef) {
-        self.variables[index] = value;
+        self.variables[index] = Local {
+            value,
+            kind: Kind::Value,
+        };
+    }
+
+    /// Bounds-checked write to the local at `index`.
+    pub fn store_variable(&mut self, index: usize, value: ValueRef) -> Result<()> {
+        self.store_local(index, value, Kind::Value)
     }
 
-    pub fn set<V: StackValue>(&mut self, index: usize, value: V) {
+    fn store_local(&mut self, index: usize, value: ValueRef, kind: Kind) -> Result<()> {
+        let variable = self
+            .variables
+            .get_mut(index)
+            .ok_or(StackError::InvalidLocal(index))?;
+        *variable = Local { value, kind };
+
+        Ok(())
+    }
+
+    pub fn set<V: StackValue>(&mut self, index: usize, value: V) -> Result<()> {
         value.set(index, self)
     }
 
+    /// Pops the `count` topmost slots, returned bottom first.
+    pub(in crate::vm::interpreter) fn pop_slots(
+        &mut self,
+        count: usize,
+    ) -> Result<SmallVec<[ValueRef; 2]>> {
+        let mut slots = (0..count)
+            .map(|_| self.pop_ref())
+            .collect::<Result<SmallVec<_>>>()?;
+        slots.reverse();
+
+        Ok(slots)
+    }
+
+    /// Pushes the `slots` of a single value of the given `category`.
+    pub(in crate::vm::interpreter) fn push_slots(
+        &mut self,
+        slots: &[ValueRef],
+        category: Category,
+    ) -> Result<()> {
+        slots
+            .iter()
+            .try_for_each(|&slot| self.push_slot(slot, category))
+    }
+
+    /// Discards every value on the operand stack.
+    pub(in crate::vm::interpreter) fn clear_operand_stack(&mut self) {
+        self.operand_stack.clear();
+    }
+
+    /// The handler of this frame method catching an exception of class `class` thrown by the
+    /// instruction being executed, or by the method it invoked.
+    pub(in crate::vm::interpreter) fn find_handler(
+        &self,
+        class: &str,
+    ) -> super::Result<Option<u16>> {
+        let pc = self.ex_pc.unwrap_or(self.pc);
+        for handler in self.exception_handlers.iter().filter(|h| h.covers(pc)) {
+            let catches = match &handler.catch_type {
+                Some(catch_type) => with_method_area(|area| area.is_assignable(class, catch_type))?,
+                None => true,
+            };
+
+            if catches {
+                return Ok(Some(handler.handler_pc));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Jumps to the handler at `handler_pc`, with the thrown `exception` as its only operand.
+    pub(in crate::vm::interpreter) fn enter_handler(
+        &mut self,
+        handler_pc: u16,
+        exception: ValueRef,
+    ) -> Result<()> {
+        self.pc = handler_pc as usize;
+        self.reset_ex_pc();
+        self.push_exception(exception)
+    }
+
+    /// Prepares the operand stack for entering an exception handler, which must find nothing but
+    /// the reference to the thrown `exception` on it.
+    pub(in crate::vm::interpreter) fn push_exception(&mut self, exception: ValueRef) -> Result<()> {
+        self.clear_operand_stack();
+        self.push_ref(exception)
+    }
+
+    /// Copies the operand stack, bottom first.
+    pub(in crate::vm::interpreter) fn operand_stack(&self) -> Vec<ValueRef> {
+        self.operand_stack
+            .inner
+            .iter()
+            .map(|slot| slot.value)
+            .collect()
+    }
+
+    /// Renders the operand stack, bottom first, and the local variables, for debugging.
+    /// References held by locals are prefixed by `@`.
+    pub(in crate::vm::interpreter) fn debug_state(&self) -> String {
+        let locals: Vec<String> = self.variables.iter().map(Local::to_string).collect();
+        format!(
+            "stack={:?} locals=[{}]",
+            self.operand_stack(),
+            locals.join(", ")
+        )
+    }
+
+    /// Duplicates the `count` topmost slots and inserts the copies `below` slots further down,
+    /// keeping the category of every slot. Fails if either cut would split a category 2 value,
+    /// which is how the forms of the `DUP*` instructions are told apart.
+    pub(in crate::vm::interpreter) fn duplicate(
+        &mut self,
+        count: usize,
+        below: usize,
+        code: Opcode,
+    ) -> Result<()> {
+        if !self.is_value_boundary(count)? || !self.is_value_boundary(count + below)? {
+            return Err(StackError::InvalidCategory(code.to_string()));
+        }
+
+        let len = self.operand_stack.inner.len();
+        let top: SmallVec<[Slot; 2]> = self.operand_stack.inner[len - count..].into();
+        let at = len - count - below;
+        for (offset, slot) in top.into_iter().enumerate() {
+            if self.operand_stack.inner.len() >= self.operand_stack.capacity {
+                return Err(StackError::ExceededStackSize);
+            }
+            self.operand_stack.inner.insert(at + offset, slot);
+        }
+
+        self.next_pc();
+        trace!("{code} -> {:?}", self.operand_stack());
+        Ok(())
+    }
+
+    /// Discards the `count` topmost slots, failing if that would split a category 2 value, so
+    /// `POP2` removes either one `long` or `double` or two category 1 values.
+    pub(in crate::vm::interpreter) fn discard(&mut self, count: usize, code: Opcode) -> Result<()> {
+        if !self.is_value_boundary(count)? {
+            return Err(StackError::InvalidCategory(code.to_string()));
+        }
+
+        let slots = self.pop_slots(count)?;
+
+        self.next_pc();
+        trace!("{code} -> {slots:?}");
+        Ok(())
+    }
+
+    /// Whether cutting the operand stack `depth` slots below its top leaves every category 2
+    /// value whole.
+    fn is_value_boundary(&self, depth: usize) -> Result<bool> {
+        let mut current = 0;
+        while current < depth {
+            current += match self.category(current)? {
+                Category::One => 1,
+                Category::Two => 2,
+            };
+        }
+
+        Ok(current == depth)
+    }
+
+    /// Category of the value owning the operand stack slot `depth` positions below the top.
+    pub(in crate::vm::interpreter) fn category(&self, depth: usize) -> Result<Category> {
+        self.operand_stack
+            .peek(depth)
+            .map(|slot| slot.category)
+            .ok_or(StackError::StackUnderflow)
+    }
+
     fn push_ref(&mut self, value: ValueRef) -> Result<()> {
-        self.operand_stack.push(value)
+        self.push_slot(value, Category::One)
+    }
+
+    fn push_slot(&mut self, value: ValueRef, category: Category) -> Result<()> {
+        self.operand_stack.push(Slot {
+            value,
+            category,
+            return_address: false,
+        })
     }
 
     fn pop_ref(&mut self) -> Result<ValueRef> {
-        self.operand_stack.pop().ok_or(StackError::EmptyStack)
+        self.operand_stack
+            .pop()
+            .map(|slot| slot.value)
+            .ok_or(StackError::EmptyStack)
     }
 
-    fn store_ex_pc(&mut self) {
+    pub(in crate::vm::interpreter) fn store_ex_pc(&mut self) {
         self.ex_pc = Some(self.pc);
     }
 
@@ -397,8 +832,30 @@ impl StackFrame {
 }
 
 impl StackFrames {
-    pub fn add_frame(&mut self, frame: StackFrame) {
-        self.frames.push(frame)
+    const DEFAULT_MAX_DEPTH: usize = 1024;
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_arithmetic_mode(mut self, arithmetic_mode: ArithmeticMode) -> Self {
+        self.arithmetic_mode = arithmetic_mode;
+        self
+    }
+
+    pub fn arithmetic_mode(&self) -> ArithmeticMode {
+        self.arithmetic_mode
+    }
+
+    /// Pushes a new frame, failing once the maximum depth has been reached.
+    pub fn add_frame(&mut self, frame: StackFrame) -> Result<()> {
+        if self.frames.len() >= self.max_depth {
+            return Err(StackError::StackOverflow(self.max_depth));
+        }
+
+        self.frames.push(frame);
+        Ok(())
     }
 
     pub fn quit_frame(&mut self) -> Option<StackFrame> {
@@ -415,7 +872,16 @@ impl StackFrames {
         self.frames.is_empty()
     }
 
-    fn pop(&mut self) -> Option<StackFrame> {
+    pub fn set_result(&mut self, result: Vec<ValueRef>) {
+        self.result = result;
+    }
+
+    pub fn take_result(&mut self) -> Vec<ValueRef> {
+        std::mem::take(&mut self.result)
+    }
+
+    /// Discards the current frame, leaving the caller as it was at the invocation.
+    pub(super) fn pop(&mut self) -> Option<StackFrame> {
         self.frames.pop()
     }
 
@@ -428,9 +894,35 @@ impl StackFrames {
     }
 }
 
+/// Shows where the frame stands, along with its operand stack and local variables, e.g.
+/// `Test@2 IADD [60 3b 1a ac] stack=[1, 2] locals=[0]`. Only a few bytes from the current pc
+/// are shown, never the whole bytecode.
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        const WINDOW: usize = 4;
+
+        write!(f, "{}@{}", self.current_classname, self.pc)?;
+        match self.bytecode.get(self.pc..) {
+            Some(code) if !code.is_empty() => {
+                let window = &code[..code.len().min(WINDOW)];
+                let bytes: Vec<String> = window.iter().map(|byte| format!("{byte:02x}")).collect();
+                write!(f, " {} [{}]", Opcode::from(code[0]), bytes.join(" "))?;
+            }
+            _ => write!(f, " <end of code>")?,
+        }
+
+        write!(f, " {}", self.debug_state())
+    }
+}
+
 impl From<Vec<StackFrame>> for StackFrames {
     fn from(frames: Vec<StackFrame>) -> Self {
-        Self { frames }
+        Self {
+            frames,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            arithmetic_mode: ArithmeticMode::default(),
+            result: Vec::new(),
+        }
     }
 }
 
@@ -454,6 +946,11 @@ impl<T> Stack<T> {
         self.inner.pop()
     }
 
+    /// Element `depth` positions below the top, the top itself being at depth `0`.
+    fn peek(&self, depth: usize) -> Option<&T> {
+        self.inner.iter().rev().nth(depth)
+    }
+
     fn clear(&mut self) {
         self.inner.clear();
     }
@@ -471,13 +968,43 @@ impl From<i32> for Value {
     }
 }
 
+impl Value {
+    /// Splits the value into the slots it occupies in the local variables or operand stack,
+    /// with the low half first for `long` and `double`.
+    pub fn to_slots(self) -> SmallVec<[ValueRef; 2]> {
+        match self {
+            Value::Int(int) => smallvec![int],
+            Value::Float(float) => smallvec![float.to_bits() as i32],
+            Value::Long(long) => smallvec![long as i32, (long >> 32) as i32],
+            Value::Double(double) => {
+                let bits = double.to_bits() as i64;
+                smallvec![bits as i32, (bits >> 32) as i32]
+            }
+        }
+    }
+
+    /// Rebuilds a value of the field descriptor type `ty` from its slots.
+    /// References, `boolean`, `byte`, `char` and `short` are all represented as `int`.
+    pub fn from_slots(ty: char, slots: &[ValueRef]) -> Option<Self> {
+        match (ty, slots) {
+            ('J', [l, h, ..]) => Some(Value::Long(from_i32_to_i64(*l, *h))),
+            ('D', [l, h, ..]) => Some(Value::Double(
+                f64::from_bits(from_i32_to_i64(*l, *h) as u64),
+            )),
+            ('F', [bits, ..]) => Some(Value::Float(f32::from_bits(*bits as u32))),
+            ('I' | 'Z' | 'B' | 'C' | 'S' | 'L' | '[', [int, ..]) => Some(Value::Int(*int)),
+            _ => None,
+        }
+    }
+}
+
 impl StackValue for i32 {
-    fn get(index: usize, frame: &StackFrame) -> Self {
-        frame.get_variable(index)
+    fn get(index: usize, frame: &StackFrame) -> Result<Self> {
+        frame.load_variable(index)
     }
 
-    fn set(&self, index: usize, frame: &mut StackFrame) {
-        frame.set_variable(index, *self)
+    fn set(&self, index: usize, frame: &mut StackFrame) -> Result<()> {
+        frame.store_variable(index, *self)
     }
 
     fn push_onto(&self, frame: &mut StackFrame) -> Result<()> {
@@ -488,33 +1015,142 @@ impl StackValue for i32 {
         frame.pop_ref()
     }
 
-    fn from_slice(value: &[ValueRef]) -> Self {
-        value[0]
+    fn from_slice(value: &[ValueRef]) -> Result<Self> {
+        match value {
+            [value, ..] => Ok(*value),
+            _ => Err(StackError::MissingSlots {
+                expected: 1,
+                found: value.len(),
+            }),
+        }
+    }
+
+    fn to_slice(&self) -> SmallVec<[ValueRef; 2]> {
+        smallvec![*self]
+    }
+}
+
+impl StackValue for Reference {
+    /// Only `aload` reads references from locals, which may hold anything but a `returnAddress`.
+    fn get(index: usize, frame: &StackFrame) -> Result<Self> {
+        match frame.local(index)? {
+            Local {
+                kind: Kind::ReturnAddress,
+                ..
+            } => Err(StackError::NotAReference(Opcode::ALOAD.to_string())),
+            Local { value, .. } => Ok(Reference(value)),
+        }
+    }
+
+    fn set(&self, index: usize, frame: &mut StackFrame) -> Result<()> {
+        frame.store_local(index, self.0, Kind::Reference)
+    }
+
+    fn push_onto(&self, frame: &mut StackFrame) -> Result<()> {
+        frame.push_ref(self.0)
+    }
+
+    /// Only `astore` pops references into locals.
+    fn pop_from(frame: &mut StackFrame) -> Result<Self> {
+        frame.pop_reference(Opcode::ASTORE).map(Reference)
+    }
+
+    fn from_slice(value: &[ValueRef]) -> Result<Self> {
+        i32::from_slice(value).map(Reference)
+    }
+
+    fn to_slice(&self) -> SmallVec<[ValueRef; 2]> {
+        smallvec![self.0]
+    }
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "@{}", self.0)
+    }
+}
+
+impl StackValue for ReturnAddress {
+    fn get(index: usize, frame: &StackFrame) -> Result<Self> {
+        match frame.local(index)? {
+            Local {
+                value,
+                kind: Kind::ReturnAddress,
+            } => Ok(ReturnAddress(value)),
+            _ => Err(StackError::NotAReturnAddress(Opcode::RET.to_string())),
+        }
+    }
+
+    fn set(&self, index: usize, frame: &mut StackFrame) -> Result<()> {
+        frame.store_local(index, self.0, Kind::ReturnAddress)
+    }
+
+    fn push_onto(&self, frame: &mut StackFrame) -> Result<()> {
+        frame.operand_stack.push(Slot {
+            value: self.0,
+            category: Category::One,
+            return_address: true,
+        })
+    }
+
+    fn pop_from(frame: &mut StackFrame) -> Result<Self> {
+        let slot = frame
+            .operand_stack
+            .pop()
+            .ok_or(StackError::StackUnderflow)?;
+        match slot.return_address {
+            true => Ok(ReturnAddress(slot.value)),
+            false => Err(StackError::NotAReturnAddress(Opcode::ASTORE.to_string())),
+        }
+    }
+
+    fn from_slice(value: &[ValueRef]) -> Result<Self> {
+        i32::from_slice(value).map(ReturnAddress)
+    }
+
+    fn to_slice(&self) -> SmallVec<[ValueRef; 2]> {
+        smallvec![self.0]
+    }
+}
+
+impl Display for ReturnAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "return to {}", self.0)
+    }
+}
+
+impl Display for Local {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Kind::Value => write!(f, "{}", self.value),
+            Kind::Reference => write!(f, "{}", Reference(self.value)),
+            Kind::ReturnAddress => write!(f, "{}", ReturnAddress(self.value)),
+        }
     }
 }
 
 impl StackValue for i64 {
-    fn get(index: usize, frame: &StackFrame) -> Self {
-        let l = frame.get_variable(index);
-        let h = frame.get_variable(index + 1);
+    fn get(index: usize, frame: &StackFrame) -> Result<Self> {
+        let l = frame.load_variable(index)?;
+        let h = frame.load_variable(index + 1)?;
 
-        from_i32_to_i64(l, h)
+        Ok(from_i32_to_i64(l, h))
     }
 
-    fn set(&self, index: usize, frame: &mut StackFrame) {
+    fn set(&self, index: usize, frame: &mut StackFrame) -> Result<()> {
         let l = *self as i32;
         let h = (*self >> 32) as i32;
 
-        frame.set_variable(index, l);
-        frame.set_variable(index + 1, h);
+        frame.store_variable(index, l)?;
+        frame.store_variable(index + 1, h)
     }
 
     fn push_onto(&self, frame: &mut StackFrame) -> Result<()> {
         let l = *self as i32;
         let h = (*self >> 32) as i32;
 
-        frame.push_ref(l)?;
-        frame.push_ref(h)
+        frame.push_slot(l, Category::Two)?;
+        frame.push_slot(h, Category::Two)
     }
 
     fn pop_from(frame: &mut StackFrame) -> Result<Self> {
@@ -524,20 +1160,29 @@ impl StackValue for i64 {
         Ok(from_i32_to_i64(l, h))
     }
 
-    fn from_slice(value: &[ValueRef]) -> Self {
-        let (h, l) = (value[0], value[1]);
-        from_i32_to_i64(l, h)
+    fn from_slice(value: &[ValueRef]) -> Result<Self> {
+        match value {
+            [h, l, ..] => Ok(from_i32_to_i64(*l, *h)),
+            _ => Err(StackError::MissingSlots {
+                expected: 2,
+                found: value.len(),
+            }),
+        }
+    }
+
+    fn to_slice(&self) -> SmallVec<[ValueRef; 2]> {
+        smallvec![(*self >> 32) as i32, *self as i32]
     }
 }
 
 impl StackValue for f32 {
-    fn get(index: usize, frame: &StackFrame) -> Self {
-        let v: i32 = frame.get(index);
-        f32::from_bits(v as u32)
+    fn get(index: usize, frame: &StackFrame) -> Result<Self> {
+        let v: i32 = frame.get(index)?;
+        Ok(f32::from_bits(v as u32))
     }
 
-    fn set(&self, index: usize, frame: &mut StackFrame) {
-        frame.set(index, self.to_bits() as i32);
+    fn set(&self, index: usize, frame: &mut StackFrame) -> Result<()> {
+        frame.set(index, self.to_bits() as i32)
     }
 
     fn push_onto(&self, frame: &mut StackFrame) -> Result<()> {
@@ -549,20 +1194,24 @@ impl StackValue for f32 {
         Ok(f32::from_bits(v as u32))
     }
 
-    fn from_slice(value: &[ValueRef]) -> Self {
-        let value: i32 = StackValue::from_slice(value);
-        f32::from_bits(value as u32)
+    fn from_slice(value: &[ValueRef]) -> Result<Self> {
+        let value: i32 = StackValue::from_slice(value)?;
+        Ok(f32::from_bits(value as u32))
+    }
+
+    fn to_slice(&self) -> SmallVec<[ValueRef; 2]> {
+        (self.to_bits() as i32).to_slice()
     }
 }
 
 impl StackValue for f64 {
-    fn get(index: usize, frame: &StackFrame) -> Self {
-        let v: i64 = frame.get(index);
-        f64::from_bits(v as u64)
+    fn get(index: usize, frame: &StackFrame) -> Result<Self> {
+        let v: i64 = frame.get(index)?;
+        Ok(f64::from_bits(v as u64))
     }
 
-    fn set(&self, index: usize, frame: &mut StackFrame) {
-        frame.set(index, self.to_bits() as i64);
+    fn set(&self, index: usize, frame: &mut StackFrame) -> Result<()> {
+        frame.set(index, self.to_bits() as i64)
     }
 
     fn push_onto(&self, frame: &mut StackFrame) -> Result<()> {
@@ -574,9 +1223,13 @@ impl StackValue for f64 {
         Ok(f64::from_bits(v as u64))
     }
 
-    fn from_slice(value: &[ValueRef]) -> Self {
-        let value: i64 = StackValue::from_slice(value);
-        f64::from_bits(value as u64)
+    fn from_slice(value: &[ValueRef]) -> Result<Self> {
+        let value: i64 = StackValue::from_slice(value)?;
+        Ok(f64::from_bits(value as u64))
+    }
+
+    fn to_slice(&self) -> SmallVec<[ValueRef; 2]> {
+        (self.to_bits() as i64).to_slice()
     }
 }
 
@@ -589,10 +1242,79 @@ fn from_i32_to_i64(l: i32, h: i32) -> i64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vm::interpreter::InterpreterError;
+
+    #[test]
+    fn display_frame() -> Result<()> {
+        let code = [
+            Opcode::ICONST_1 as u8,
+            Opcode::ICONST_2 as u8,
+            Opcode::IADD as u8,
+            Opcode::ISTORE_0 as u8,
+            Opcode::ILOAD_0 as u8,
+            Opcode::IRETURN as u8,
+        ];
+        let mut frame = StackFrame::from_bytecode(&code, 1, 2);
+        frame.push(1)?;
+        frame.push(2)?;
+        frame.pc = 2;
+
+        assert_eq!(
+            frame.to_string(),
+            "Test@2 IADD [60 3b 1a ac] stack=[1, 2] locals=[0]"
+        );
+
+        frame.pc = code.len();
+        assert!(frame.to_string().starts_with("Test@6 <end of code>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn peek_leaves_the_stack_alone() -> Result<()> {
+        let mut frame = StackFrame::from_bytecode(&[], 0, 4);
+        assert_eq!(frame.peek::<i32>(), None);
+
+        frame.push(7i32)?;
+        frame.push(-3i64)?;
+
+        assert_eq!(frame.peek::<i64>(), Some(-3));
+        assert_eq!(frame.peek::<i64>(), Some(-3));
+        assert_eq!(frame.operand_stack().len(), 3);
+
+        frame.pop::<i64>();
+        frame.push(1.5f32)?;
+        assert_eq!(frame.peek::<f32>(), Some(1.5));
+        assert_eq!(frame.operand_stack().len(), 2);
+        assert_eq!(frame.pop::<f32>(), Some(1.5));
+        assert_eq!(frame.pop::<i32>(), Some(7));
+
+        Ok(())
+    }
+
+    #[test]
+    fn underflow_is_an_error() -> Result<()> {
+        let underflow = |result: crate::vm::Result<()>| {
+            matches!(
+                result,
+                Err(VmError::Interpreter(InterpreterError::Stack(
+                    StackError::StackUnderflow
+                )))
+            )
+        };
+        let mut frame = StackFrame::from_bytecode(&[], 0, 2);
+        assert!(underflow(frame.convert(|v: i32| v as i64, Opcode::I2L)));
+        assert!(underflow(frame.unary_op(|v: i32| -v, Opcode::INEG)));
+
+        frame.push(0i32)?;
+        assert!(underflow(frame.load_array::<i32>(Opcode::IALOAD)));
+
+        Ok(())
+    }
 
     #[test]
     fn frame_stack_basics() {
-        let mut frame = StackFrame::new(10, 5, Arc::default(), Arc::default());
+        let mut frame = StackFrame::from_bytecode(&[], 10, 5);
 
         let value1 = 10;
         let value2 = 20;
@@ -606,7 +1328,7 @@ mod tests {
 
     #[test]
     fn frame_stack_overflow() {
-        let mut frame = StackFrame::new(5, 3, Arc::default(), Arc::default());
+        let mut frame = StackFrame::from_bytecode(&[], 5, 3);
 
         let value1 = 15.12f32;
         let value2 = 19.0f32;
@@ -621,4 +1343,57 @@ mod tests {
         assert_eq!(frame.pop(), Some(value3));
         assert!(frame.push(0.0f32).is_ok())
     }
+
+    #[test]
+    fn handler_entry_keeps_only_the_exception() -> Result<()> {
+        let mut frame = StackFrame::from_bytecode(&[], 0, 4);
+        frame.push(1i32)?;
+        frame.push(2.5f64)?;
+        frame.push(3i32)?;
+
+        frame.push_exception(42)?;
+        assert_eq!(frame.operand_stack(), [42]);
+        assert_eq!(frame.category(0)?, Category::One);
+
+        frame.clear_operand_stack();
+        assert!(frame.operand_stack().is_empty());
+        assert_eq!(frame.pop::<i32>(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_slots_round_trip() {
+        let values = [
+            ('I', Value::Int(-7)),
+            ('J', Value::Long(i64::MIN + 3)),
+            ('F', Value::Float(-1.5)),
+            ('D', Value::Double(f64::MAX)),
+        ];
+
+        for (ty, value) in values {
+            let slots = value.to_slots();
+            assert_eq!(Value::from_slots(ty, &slots), Some(value));
+        }
+
+        assert_eq!(Value::Long(1).to_slots().len(), 2);
+        assert_eq!(Value::from_slots('J', &[1]), None);
+    }
+
+    #[test]
+    fn short_slices_are_rejected() -> Result<()> {
+        assert_eq!(
+            <i64 as StackValue>::from_slice(&[1]),
+            Err(StackError::MissingSlots {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert!(<f64 as StackValue>::from_slice(&[1]).is_err());
+        assert!(<i32 as StackValue>::from_slice(&[]).is_err());
+
+        assert_eq!(<i64 as StackValue>::from_slice(&[0, 5])?, 5);
+        assert_eq!(<i32 as StackValue>::from_slice(&[4, 2])?, 4);
+        Ok(())
+    }
 }
@@ -1,19 +1,58 @@
 use crate::vm::{
-    Result,
+    Result, VmError, VmException,
     runtime::method_area::{Class, with_method_area},
 };
 
+const JAVA_LANG_ERROR: &str = "java/lang/Error";
+const EXCEPTION_IN_INITIALIZER_ERROR: &str = "java/lang/ExceptionInInitializerError";
+
 pub(in crate::vm) struct Static {}
 
 impl Static {
     const STATIC_INIT_METHOD: &'static str = "<clinit>:()V";
 
+    /// Initialises the class `classname` on its first active use, running its `<clinit>` after
+    /// initialising its superclass. Later calls do nothing, unless the initialisation failed, in
+    /// which case they raise `NoClassDefFoundError`.
     pub fn initialise(classname: &str) -> Result<()> {
         let class = with_method_area(|area| area.get(classname))?;
-        todo!()
+        Self::initialise_class(&class)
     }
 
     fn initialise_class(class: &Class) -> Result<()> {
-        todo!()
+        // initialisers touching their own class, even indirectly, must not run it again
+        if !class.begin_initialisation()? {
+            return Ok(());
+        }
+
+        if let Err(error) = Self::run_initialisers(class) {
+            class.fail_initialisation();
+            return Err(error);
+        }
+
+        class.finish_initialisation();
+        Ok(())
+    }
+
+    fn run_initialisers(class: &Class) -> Result<()> {
+        if let Some(parent) = class.parent() {
+            Self::initialise(parent)?;
+        }
+        let Ok(method) = class.get_method(Self::STATIC_INIT_METHOD) else {
+            return Ok(());
+        };
+
+        let exception = match super::execute(method.new_frame()?) {
+            Err(VmError::Exception(exception)) => exception,
+            result => return result.map(|_| ()),
+        };
+        // errors are rethrown as they are, any other exception is wrapped, see JVMS (5.5)
+        match with_method_area(|area| area.is_assignable(&exception.class, JAVA_LANG_ERROR))? {
+            true => Err(exception.into()),
+            false => {
+                let message = exception.to_string();
+                Err(VmException::new(EXCEPTION_IN_INITIALIZER_ERROR, message).into())
+            }
+        }
     }
 }
@@ -0,0 +1,26 @@
+//! Helpers shared by the tests of the interpreter.
+
+use parking_lot::Mutex;
+use std::{io, sync::Arc};
+
+/// Captures what is written to it, e.g. by a subscriber or a print stream. Clones share the
+/// captured bytes.
+#[derive(Clone, Default)]
+pub(super) struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    /// Everything written so far.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock()).into_owned()
+    }
+}
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
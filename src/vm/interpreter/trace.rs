@@ -0,0 +1,41 @@
+//! Opt-in recording of every instruction the interpreter dispatches.
+//! Unlike the `trace!` logs, the recorded trace is structured, so it can be inspected or diffed
+//! against the output of a reference JVM.
+
+use crate::vm::interpreter::{StackFrame, instructions::opcode::Opcode, stack::ValueRef};
+use std::sync::Arc;
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ExecutionTrace {
+    entries: Vec<TraceEntry>,
+}
+
+/// State of the current frame right before an instruction executes.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub opcode: Opcode,
+    pub classname: Arc<str>,
+    /// Operand stack, bottom first.
+    pub stack: Vec<ValueRef>,
+}
+
+impl ExecutionTrace {
+    pub(in crate::vm::interpreter) fn record(&mut self, frame: &StackFrame) {
+        self.entries.push(TraceEntry {
+            pc: frame.pc,
+            opcode: Opcode::from(frame.current_byte()),
+            classname: Arc::clone(&frame.current_classname),
+            stack: frame.operand_stack(),
+        });
+    }
+
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// The dispatched opcodes, in execution order.
+    pub fn opcodes(&self) -> impl Iterator<Item = Opcode> + '_ {
+        self.entries.iter().map(|entry| entry.opcode)
+    }
+}
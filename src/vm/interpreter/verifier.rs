@@ -0,0 +1,775 @@
+//! Lightweight bytecode verification performed when a class is defined, before any of its
+//! methods is executed.
+//!
+//! The verifier abstractly interprets the instruction stream tracking the operand stack, in
+//! slots, at each instruction. Every branch target acts as a merge point, where all incoming
+//! paths must agree on the stack, which is the same guarantee the `StackMapTable` frames state.
+//! Exception handlers are reached from every instruction they cover, with only the thrown
+//! exception on the stack.
+//! A method is rejected if any reachable instruction would underflow the operand stack or grow
+//! it beyond the declared `max_stack`.
+//!
+//! Slots are only told apart from the return addresses pushed by `jsr`, which may be moved
+//! around by the stack instructions and stored by `astore`, but never used as any other value,
+//! and which are the only thing `ret` accepts. Local variables are tracked for that purpose
+//! alone; after a subroutine returns, they are assumed to be the ones before the `jsr`.
+//!
+//! Reference: https://docs.oracle.com/javase/specs/jvms/se24/html/jvms-4.html#jvms-4.10
+
+use crate::vm::{
+    interpreter::instructions::{
+        InstructionCategory,
+        opcode::Opcode::{self, *},
+    },
+    runtime::method_area::ExceptionHandler,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum VerifyError {
+    #[error("Operand stack underflow at pc {pc}")]
+    StackUnderflow { pc: usize },
+
+    #[error("Operand stack depth {depth} exceeds max_stack at pc {pc}")]
+    StackOverflow { pc: usize, depth: usize },
+
+    #[error("Inconsistent operand stack when merging into pc {pc}")]
+    InconsistentStack { pc: usize },
+
+    #[error("Instruction at pc {pc} branches to invalid target {target}")]
+    InvalidBranch { pc: usize, target: isize },
+
+    #[error("Unknown opcode {opcode:#04x} at pc {pc}")]
+    UnknownOpcode { pc: usize, opcode: u8 },
+
+    #[error("WIDE at pc {0} modifies an instruction it cannot")]
+    InvalidWideOperand(usize),
+
+    #[error("Instruction at pc {0} is truncated")]
+    Truncated(usize),
+
+    #[error("Execution falls off the end of the code after pc {0}")]
+    FallsOffEnd(usize),
+
+    #[error("Could not resolve the descriptor of constant pool entry {0}")]
+    UnresolvedDescriptor(u16),
+
+    #[error("Instruction at pc {0} uses a return address as a value")]
+    MisusedReturnAddress(usize),
+
+    #[error("RET at pc {pc} reads local {index}, which holds no return address")]
+    NotAReturnAddress { pc: usize, index: usize },
+}
+
+type Result<T> = std::result::Result<T, VerifyError>;
+
+/// A decoded instruction and its effect over the operand stack, in slots.
+struct Instruction {
+    length: usize,
+    pop: usize,
+    push: usize,
+    flow: Flow,
+    effect: Effect,
+}
+
+enum Flow {
+    /// Execution continues with the next instruction.
+    Next,
+    /// Either branches to the targets or continues with the next instruction.
+    Branch(Vec<isize>),
+    /// Always branches to one of the targets.
+    Jump(Vec<isize>),
+    /// Jumps to a subroutine, which returns to the next instruction.
+    Subroutine(isize),
+    /// Leaves the method or subroutine.
+    Exit,
+}
+
+/// What an instruction does with the slots it pops, besides removing them from the stack.
+enum Effect {
+    /// Consumes plain values, pushing new ones.
+    Values,
+    /// Pushes back the popped slots at the given positions, the deepest one being 0.
+    Shuffle(&'static [usize]),
+    /// Pushes the local variables starting at the index.
+    Load(usize),
+    /// Stores the popped slots into the local variables starting at the index. References may be
+    /// return addresses.
+    Store { index: usize, reference: bool },
+    /// Increments the local variable at the index.
+    Increment(usize),
+    /// Returns to the address held by the local variable at the index.
+    Return(usize),
+}
+
+/// Type of a slot, as far as the verifier tells them apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Value,
+    ReturnAddress,
+    /// A local variable holding different types depending on the path taken to reach it.
+    Unusable,
+}
+
+/// Types of the operand stack and local variables before an instruction.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Frame {
+    stack: Vec<Type>,
+    /// Local variables written so far; the others hold plain values.
+    locals: Vec<Type>,
+}
+
+/// Verifies the `code` of a method, protected by the exception `handlers`, against its declared
+/// `max_stack`.
+///
+/// `descriptor` resolves the constant pool index of a field or method reference into its
+/// descriptor, which is needed to compute the stack effect of field access and invoke
+/// instructions.
+pub(in crate::vm) fn verify<'d>(
+    code: &[u8],
+    max_stack: u16,
+    handlers: &[ExceptionHandler],
+    descriptor: impl Fn(u16) -> Option<&'d str>,
+) -> Result<()> {
+    let max_stack = max_stack as usize;
+    let starts = instruction_starts(code, &descriptor)?;
+
+    let mut frames: Vec<Option<Frame>> = vec![None; code.len()];
+    let mut pending = vec![0usize];
+    if !code.is_empty() {
+        frames[0] = Some(Frame::default());
+    }
+
+    while let Some(pc) = pending.pop() {
+        let mut frame = frames[pc]
+            .clone()
+            .expect("pending instructions always have a frame");
+        let instruction = decode(code, pc, &descriptor)?;
+        // a thrown exception replaces the stack, leaving the locals as they were before
+        let handled: Vec<_> = handlers
+            .iter()
+            .filter(|handler| handler.covers(pc))
+            .map(|handler| {
+                let thrown = Frame {
+                    stack: vec![Type::Value],
+                    locals: frame.locals.clone(),
+                };
+                (handler.handler_pc as isize, thrown)
+            })
+            .collect();
+
+        instruction.execute(&mut frame, pc)?;
+        let depth = frame.stack.len();
+        if depth > max_stack {
+            return Err(VerifyError::StackOverflow { pc, depth });
+        }
+
+        let next = pc as isize + instruction.length as isize;
+        let successors = match instruction.flow {
+            Flow::Next => vec![(next, frame)],
+            Flow::Branch(targets) => targets
+                .into_iter()
+                .map(|offset| (pc as isize + offset, frame.clone()))
+                .chain([(next, frame.clone())])
+                .collect(),
+            Flow::Jump(targets) => targets
+                .into_iter()
+                .map(|offset| (pc as isize + offset, frame.clone()))
+                .collect(),
+            // the return address is only on the stack inside of the subroutine
+            Flow::Subroutine(offset) => {
+                let mut returned = frame.clone();
+                returned.stack.pop();
+                vec![(pc as isize + offset, frame), (next, returned)]
+            }
+            Flow::Exit => vec![],
+        };
+
+        for (target, frame) in successors.into_iter().chain(handled) {
+            if target == code.len() as isize && target == next {
+                return Err(VerifyError::FallsOffEnd(pc));
+            }
+
+            let is_start = usize::try_from(target).is_ok_and(|t| starts.get(t) == Some(&true));
+            if !is_start {
+                return Err(VerifyError::InvalidBranch { pc, target });
+            }
+
+            let target = target as usize;
+            let changed = match &mut frames[target] {
+                None => {
+                    frames[target] = Some(frame);
+                    true
+                }
+                Some(existing) => existing.merge(&frame, target)?,
+            };
+            if changed {
+                pending.push(target);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Instruction {
+    /// Updates `frame`, the state before the instruction at `pc`, into the one after it.
+    fn execute(&self, frame: &mut Frame, pc: usize) -> Result<()> {
+        let depth = frame
+            .stack
+            .len()
+            .checked_sub(self.pop)
+            .ok_or(VerifyError::StackUnderflow { pc })?;
+        let popped = frame.stack.split_off(depth);
+
+        match self.effect {
+            Effect::Shuffle(order) => {
+                frame.stack.extend(order.iter().map(|&slot| popped[slot]));
+                return Ok(());
+            }
+            // `astore` is the only way to take a return address off the stack
+            Effect::Store {
+                index,
+                reference: true,
+            } => frame.set_local(index, popped[0]),
+            _ if popped.contains(&Type::ReturnAddress) => {
+                return Err(VerifyError::MisusedReturnAddress(pc));
+            }
+            Effect::Store { index, .. } => {
+                (index..index + self.pop).for_each(|local| frame.set_local(local, Type::Value));
+            }
+            Effect::Load(index) => frame.ensure_values(index..index + self.push, pc)?,
+            Effect::Increment(index) => frame.ensure_values(index..index + 1, pc)?,
+            Effect::Return(index) => {
+                if frame.local(index) != Type::ReturnAddress {
+                    return Err(VerifyError::NotAReturnAddress { pc, index });
+                }
+            }
+            Effect::Values => {}
+        }
+
+        let pushed = match self.flow {
+            Flow::Subroutine(_) => Type::ReturnAddress,
+            _ => Type::Value,
+        };
+        frame.stack.extend(std::iter::repeat_n(pushed, self.push));
+        Ok(())
+    }
+}
+
+impl Frame {
+    fn local(&self, index: usize) -> Type {
+        self.locals.get(index).copied().unwrap_or(Type::Value)
+    }
+
+    fn set_local(&mut self, index: usize, r#type: Type) {
+        if index >= self.locals.len() {
+            self.locals.resize(index + 1, Type::Value);
+        }
+        self.locals[index] = r#type;
+    }
+
+    /// Fails unless the local variables in `indexes` all hold plain values.
+    fn ensure_values(&self, mut indexes: std::ops::Range<usize>, pc: usize) -> Result<()> {
+        match indexes.all(|index| self.local(index) == Type::Value) {
+            true => Ok(()),
+            false => Err(VerifyError::MisusedReturnAddress(pc)),
+        }
+    }
+
+    /// Merges the `incoming` frame into this one, the frame of the instruction at `pc`. Local
+    /// variables that disagree become unusable. Returns whether anything changed.
+    fn merge(&mut self, incoming: &Frame, pc: usize) -> Result<bool> {
+        if self.stack != incoming.stack {
+            return Err(VerifyError::InconsistentStack { pc });
+        }
+
+        let mut changed = false;
+        for index in 0..self.locals.len().max(incoming.locals.len()) {
+            let current = self.local(index);
+            if current != incoming.local(index) && current != Type::Unusable {
+                self.set_local(index, Type::Unusable);
+                changed = true;
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+/// Marks which offsets of `code` begin an instruction, so that branches into the middle of
+/// one can be rejected.
+fn instruction_starts<'d>(
+    code: &[u8],
+    descriptor: &impl Fn(u16) -> Option<&'d str>,
+) -> Result<Vec<bool>> {
+    let mut starts = vec![false; code.len()];
+    let mut pc = 0;
+
+    while pc < code.len() {
+        starts[pc] = true;
+        pc += decode(code, pc, descriptor)?.length;
+    }
+
+    Ok(starts)
+}
+
+fn decode<'d>(
+    code: &[u8],
+    pc: usize,
+    descriptor: &impl Fn(u16) -> Option<&'d str>,
+) -> Result<Instruction> {
+    let byte = |at: usize| code.get(at).copied().ok_or(VerifyError::Truncated(pc));
+    let short = |at: usize| Ok(u16::from_be_bytes([byte(at)?, byte(at + 1)?]));
+    let int = |at: usize| {
+        Ok(i32::from_be_bytes([
+            byte(at)?,
+            byte(at + 1)?,
+            byte(at + 2)?,
+            byte(at + 3)?,
+        ]))
+    };
+    let member = |at: usize| {
+        let index = short(at)?;
+        descriptor(index).ok_or(VerifyError::UnresolvedDescriptor(index))
+    };
+
+    let instruction = |length, pop, push, effect| Instruction {
+        length,
+        pop,
+        push,
+        flow: Flow::Next,
+        effect,
+    };
+    let next = |length, pop, push| instruction(length, pop, push, Effect::Values);
+    let store = |length, size, index, reference| {
+        instruction(length, size, 0, Effect::Store { index, reference })
+    };
+    let branch = |pop| -> Result<Instruction> {
+        Ok(Instruction {
+            length: 3,
+            pop,
+            push: 0,
+            flow: Flow::Branch(vec![short(pc + 1)? as i16 as isize]),
+            effect: Effect::Values,
+        })
+    };
+    let exit = |pop| Instruction {
+        length: 1,
+        pop,
+        push: 0,
+        flow: Flow::Exit,
+        effect: Effect::Values,
+    };
+
+    let switch = |opcode| -> Result<Instruction> {
+        // operands are 4-byte aligned relative to the start of the code
+        let operands = (pc + 4) & !3;
+        let default = int(operands)? as isize;
+        let mut targets = vec![default];
+
+        let length = match opcode {
+            TABLESWITCH => {
+                let low = int(operands + 4)?;
+                let high = int(operands + 8)?;
+                let count = (high as i64 - low as i64 + 1).max(0) as usize;
+                for i in 0..count {
+                    targets.push(int(operands + 12 + i * 4)? as isize);
+                }
+
+                operands + 12 + count * 4 - pc
+            }
+            _ => {
+                let pairs = int(operands + 4)?.max(0) as usize;
+                for i in 0..pairs {
+                    targets.push(int(operands + 12 + i * 8)? as isize);
+                }
+
+                operands + 8 + pairs * 8 - pc
+            }
+        };
+
+        Ok(Instruction {
+            length,
+            pop: 1,
+            push: 0,
+            flow: Flow::Jump(targets),
+            effect: Effect::Values,
+        })
+    };
+
+    let opcode = byte(pc)?;
+    if InstructionCategory::try_from(opcode).is_err() {
+        return Err(VerifyError::UnknownOpcode { pc, opcode });
+    }
+    let opcode = Opcode::from(opcode);
+    // index of the local variable accessed by the `<x>load_<n>` and `<x>store_<n>` forms
+    let implicit = |first: Opcode| (opcode as u8 - first as u8) as usize % 4;
+    let instruction = match opcode {
+        NOP => next(1, 0, 0),
+        ACONST_NULL | ICONST_M1 | ICONST_0 | ICONST_1 | ICONST_2 | ICONST_3 | ICONST_4
+        | ICONST_5 | FCONST_0 | FCONST_1 | FCONST_2 => next(1, 0, 1),
+        LCONST_0 | LCONST_1 | DCONST_0 | DCONST_1 => next(1, 0, 2),
+        BIPUSH | LDC => next(2, 0, 1),
+        SIPUSH | LDC_W => next(3, 0, 1),
+        LDC2_W => next(3, 0, 2),
+
+        ILOAD | FLOAD | ALOAD => instruction(2, 0, 1, Effect::Load(byte(pc + 1)? as usize)),
+        LLOAD | DLOAD => instruction(2, 0, 2, Effect::Load(byte(pc + 1)? as usize)),
+        ILOAD_0 | ILOAD_1 | ILOAD_2 | ILOAD_3 | FLOAD_0 | FLOAD_1 | FLOAD_2 | FLOAD_3 | ALOAD_0
+        | ALOAD_1 | ALOAD_2 | ALOAD_3 => instruction(1, 0, 1, Effect::Load(implicit(ILOAD_0))),
+        LLOAD_0 | LLOAD_1 | LLOAD_2 | LLOAD_3 | DLOAD_0 | DLOAD_1 | DLOAD_2 | DLOAD_3 => {
+            instruction(1, 0, 2, Effect::Load(implicit(ILOAD_0)))
+        }
+        IALOAD | FALOAD | AALOAD | BALOAD | CALOAD | SALOAD => next(1, 2, 1),
+        LALOAD | DALOAD => next(1, 2, 2),
+
+        ISTORE | FSTORE | ASTORE => store(2, 1, byte(pc + 1)? as usize, opcode == ASTORE),
+        LSTORE | DSTORE => store(2, 2, byte(pc + 1)? as usize, false),
+        ISTORE_0 | ISTORE_1 | ISTORE_2 | ISTORE_3 | FSTORE_0 | FSTORE_1 | FSTORE_2 | FSTORE_3 => {
+            store(1, 1, implicit(ISTORE_0), false)
+        }
+        ASTORE_0 | ASTORE_1 | ASTORE_2 | ASTORE_3 => store(1, 1, implicit(ISTORE_0), true),
+        LSTORE_0 | LSTORE_1 | LSTORE_2 | LSTORE_3 => store(1, 2, implicit(ISTORE_0), false),
+        DSTORE_0 | DSTORE_1 | DSTORE_2 | DSTORE_3 => store(1, 2, implicit(ISTORE_0), false),
+        IASTORE | FASTORE | AASTORE | BASTORE | CASTORE | SASTORE => next(1, 3, 0),
+        LASTORE | DASTORE => next(1, 4, 0),
+
+        POP => instruction(1, 1, 0, Effect::Shuffle(&[])),
+        POP2 => instruction(1, 2, 0, Effect::Shuffle(&[])),
+        DUP => instruction(1, 1, 2, Effect::Shuffle(&[0, 0])),
+        DUP_X1 => instruction(1, 2, 3, Effect::Shuffle(&[1, 0, 1])),
+        DUP_X2 => instruction(1, 3, 4, Effect::Shuffle(&[2, 0, 1, 2])),
+        DUP2 => instruction(1, 2, 4, Effect::Shuffle(&[0, 1, 0, 1])),
+        DUP2_X1 => instruction(1, 3, 5, Effect::Shuffle(&[1, 2, 0, 1, 2])),
+        DUP2_X2 => instruction(1, 4, 6, Effect::Shuffle(&[2, 3, 0, 1, 2, 3])),
+        SWAP => instruction(1, 2, 2, Effect::Shuffle(&[1, 0])),
+
+        IADD | ISUB | IMUL | IDIV | IREM | IAND | IOR | IXOR | ISHL | ISHR | IUSHR | FADD
+        | FSUB | FMUL | FDIV | FREM => next(1, 2, 1),
+        LADD | LSUB | LMUL | LDIV | LREM | LAND | LOR | LXOR => next(1, 4, 2),
+        DADD | DSUB | DMUL | DDIV | DREM => next(1, 4, 2),
+        LSHL | LSHR | LUSHR => next(1, 3, 2),
+        INEG | FNEG => next(1, 1, 1),
+        LNEG | DNEG => next(1, 2, 2),
+        IINC => instruction(3, 0, 0, Effect::Increment(byte(pc + 1)? as usize)),
+
+        I2F | F2I | I2B | I2C | I2S => next(1, 1, 1),
+        I2L | I2D | F2L | F2D => next(1, 1, 2),
+        L2I | L2F | D2I | D2F => next(1, 2, 1),
+        L2D | D2L => next(1, 2, 2),
+
+        LCMP | DCMPL | DCMPG => next(1, 4, 1),
+        FCMPL | FCMPG => next(1, 2, 1),
+        IFEQ | IFNE | IFLT | IFGE | IFGT | IFLE | IFNULL | IFNONNULL => branch(1)?,
+        IF_ICMPEQ | IF_ICMPNE | IF_ICMPLT | IF_ICMPGE | IF_ICMPGT | IF_ICMPLE | IF_ACMPEQ
+        | IF_ACMPNE => branch(2)?,
+
+        GOTO => Instruction {
+            length: 3,
+            pop: 0,
+            push: 0,
+            flow: Flow::Jump(vec![short(pc + 1)? as i16 as isize]),
+            effect: Effect::Values,
+        },
+        GOTO_W => Instruction {
+            length: 5,
+            pop: 0,
+            push: 0,
+            flow: Flow::Jump(vec![int(pc + 1)? as isize]),
+            effect: Effect::Values,
+        },
+        JSR => Instruction {
+            length: 3,
+            pop: 0,
+            push: 1,
+            flow: Flow::Subroutine(short(pc + 1)? as i16 as isize),
+            effect: Effect::Values,
+        },
+        JSR_W => Instruction {
+            length: 5,
+            pop: 0,
+            push: 1,
+            flow: Flow::Subroutine(int(pc + 1)? as isize),
+            effect: Effect::Values,
+        },
+        RET => Instruction {
+            length: 2,
+            pop: 0,
+            push: 0,
+            flow: Flow::Exit,
+            effect: Effect::Return(byte(pc + 1)? as usize),
+        },
+
+        TABLESWITCH | LOOKUPSWITCH => switch(opcode)?,
+
+        IRETURN | FRETURN | ARETURN | ATHROW => exit(1),
+        LRETURN | DRETURN => exit(2),
+        RETURN => exit(0),
+
+        GETSTATIC => next(3, 0, field_size(member(pc + 1)?)),
+        PUTSTATIC => next(3, field_size(member(pc + 1)?), 0),
+        GETFIELD => next(3, 1, field_size(member(pc + 1)?)),
+        PUTFIELD => next(3, 1 + field_size(member(pc + 1)?), 0),
+
+        INVOKEVIRTUAL | INVOKESPECIAL | INVOKESTATIC | INVOKEINTERFACE | INVOKEDYNAMIC => {
+            let (args, ret) = method_sizes(member(pc + 1)?);
+            let receiver = !matches!(opcode, INVOKESTATIC | INVOKEDYNAMIC);
+            let length = match opcode {
+                INVOKEINTERFACE | INVOKEDYNAMIC => 5,
+                _ => 3,
+            };
+
+            next(length, args + receiver as usize, ret)
+        }
+
+        NEW => next(3, 0, 1),
+        NEWARRAY => next(2, 1, 1),
+        ANEWARRAY | CHECKCAST | INSTANCEOF => next(3, 1, 1),
+        ARRAYLENGTH => next(1, 1, 1),
+        MONITORENTER | MONITOREXIT => next(1, 1, 0),
+        MULTIANEWARRAY => next(4, byte(pc + 3)? as usize, 1),
+
+        WIDE => match Opcode::from(byte(pc + 1)?) {
+            ILOAD | FLOAD | ALOAD => instruction(4, 0, 1, Effect::Load(short(pc + 2)? as usize)),
+            LLOAD | DLOAD => instruction(4, 0, 2, Effect::Load(short(pc + 2)? as usize)),
+            ISTORE | FSTORE => store(4, 1, short(pc + 2)? as usize, false),
+            ASTORE => store(4, 1, short(pc + 2)? as usize, true),
+            LSTORE | DSTORE => store(4, 2, short(pc + 2)? as usize, false),
+            IINC => instruction(6, 0, 0, Effect::Increment(short(pc + 2)? as usize)),
+            RET => Instruction {
+                length: 4,
+                pop: 0,
+                push: 0,
+                flow: Flow::Exit,
+                effect: Effect::Return(short(pc + 2)? as usize),
+            },
+            _ => return Err(VerifyError::InvalidWideOperand(pc)),
+        },
+    };
+
+    if pc + instruction.length > code.len() {
+        return Err(VerifyError::Truncated(pc));
+    }
+
+    Ok(instruction)
+}
+
+/// Number of slots taken by a value of the given field descriptor.
+fn field_size(descriptor: &str) -> usize {
+    match descriptor.as_bytes().first() {
+        Some(b'J' | b'D') => 2,
+        Some(b'V') => 0,
+        _ => 1,
+    }
+}
+
+/// Number of slots taken by the arguments and by the return value of a method descriptor.
+pub(in crate::vm::interpreter) fn method_sizes(descriptor: &str) -> (usize, usize) {
+    let (params, ret) = descriptor
+        .trim_start_matches('(')
+        .split_once(')')
+        .unwrap_or(("", descriptor));
+
+    let mut args = 0;
+    let mut chars = params.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            'J' | 'D' => args += 2,
+            'L' => {
+                chars.by_ref().find(|&c| c == ';');
+                args += 1;
+            }
+            '[' => {
+                // an array is a single reference regardless of its element type
+                let mut element = chars.next();
+                while element == Some('[') {
+                    element = chars.next();
+                }
+                if element == Some('L') {
+                    chars.by_ref().find(|&c| c == ';');
+                }
+                args += 1;
+            }
+            _ => args += 1,
+        }
+    }
+
+    (args, field_size(ret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_descriptors(_: u16) -> Option<&'static str> {
+        None
+    }
+
+    #[test]
+    fn rejects_underflow() {
+        let code = [ICONST_1 as u8, IADD as u8, IRETURN as u8];
+        assert_eq!(
+            verify(&code, 2, &[], no_descriptors),
+            Err(VerifyError::StackUnderflow { pc: 1 })
+        );
+    }
+
+    #[test]
+    fn rejects_exceeding_max_stack() {
+        let code = [ICONST_1 as u8, ICONST_2 as u8, IADD as u8, IRETURN as u8];
+        assert_eq!(verify(&code, 2, &[], no_descriptors), Ok(()));
+        assert_eq!(
+            verify(&code, 1, &[], no_descriptors),
+            Err(VerifyError::StackOverflow { pc: 1, depth: 2 })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_opcodes() {
+        let code = [ICONST_1 as u8, 0xCA, IRETURN as u8];
+        assert_eq!(
+            verify(&code, 1, &[], no_descriptors),
+            Err(VerifyError::UnknownOpcode {
+                pc: 1,
+                opcode: 0xCA
+            })
+        );
+
+        let code = [WIDE as u8, IADD as u8, 0, 0, RETURN as u8];
+        assert_eq!(
+            verify(&code, 1, &[], no_descriptors),
+            Err(VerifyError::InvalidWideOperand(0))
+        );
+    }
+
+    #[test]
+    fn merges_loop_back_edges() {
+        // while (true) { i++; }
+        let code = [
+            ICONST_0 as u8,
+            ISTORE_0 as u8,
+            IINC as u8,
+            0,
+            1,
+            GOTO as u8,
+            0xFF,
+            0xFD,
+        ];
+        assert_eq!(verify(&code, 1, &[], no_descriptors), Ok(()));
+
+        // pushing on every iteration leaves the loop header with two different depths
+        let code = [ICONST_0 as u8, GOTO as u8, 0xFF, 0xFF];
+        assert_eq!(
+            verify(&code, 4, &[], no_descriptors),
+            Err(VerifyError::InconsistentStack { pc: 0 })
+        );
+    }
+
+    #[test]
+    fn invokes_use_descriptor_sizes() {
+        // static long sum(int, long, String[])
+        let code = [
+            ICONST_0 as u8,
+            LCONST_1 as u8,
+            ACONST_NULL as u8,
+            INVOKESTATIC as u8,
+            0,
+            7,
+            LRETURN as u8,
+        ];
+        let descriptor = |index| (index == 7).then_some("(IJ[Ljava/lang/String;)J");
+
+        assert_eq!(verify(&code, 4, &[], descriptor), Ok(()));
+        assert_eq!(
+            verify(&code, 4, &[], no_descriptors),
+            Err(VerifyError::UnresolvedDescriptor(7))
+        );
+    }
+
+    #[test]
+    fn subroutine_return_addresses() {
+        // int i = 0; try { return i; } finally { i += 5; } compiled into a subroutine
+        let code = [
+            JSR as u8,
+            0x00,
+            0x05,
+            ILOAD_0 as u8,
+            IRETURN as u8,
+            ASTORE_1 as u8,
+            IINC as u8,
+            0,
+            5,
+            RET as u8,
+            1,
+        ];
+        assert_eq!(verify(&code, 1, &[], no_descriptors), Ok(()));
+
+        // the return address stored away, then loaded back as an int to do arithmetic with
+        let code = [
+            JSR as u8,
+            0x00,
+            0x05,
+            ILOAD_0 as u8,
+            IRETURN as u8,
+            ASTORE_1 as u8,
+            ILOAD_1 as u8,
+            ICONST_1 as u8,
+            IADD as u8,
+            ISTORE_0 as u8,
+            RET as u8,
+            1,
+        ];
+        assert_eq!(
+            verify(&code, 2, &[], no_descriptors),
+            Err(VerifyError::MisusedReturnAddress(6))
+        );
+
+        // stored where an int is expected
+        let mut code = code;
+        code[5] = ISTORE_1 as u8;
+        assert_eq!(
+            verify(&code, 2, &[], no_descriptors),
+            Err(VerifyError::MisusedReturnAddress(5))
+        );
+
+        // returning to an int
+        let code = [JSR as u8, 0x00, 0x04, RETURN as u8, POP as u8, RET as u8, 0];
+        assert_eq!(
+            verify(&code, 1, &[], no_descriptors),
+            Err(VerifyError::NotAReturnAddress { pc: 5, index: 0 })
+        );
+    }
+
+    #[test]
+    fn exception_handlers_are_reachable() {
+        // try { return 1 / 0; } catch (ArithmeticException e) { ... }
+        let code = [
+            ICONST_1 as u8,
+            ICONST_0 as u8,
+            IDIV as u8,
+            IRETURN as u8,
+            POP as u8,
+            POP as u8,
+            RETURN as u8,
+        ];
+        let handler = |handler_pc| ExceptionHandler {
+            start_pc: 0,
+            end_pc: 4,
+            handler_pc,
+            catch_type: Some("java/lang/ArithmeticException".into()),
+        };
+
+        // the handler is dead code unless it's declared
+        assert_eq!(verify(&code, 2, &[], no_descriptors), Ok(()));
+        // it starts with the exception alone on the stack, so the second pop underflows
+        assert_eq!(
+            verify(&code, 2, &[handler(4)], no_descriptors),
+            Err(VerifyError::StackUnderflow { pc: 5 })
+        );
+        assert_eq!(
+            verify(&code, 2, &[handler(7)], no_descriptors),
+            Err(VerifyError::InvalidBranch { pc: 0, target: 7 })
+        );
+    }
+}
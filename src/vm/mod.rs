@@ -7,35 +7,85 @@
 //! This module acts as the bridge between the static class file data and the dynamic execution of Java programs,
 //! forming the heart of the JVM interpreter and class loader runtime system.
 
-use std::path::Path;
+use std::{io::Write, path::Path};
 use thiserror::Error;
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::vm::{
     interpreter::static_method::Static,
-    runtime::method_area::{MethodArea, with_method_area},
+    runtime::{
+        heap::with_mut_heap,
+        method_area::{MethodArea, Stream, with_method_area},
+    },
 };
 
 mod interpreter;
 mod runtime;
 
+pub use interpreter::{ArithmeticMode, ExecutionTrace, TraceEntry, Value, disassembler};
+
 #[derive(Default)]
 pub struct Args<'a> {
+    /// Class whose `main` method [`run`] invokes, unused by [`initialise`].
     pub entry: &'a str,
+    /// Source of class files consulted before the JDK, see [`ClassProvider`].
+    pub class_provider: Option<ClassProvider>,
+    /// How the program deals with integer overflows, wrapping around by default. Methods called
+    /// through [`invoke`] and its variants use their own mode instead.
+    pub arithmetic_mode: ArithmeticMode,
+    /// Approximate number of bytes the heap may take before allocations throw
+    /// `java/lang/OutOfMemoryError`, unbounded if `None`.
+    pub heap_capacity: Option<usize>,
+    /// Where `System.out` prints to, instead of the standard output of the process.
+    pub stdout: Option<Sink>,
+    /// Where `System.err` prints to, instead of the standard error of the process.
+    pub stderr: Option<Sink>,
 }
 
+/// Provides the bytes of the class file of a class, given its internal name, e.g.
+/// `com/example/Main`, or `None` to defer to the default lookup. It allows serving classes from
+/// memory, the network or test doubles.
+pub type ClassProvider = Box<dyn Fn(&str) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Destination of what a program prints, e.g. a buffer to capture it instead of the process own
+/// standard streams.
+pub type Sink = Box<dyn Write + Send + Sync>;
+
 #[derive(Error, Debug)]
 pub enum VmError {
     #[error(transparent)]
     Runtime(#[from] runtime::RuntimeError),
     #[error(transparent)]
     Interpreter(#[from] interpreter::InterpreterError),
+    #[error(transparent)]
+    Exception(#[from] VmException),
+    /// The program called `System.exit`, which unwinds every frame up to the caller of the VM.
+    #[error("Program exited with status {0}")]
+    Exit(i32),
+}
+
+/// Status a Java program ended with, either the code given to `System.exit` or 0 if its `main`
+/// returned normally.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ExitStatus(i32);
+
+/// A Java exception raised by the VM itself, e.g. on a null dereference, which the running code
+/// can catch like any exception it throws.
+#[derive(Error, Debug, PartialEq, Clone)]
+#[error("{class}: {message}")]
+pub struct VmException {
+    /// Internal name of the exception class, e.g. `java/lang/NullPointerException`.
+    pub class: String,
+    pub message: String,
 }
 
 pub(in crate::vm) type Result<T> = std::result::Result<T, VmError>;
 
+/// Environment variable listing the instruction categories to trace.
+const TRACE_CATEGORIES: &str = "IGNIS_TRACE";
 const UNSAFE_CONSTANTS: &str = "jdk/internal/misc/UnsafeConstants";
 const ADDRESS_SIZE: &str = "ADDRESS_SIZE0";
+const BIG_ENDIAN: &str = "BIG_ENDIAN";
 const ACCESSIBLE_OBJ: &str = "java/lang/reflect/AccessibleObject";
 
 #[cfg(target_endian = "big")]
@@ -44,21 +94,153 @@ const ENDIANNESS: i32 = 1;
 #[cfg(not(target_endian = "big"))]
 const ENDIANNESS: i32 = 0;
 
+impl VmException {
+    pub fn new(class: &str, message: impl Into<String>) -> Self {
+        Self {
+            class: class.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn null_pointer(message: impl Into<String>) -> Self {
+        Self::new("java/lang/NullPointerException", message)
+    }
+}
+
+impl ExitStatus {
+    pub fn code(&self) -> i32 {
+        self.0
+    }
+
+    pub fn success(&self) -> bool {
+        self.0 == 0
+    }
+}
+
 /// Launches the VM.
-/// This initialise the JVM itself, loading the given class and invoking it `main` function.
-pub fn run(args: Args, path: &Path) -> Result<()> {
-    setup(path)?;
+/// This initialise the JVM itself, loading the given class and invoking it `main` function,
+/// whose exit status is returned so an embedding program can propagate it.
+pub fn run(args: Args, path: &Path) -> Result<ExitStatus> {
+    let (entry, mode) = (args.entry, args.arithmetic_mode);
+    initialise(args, path)?;
 
     Static::initialise(UNSAFE_CONSTANTS)?;
     let uc = with_method_area(|area| area.get(UNSAFE_CONSTANTS))?;
-    let be = uc.get_static("BIG_ENDIAN").unwrap();
-    be.set(vec![ENDIANNESS])?;
-
-    let address = uc.get_static(ADDRESS_SIZE).unwrap();
-    address.set(vec![8]); // we are going to set only for 64 bit machines
+    let constant = |name: &str| {
+        uc.get_static(name)
+            .ok_or_else(|| runtime::RuntimeError::InvalidObjectAcess {
+                classname: UNSAFE_CONSTANTS.to_string(),
+                field: name.to_string(),
+            })
+    };
+    constant(BIG_ENDIAN)?.set_int(ENDIANNESS)?;
+    constant(ADDRESS_SIZE)?.set_int(8)?; // we are going to set only for 64 bit machines
     Static::initialise(ACCESSIBLE_OBJ)?;
 
-    todo!()
+    interpreter::run_main(entry, mode)
+}
+
+/// Sets the VM up without running any program, looking classes up in the JDK at `path` and
+/// through `args`, so they can then be called with [`invoke`] and its variants.
+/// Unlike [`run`], the JDK itself isn't initialised, so it also works with a class provider
+/// serving every class, `java/lang/Object` included. The VM can be set up only once per process.
+pub fn initialise(args: Args, path: &Path) -> Result<()> {
+    setup(path)?;
+    with_mut_heap(|heap| heap.set_capacity(args.heap_capacity));
+    if let Some(provider) = args.class_provider {
+        with_method_area(|area| area.set_class_provider(provider));
+    }
+    for (stream, sink) in [(Stream::Out, args.stdout), (Stream::Err, args.stderr)] {
+        if let Some(sink) = sink {
+            with_method_area(|area| area.set_sink(stream, sink));
+        }
+    }
+
+    Ok(())
+}
+
+/// Calls the method `method` of `class` with the given `descriptor`, e.g. `(II)I`, loading the
+/// class if needed, and returns its result, or `None` for `void` methods. For instance methods,
+/// `args` start with the reference of the receiver.
+/// The VM must already be set up, by [`initialise`] or [`run`].
+pub fn invoke(
+    class: &str,
+    method: &str,
+    descriptor: &str,
+    args: &[Value],
+) -> Result<Option<Value>> {
+    let mode = ArithmeticMode::default();
+    interpreter::invoke(class, method, descriptor, args, None, None, mode)
+}
+
+/// Same as [`invoke`], but gives up with [`InterpreterError::InstructionBudgetExceeded`] once
+/// `max_instructions` instructions were executed without the method returning, e.g. to guard
+/// against untrusted code looping forever.
+///
+/// [`InterpreterError::InstructionBudgetExceeded`]: interpreter::InterpreterError::InstructionBudgetExceeded
+pub fn invoke_bounded(
+    class: &str,
+    method: &str,
+    descriptor: &str,
+    args: &[Value],
+    max_instructions: usize,
+) -> Result<Option<Value>> {
+    interpreter::invoke(
+        class,
+        method,
+        descriptor,
+        args,
+        Some(max_instructions),
+        None,
+        ArithmeticMode::default(),
+    )
+}
+
+/// Same as [`invoke`], with integer overflows handled according to `mode`, e.g. throwing
+/// `java/lang/ArithmeticException` with [`ArithmeticMode::Checked`].
+pub fn invoke_with_arithmetic(
+    class: &str,
+    method: &str,
+    descriptor: &str,
+    args: &[Value],
+    mode: ArithmeticMode,
+) -> Result<Option<Value>> {
+    interpreter::invoke(class, method, descriptor, args, None, None, mode)
+}
+
+/// Same as [`invoke`], also recording every instruction executed, in any frame, into the returned
+/// [`ExecutionTrace`], e.g. to diff the execution against the one of a reference JVM.
+pub fn invoke_traced(
+    class: &str,
+    method: &str,
+    descriptor: &str,
+    args: &[Value],
+) -> Result<(Option<Value>, ExecutionTrace)> {
+    let mut trace = ExecutionTrace::default();
+    let mode = ArithmeticMode::default();
+    let value = interpreter::invoke(
+        class,
+        method,
+        descriptor,
+        args,
+        None,
+        Some(&mut trace),
+        mode,
+    )?;
+
+    Ok((value, trace))
+}
+
+/// Registers `method` as the implementation of the native method `signature` of `class`, e.g.
+/// `hashCode:()I` of `java/lang/Object`, replacing the one the VM provides, if any. It receives
+/// the arguments, starting with the reference of the receiver for instance methods, and returns
+/// the result of the method, or `None` for `void` methods.
+pub fn register_native(
+    class: &str,
+    signature: &str,
+    method: impl Fn(&[Value]) -> Result<Option<Value>> + Send + Sync + 'static,
+) {
+    interpreter::register_native(class, signature, method)
 }
 
 fn setup(path: &Path) -> Result<()> {
@@ -68,17 +250,24 @@ fn setup(path: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Initialise the logger.
+/// Initialise the logger. Besides the usual `RUST_LOG` directives, the execution of instructions
+/// is traced for the categories listed by `IGNIS_TRACE`, e.g. `math,control`.
 fn logger() -> Result<()> {
     let layer = fmt::layer().with_target(false).with_ansi(false);
-    let env_layer = EnvFilter::try_from_default_env()
+    let mut env_layer = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("info"))
         .expect("Couldn't create EnvFilter");
+    if let Ok(categories) = std::env::var(TRACE_CATEGORIES) {
+        for directive in interpreter::trace_directives(&categories)? {
+            env_layer = env_layer.add_directive(directive);
+        }
+    }
 
-    tracing_subscriber::registry()
+    // the embedding program may have installed a subscriber of its own already
+    let _ = tracing_subscriber::registry()
         .with(layer)
         .with(env_layer)
-        .init();
+        .try_init();
 
     Ok(())
 }
@@ -0,0 +1,223 @@
+//! The run-time constant pool of a class. It's the owned counterpart of the classfile constant
+//! pool, kept alive alongside the class in the method area so instructions like `LDC` can read
+//! and resolve its entries during execution.
+//!
+//! Specification for the [run-time constant pool].
+//!
+//! [run-time constant pool]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.1
+
+use crate::{
+    classfile::ConstantPoolEntry,
+    vm::{
+        Result,
+        runtime::{RuntimeError, heap::with_mut_heap},
+    },
+};
+use dashmap::DashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Default)]
+pub(in crate::vm) struct RuntimeConstantPool {
+    /// Pool entries, where the slot following a `Long` or `Double` is left empty.
+    entries: Vec<Option<Constant>>,
+    /// Entries of the class `BootstrapMethods` attribute, used to resolve dynamic constants.
+    bootstrap_methods: Vec<BootstrapMethod>,
+    /// Heap references already produced for an entry. Once resolved, an entry must always yield
+    /// the same object.
+    resolved: DashMap<u16, i32>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(in crate::vm) enum Constant {
+    Utf8(Arc<str>),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+
+    Class(u16),
+    String(u16),
+
+    FieldRef(u16, u16),
+    MethodRef(u16, u16),
+    InterfaceMethodRef(u16, u16),
+    NameAndType(u16, u16),
+
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+    InvokeDynamic(u16, u16),
+    Module(u16),
+    Package(u16),
+}
+
+/// A `bootstrap_methods` entry as defined by JVMS (4.7.23).
+#[derive(Debug, PartialEq, Clone)]
+pub(in crate::vm) struct BootstrapMethod {
+    /// Index of the `MethodHandle` constant to invoke.
+    pub method_ref: u16,
+    /// Indexes of the loadable constants passed as static arguments.
+    pub arguments: Vec<u16>,
+}
+
+impl From<ConstantPoolEntry<'_>> for Constant {
+    fn from(entry: ConstantPoolEntry<'_>) -> Self {
+        match entry {
+            ConstantPoolEntry::Utf8(utf8) => Self::Utf8(Arc::from(utf8)),
+            ConstantPoolEntry::Integer(int) => Self::Integer(int),
+            ConstantPoolEntry::Float(float) => Self::Float(float),
+            ConstantPoolEntry::Long(long) => Self::Long(long),
+            ConstantPoolEntry::Double(double) => Self::Double(double),
+            ConstantPoolEntry::Class(name) => Self::Class(name.0),
+            ConstantPoolEntry::StringRef(string) => Self::String(string.0),
+            ConstantPoolEntry::FieldRef(class, name_and_type) => {
+                Self::FieldRef(class.0, name_and_type.0)
+            }
+            ConstantPoolEntry::MethodRef(class, name_and_type) => {
+                Self::MethodRef(class.0, name_and_type.0)
+            }
+            ConstantPoolEntry::InterfaceMethodRef(class, name_and_type) => {
+                Self::InterfaceMethodRef(class.0, name_and_type.0)
+            }
+            ConstantPoolEntry::NameAndType(name, descriptor) => {
+                Self::NameAndType(name.0, descriptor.0)
+            }
+            ConstantPoolEntry::MethodHandle(kind, reference) => {
+                Self::MethodHandle(kind, reference.0)
+            }
+            ConstantPoolEntry::MethodType(descriptor) => Self::MethodType(descriptor.0),
+            ConstantPoolEntry::Dynamic(bootstrap, name_and_type) => {
+                Self::Dynamic(bootstrap.0, name_and_type.0)
+            }
+            ConstantPoolEntry::InvokeDynamic(bootstrap, name_and_type) => {
+                Self::InvokeDynamic(bootstrap.0, name_and_type.0)
+            }
+            ConstantPoolEntry::Module(name) => Self::Module(name.0),
+            ConstantPoolEntry::Package(name) => Self::Package(name.0),
+        }
+    }
+}
+
+impl RuntimeConstantPool {
+    pub fn new(
+        constants: impl IntoIterator<Item = Constant>,
+        bootstrap_methods: Vec<BootstrapMethod>,
+    ) -> Self {
+        let mut entries = Vec::new();
+        for constant in constants {
+            let wide = matches!(constant, Constant::Long(_) | Constant::Double(_));
+            entries.push(Some(constant));
+
+            if wide {
+                entries.push(None);
+            }
+        }
+
+        Self {
+            entries,
+            bootstrap_methods,
+            resolved: DashMap::new(),
+        }
+    }
+
+    /// Tries to access a [constant](Constant) in a given index.
+    ///
+    /// **Note**: it uses 1-index based.
+    pub fn get(&self, index: u16) -> Result<&Constant> {
+        let slot = (index as usize)
+            .checked_sub(1)
+            .and_then(|idx| self.entries.get(idx));
+        match slot {
+            Some(Some(constant)) => Ok(constant),
+            _ => Err(RuntimeError::InvalidConstant(index).into()),
+        }
+    }
+
+    pub fn get_utf8(&self, index: u16) -> Result<&str> {
+        match self.get(index)? {
+            Constant::Utf8(string) => Ok(string),
+            _ => Err(RuntimeError::InvalidConstant(index).into()),
+        }
+    }
+
+    pub fn get_classname(&self, index: u16) -> Result<&str> {
+        match self.get(index)? {
+            Constant::Class(name_index) => self.get_utf8(*name_index),
+            _ => Err(RuntimeError::InvalidConstant(index).into()),
+        }
+    }
+
+    /// Resolves a `NameAndType` entry into its member name and descriptor.
+    pub fn resolve_name_and_type(&self, index: u16) -> Result<(&str, &str)> {
+        match self.get(index)? {
+            Constant::NameAndType(name, descriptor) => {
+                Ok((self.get_utf8(*name)?, self.get_utf8(*descriptor)?))
+            }
+            _ => Err(RuntimeError::InvalidConstant(index).into()),
+        }
+    }
+
+    /// Resolves a field or method reference into its owning class name, member name and
+    /// descriptor.
+    pub fn resolve_ref(&self, index: u16) -> Result<(&str, &str, &str)> {
+        match self.get(index)? {
+            Constant::FieldRef(class, name_and_type)
+            | Constant::MethodRef(class, name_and_type)
+            | Constant::InterfaceMethodRef(class, name_and_type) => {
+                let (name, descriptor) = self.resolve_name_and_type(*name_and_type)?;
+                Ok((self.get_classname(*class)?, name, descriptor))
+            }
+            _ => Err(RuntimeError::InvalidConstant(index).into()),
+        }
+    }
+
+    /// Descriptor of the field, method or dynamic call site referenced at `index`.
+    pub fn member_descriptor(&self, index: u16) -> Result<&str> {
+        match self.get(index)? {
+            Constant::InvokeDynamic(_, name_and_type) => {
+                Ok(self.resolve_name_and_type(*name_and_type)?.1)
+            }
+            _ => Ok(self.resolve_ref(index)?.2),
+        }
+    }
+
+    /// Slots of the `ConstantValue` at `index` initialising a constant static field, in operand
+    /// stack order. Strings are interned in the heap the first time they're needed.
+    pub fn constant_value(&self, index: u16) -> Result<Vec<i32>> {
+        let wide = |bits: i64| vec![bits as i32, (bits >> 32) as i32];
+
+        match self.get(index)? {
+            Constant::Integer(int) => Ok(vec![*int]),
+            Constant::Float(float) => Ok(vec![float.to_bits() as i32]),
+            Constant::Long(long) => Ok(wide(*long)),
+            Constant::Double(double) => Ok(wide(double.to_bits() as i64)),
+            Constant::String(utf8) => {
+                let string = self.get_utf8(*utf8)?;
+                let reference =
+                    self.resolve_with(index, || with_mut_heap(|heap| heap.intern_string(string)))?;
+                Ok(vec![reference])
+            }
+            _ => Err(RuntimeError::InvalidConstant(index).into()),
+        }
+    }
+
+    pub fn bootstrap_method(&self, index: u16) -> Result<&BootstrapMethod> {
+        self.bootstrap_methods
+            .get(index as usize)
+            .ok_or(RuntimeError::BootstrapMethodNotFound(index).into())
+    }
+
+    /// Returns the heap reference the entry at `index` was resolved to, resolving it with
+    /// `resolve` on first access.
+    pub fn resolve_with<F>(&self, index: u16, resolve: F) -> Result<i32>
+    where
+        F: FnOnce() -> Result<i32>,
+    {
+        if let Some(reference) = self.resolved.get(&index) {
+            return Ok(*reference);
+        }
+
+        let reference = resolve()?;
+        Ok(*self.resolved.entry(index).or_insert(reference))
+    }
+}
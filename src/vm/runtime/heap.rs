@@ -1,23 +1,42 @@
 use crate::vm::{
-    Result, VmError,
-    runtime::{RuntimeError as Error, method_area::FieldValue},
+    Result, VmError, VmException,
+    runtime::{
+        RuntimeError as Error,
+        method_area::{FieldValue, component_name, with_method_area},
+    },
 };
 use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicI32, Ordering},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub(in crate::vm) struct Heap {
     /// Heap storage keyed by object reference id.
     objects: IndexMap<i32, HeapValue>,
+    /// Maximum number of bytes the heap may take, unbounded if `None`.
+    capacity: Option<usize>,
+    /// Approximate number of bytes taken by the allocated values.
+    used: usize,
+    /// Interned `java/lang/String`s keyed by their contents, so equal string constants share
+    /// the same reference (JLS 3.10.5).
+    strings: HashMap<String, i32>,
 }
 
-static HEAP: Lazy<RwLock<Heap>> = Lazy::new(|| {
-    RwLock::new(Heap {
-        objects: IndexMap::new(),
-    })
-});
+static HEAP: Lazy<RwLock<Heap>> = Lazy::new(|| RwLock::new(Heap::default()));
+
+/// Approximate bookkeeping cost of any heap value, besides its contents.
+const HEADER_SIZE: usize = 16;
+/// Approximate size of an instance field.
+const FIELD_SIZE: usize = 8;
+
+const JAVA_LANG_STRING: &str = "java/lang/String";
+/// Encodings of the characters of a `java/lang/String`, as given by its `coder` field.
+const LATIN1: i32 = 0;
+const UTF16: i32 = 1;
 
 static HEAP_ID: AtomicI32 = AtomicI32::new(1);
 
@@ -26,14 +45,26 @@ static HEAP_ID: AtomicI32 = AtomicI32::new(1);
 enum HeapValue {
     Object(Instance),
     Array(Array),
+    ObjectArray(ObjectArray),
 }
 
 #[derive(Debug)]
 struct Array {
     name: String,
+    /// Type of the elements, e.g. `java/lang/String` for `[Ljava/lang/String;` or `[I` for `[[I`.
+    element_name: String,
     value: Vec<u8>,
 }
 
+/// An array of references, such as `[Ljava/lang/String;` or `[[I`, holding the heap id of each
+/// element, with 0 for null.
+#[derive(Debug)]
+struct ObjectArray {
+    name: String,
+    element_name: String,
+    elements: Vec<i32>,
+}
+
 #[derive(Debug)]
 /// Represents a Java object instance in the JVM heap.
 pub(in crate::vm) struct Instance {
@@ -60,44 +91,172 @@ where
 }
 
 impl Heap {
+    /// Creates a heap which fails allocations with an `OutOfMemoryError` once `capacity` bytes
+    /// are taken.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Bounds the heap to `capacity` bytes, or lifts the bound if `None`. Values allocated
+    /// already are kept, even if they exceed the new capacity.
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+    }
+
+    /// Approximate number of bytes taken by the allocated values.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
     /// Allocates a new *zeroed* array in the heap with the given `length`.
     /// Returns its heap ID.
-    pub fn allocate_array(&mut self, name: &str, length: i32) -> i32 {
+    pub fn allocate_array(&mut self, name: &str, length: i32) -> Result<i32> {
+        if length < 0 {
+            let message = length.to_string();
+            return Err(VmException::new("java/lang/NegativeArraySizeException", message).into());
+        }
+
+        if ObjectArray::holds_references(name) {
+            let length = length as usize;
+            self.reserve(length.saturating_mul(size_of::<i32>()))?;
+
+            let id = Self::next_id();
+            let array = ObjectArray::new(name, length);
+            self.objects.insert(id, HeapValue::ObjectArray(array));
+            return Ok(id);
+        }
+
         let element_size = Array::size(name);
-        let len = (length as usize) * element_size;
+        let len = (length as usize).saturating_mul(element_size);
+        self.reserve(len)?;
         let value = vec![0u8; len];
 
-        let array = Array {
-            name: name.to_string(),
-            value,
-        };
+        let array = Array::new(name, value);
         let id = Self::next_id();
 
         self.objects.insert(id, HeapValue::Array(array));
-        id
+        Ok(id)
     }
 
     // Allocates a new array in the heap initialised with the given values.
     // Returns its heap ID.
-    pub fn allocate_array_with_values(&mut self, name: &str, array: Vec<u8>) -> i32 {
+    pub fn allocate_array_with_values(&mut self, name: &str, array: Vec<u8>) -> Result<i32> {
+        self.reserve(array.len())?;
         let id = Self::next_id();
-        let array = Array {
-            name: name.to_string(),
-            value: array,
-        };
+        let array = Array::new(name, array);
 
         self.objects.insert(id, HeapValue::Array(array));
-        id
+        Ok(id)
     }
 
     /// Allocates this given object instance into the heap.
     /// Returns its heap ID.
-    pub fn allocate_instance(&mut self, instance: Instance) -> i32 {
+    pub fn allocate_instance(&mut self, instance: Instance) -> Result<i32> {
+        let fields: usize = instance.fields.values().map(IndexMap::len).sum();
+        self.reserve(fields * FIELD_SIZE)?;
+
+        let id = Self::next_id();
+        self.objects.insert(id, HeapValue::Object(instance));
+        Ok(id)
+    }
+
+    /// Allocates an exception raised by the VM itself. Unlike other values, it's allocated even
+    /// beyond the capacity, so that an `OutOfMemoryError` can still be thrown, and caught, once
+    /// the heap is full.
+    pub fn allocate_exception(&mut self, instance: Instance) -> i32 {
+        self.used = self.used.saturating_add(HEADER_SIZE);
+
         let id = Self::next_id();
         self.objects.insert(id, HeapValue::Object(instance));
         id
     }
 
+    /// Allocates a `java/lang/String` holding `value`, laid out as the JDK does: its characters
+    /// are kept in a `byte[]`, encoded as Latin-1 if they all fit, or as UTF-16 otherwise.
+    pub fn allocate_string(&mut self, value: &str) -> Result<i32> {
+        let (bytes, coder) = match value.chars().all(|char| (char as u32) <= 0xFF) {
+            true => (value.chars().map(|char| char as u8).collect(), LATIN1),
+            false => (
+                value.encode_utf16().flat_map(u16::to_ne_bytes).collect(),
+                UTF16,
+            ),
+        };
+        let array = self.allocate_array_with_values("[B", bytes)?;
+
+        let fields = [("value", array), ("coder", coder)]
+            .map(|(name, value)| (name.to_string(), FieldValue::new(vec![value])));
+        let mut instance = Instance::without_fields(JAVA_LANG_STRING);
+        instance
+            .fields
+            .insert(JAVA_LANG_STRING.to_string(), fields.into());
+
+        self.allocate_instance(instance)
+    }
+
+    /// Reference to the interned `java/lang/String` holding `value`, allocating it the first
+    /// time these contents are interned.
+    pub fn intern_string(&mut self, value: &str) -> Result<i32> {
+        if let Some(&reference) = self.strings.get(value) {
+            return Ok(reference);
+        }
+
+        let reference = self.allocate_string(value)?;
+        self.strings.insert(value.to_string(), reference);
+        Ok(reference)
+    }
+
+    /// Contents of the `java/lang/String` at `string_ref`.
+    pub fn get_string(&self, string_ref: i32) -> Result<String> {
+        let field = |field| -> Result<i32> {
+            let value = self.get_field_value(string_ref, JAVA_LANG_STRING, field)?;
+            value.first().copied().ok_or(
+                Error::InvalidObjectAcess {
+                    classname: JAVA_LANG_STRING.to_string(),
+                    field: field.to_string(),
+                }
+                .into(),
+            )
+        };
+        let (array_ref, coder) = (field("value")?, field("coder")?);
+
+        let bytes = match self.objects.get(&array_ref) {
+            Some(HeapValue::Array(array)) => &array.value,
+            _ => {
+                return Err(Error::InvalidObjectAcess {
+                    classname: JAVA_LANG_STRING.to_string(),
+                    field: "value".to_string(),
+                }
+                .into());
+            }
+        };
+
+        match coder {
+            UTF16 => {
+                let units = bytes
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_ne_bytes([unit[0], unit[1]]));
+                Ok(char::decode_utf16(units)
+                    .map(|char| char.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect())
+            }
+            _ => Ok(bytes.iter().map(|&byte| byte as char).collect()),
+        }
+    }
+
+    /// Accounts for a new value with `size` bytes of contents, failing if it doesn't fit.
+    fn reserve(&mut self, size: usize) -> Result<()> {
+        let used = self.used.saturating_add(HEADER_SIZE + size);
+        if self.capacity.is_some_and(|capacity| used > capacity) {
+            return Err(VmException::new("java/lang/OutOfMemoryError", "Java heap space").into());
+        }
+
+        self.used = used;
+        Ok(())
+    }
+
     pub fn get_field_value<'a>(
         &'a self,
         obj_ref: i32,
@@ -105,15 +264,36 @@ impl Heap {
         field: &'a str,
     ) -> Result<Vec<i32>> {
         if obj_ref == 0 {
-            return Err(Error::InvalidObjectAcess {
+            let message = format!("cannot read field {classname}.{field} of null");
+            return Err(VmException::null_pointer(message).into());
+        }
+
+        match self.objects.get(&obj_ref) {
+            Some(HeapValue::Object(instance)) => instance.get_value(classname, field),
+            _ => Err(Error::InvalidObjectAcess {
                 classname: classname.to_string(),
                 field: field.to_string(),
             }
-            .into());
+            .into()),
         }
+    }
 
-        match self.objects.get(&obj_ref) {
-            Some(HeapValue::Object(instance)) => instance.get_value(classname, field),
+    /// Writes `value` to the field `field` of the object at `obj_ref`, resolved from `classname`
+    /// up through its superclasses, just as [`get_field_value`](Self::get_field_value) reads it.
+    pub fn set_field_value(
+        &mut self,
+        obj_ref: i32,
+        classname: &str,
+        field: &str,
+        value: Vec<i32>,
+    ) -> Result<()> {
+        if obj_ref == 0 {
+            let message = format!("cannot assign field {classname}.{field} of null");
+            return Err(VmException::null_pointer(message).into());
+        }
+
+        match self.objects.get_mut(&obj_ref) {
+            Some(HeapValue::Object(instance)) => instance.set_value(classname, field, value),
             _ => Err(Error::InvalidObjectAcess {
                 classname: classname.to_string(),
                 field: field.to_string(),
@@ -123,8 +303,65 @@ impl Heap {
     }
 
     pub fn get_array_value(&self, array_ref: i32, index: i32) -> Result<Vec<i32>> {
+        if array_ref == 0 {
+            return Err(VmException::null_pointer("cannot load from a null array").into());
+        }
+
         match self.objects.get(&array_ref) {
             Some(HeapValue::Array(array)) => array.get(index),
+            Some(HeapValue::ObjectArray(array)) => array.get(index),
+            _ => Err(Error::InvalidArrayAccess(index as usize).into()),
+        }
+    }
+
+    /// Number of elements of the array at `array_ref`.
+    pub fn array_length(&self, array_ref: i32) -> Result<i32> {
+        if array_ref == 0 {
+            return Err(VmException::null_pointer("cannot read the length of a null array").into());
+        }
+
+        match self.objects.get(&array_ref) {
+            Some(HeapValue::Array(array)) => {
+                Ok((array.value.len() / Array::size(&array.name)) as i32)
+            }
+            Some(HeapValue::ObjectArray(array)) => Ok(array.elements.len() as i32),
+            _ => Err(Error::InvalidReference(array_ref).into()),
+        }
+    }
+
+    /// Class name of the object or array at `reference`.
+    pub fn classname(&self, reference: i32) -> Option<&str> {
+        match self.objects.get(&reference)? {
+            HeapValue::Object(instance) => Some(&instance.name),
+            HeapValue::Array(array) => Some(&array.name),
+            HeapValue::ObjectArray(array) => Some(&array.name),
+        }
+    }
+
+    /// Element type of the array at `array_ref`.
+    pub fn array_element_name(&self, array_ref: i32) -> Option<&str> {
+        match self.objects.get(&array_ref)? {
+            HeapValue::Array(array) => Some(array.element_name()),
+            HeapValue::ObjectArray(array) => Some(&array.element_name),
+            HeapValue::Object(_) => None,
+        }
+    }
+
+    /// Writes the `value` reference at `index` of the reference array at `array_ref`.
+    pub fn set_array_reference(&mut self, array_ref: i32, index: i32, value: i32) -> Result<()> {
+        self.set_array_value(array_ref, index, &[value])
+    }
+
+    /// Writes the element at `index` of the array at `array_ref` from the slots `value` takes,
+    /// laid out as [`Heap::get_array_value`] returns them.
+    pub fn set_array_value(&mut self, array_ref: i32, index: i32, value: &[i32]) -> Result<()> {
+        if array_ref == 0 {
+            return Err(VmException::null_pointer("cannot store into a null array").into());
+        }
+
+        match self.objects.get_mut(&array_ref) {
+            Some(HeapValue::Array(array)) => array.set(index, value),
+            Some(HeapValue::ObjectArray(array)) => array.set(index, value),
             _ => Err(Error::InvalidArrayAccess(index as usize).into()),
         }
     }
@@ -135,29 +372,66 @@ impl Heap {
 }
 
 impl Instance {
+    /// Creates an instance carrying no fields, for objects the VM synthesises before their class
+    /// can be loaded.
+    pub fn without_fields(classname: &str) -> Self {
+        Self {
+            name: classname.to_string(),
+            fields: IndexMap::new(),
+        }
+    }
+
     fn get_value(&self, classname: &str, field: &str) -> Result<Vec<i32>> {
-        self.lookup_field(classname, field)
-            .and_then(|value| Some(value.value()))
+        self.lookup_field(classname, field)?
             .ok_or(Error::InvalidObjectAcess {
                 classname: classname.to_string(),
                 field: field.to_string(),
             })?
+            .value()
     }
 
-    fn lookup_field(&self, from: &str, field: &str) -> Option<&FieldValue> {
-        match self.fields.get_index_of(from) {
-            Some(index) => self
-                .fields
-                .iter()
-                .take(index + 1)
-                .rev()
-                .find_map(|(_, map)| map.get(field)),
-            _ => None,
+    fn set_value(&mut self, classname: &str, field: &str, value: Vec<i32>) -> Result<()> {
+        self.lookup_field(classname, field)?
+            .ok_or(Error::InvalidObjectAcess {
+                classname: classname.to_string(),
+                field: field.to_string(),
+            })?
+            .set(value)
+    }
+
+    /// Resolves `field` as JVMS (5.4.3.2) does: among the fields declared by `from`, the class
+    /// named by the field reference, and then by each of its superclasses in turn, so a field
+    /// shadows any field of the same name declared higher up the hierarchy.
+    fn lookup_field(&self, from: &str, field: &str) -> Result<Option<&FieldValue>> {
+        let mut current = from.to_string();
+        while let Some(fields) = self.fields.get(&current) {
+            if let Some(value) = fields.get(field) {
+                return Ok(Some(value));
+            }
+
+            match with_method_area(|area| area.get(&current))?.parent() {
+                Some(parent) => current = parent.to_string(),
+                None => break,
+            }
         }
+
+        Ok(None)
     }
 }
 
 impl Array {
+    fn new(name: &str, value: Vec<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            element_name: component_name(name).unwrap_or(name).to_string(),
+            value,
+        }
+    }
+
+    fn element_name(&self) -> &str {
+        &self.element_name
+    }
+
     fn size(name: &str) -> usize {
         match name {
             "[B" => 1, // byte
@@ -172,35 +446,324 @@ impl Array {
         }
     }
 
-    fn get(&self, index: i32) -> Result<Vec<i32>> {
+    /// Writes the element at `index` from its operand stack slots, the inverse of [`Array::get`]:
+    /// `int`s are truncated to the width of `byte`, `char` and `short` elements.
+    fn set(&mut self, index: i32, value: &[i32]) -> Result<()> {
         let size = Self::size(&self.name);
-        let offset = index as usize * size;
-
-        let slice = &self.value[offset..offset + size];
-        match size {
-            1..4 => {
-                let mut buff = [0u8; 4];
-                match cfg!(target_endian = "big") {
-                    true => buff[4 - size..4].copy_from_slice(slice),
-                    false => buff[0..size].copy_from_slice(slice),
-                };
-
-                let value = i32::from_ne_bytes(buff);
-                Ok(vec![value])
+        let slice = usize::try_from(index)
+            .ok()
+            .and_then(|offset| self.value.get_mut(offset * size..(offset + 1) * size))
+            .ok_or(Error::InvalidArrayAccess(index as usize))?;
+
+        match (size, value) {
+            (1, [value, ..]) => slice.copy_from_slice(&(*value as i8).to_ne_bytes()),
+            (2, [value, ..]) => slice.copy_from_slice(&(*value as i16).to_ne_bytes()),
+            (4, [value, ..]) => slice.copy_from_slice(&value.to_ne_bytes()),
+            (8, [high, low, ..]) => {
+                let value = ((*high as i64) << 32) | *low as u32 as i64;
+                slice.copy_from_slice(&value.to_ne_bytes());
             }
-            8 => {
-                let mut buff = [0u8; 8];
-                buff.copy_from_slice(slice);
+            _ => return Err(Error::InvalidArrayEntrySize(size).into()),
+        }
+
+        Ok(())
+    }
 
-                let hi = i32::from_ne_bytes(buff[0..4].try_into().unwrap());
-                let lo = i32::from_ne_bytes(buff[4..8].try_into().unwrap());
+    /// Reads the element at `index` as the operand stack slots it takes. Elements are stored in
+    /// native byte order; `byte`s and `short`s are sign-extended while `char`s are zero-extended,
+    /// and `long`s and `double`s are split into their high and low halves, in that order.
+    fn get(&self, index: i32) -> Result<Vec<i32>> {
+        let size = Self::size(&self.name);
+        let slice = usize::try_from(index)
+            .ok()
+            .and_then(|offset| self.value.get(offset * size..(offset + 1) * size))
+            .ok_or(Error::InvalidArrayAccess(index as usize))?;
 
-                match cfg!(target_endian = "big") {
-                    true => Ok(vec![hi, lo]),
-                    false => Ok(vec![lo, hi]),
-                }
+        match (size, self.name.as_str()) {
+            (1, _) => Ok(vec![i8::from_ne_bytes([slice[0]]) as i32]),
+            (2, "[C") => Ok(vec![u16::from_ne_bytes([slice[0], slice[1]]) as i32]),
+            (2, _) => Ok(vec![i16::from_ne_bytes([slice[0], slice[1]]) as i32]),
+            (4, _) => Ok(vec![i32::from_ne_bytes(slice.try_into().unwrap())]),
+            (8, _) => {
+                let value = i64::from_ne_bytes(slice.try_into().unwrap());
+                Ok(vec![(value >> 32) as i32, value as i32])
             }
             _ => Err(Error::InvalidArrayEntrySize(size).into()),
         }
     }
 }
+
+impl ObjectArray {
+    fn new(name: &str, length: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            element_name: component_name(name).unwrap_or(name).to_string(),
+            elements: vec![0; length],
+        }
+    }
+
+    /// Whether the elements of the array class `name` are references, i.e. objects or arrays.
+    fn holds_references(name: &str) -> bool {
+        name.starts_with("[L") || name.starts_with("[[")
+    }
+
+    fn get(&self, index: i32) -> Result<Vec<i32>> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.elements.get(index))
+            .map(|&element| vec![element])
+            .ok_or(Error::InvalidArrayAccess(index as usize).into())
+    }
+
+    fn set(&mut self, index: i32, value: &[i32]) -> Result<()> {
+        let element = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.elements.get_mut(index))
+            .ok_or(Error::InvalidArrayAccess(index as usize))?;
+        *element = *value.first().ok_or(Error::InvalidArrayEntrySize(0))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::runtime::method_area::{Class, MethodArea};
+
+    #[test]
+    fn negative_array_size() -> Result<()> {
+        let mut heap = Heap::default();
+
+        for name in ["[I", "[J", "[Ljava/lang/Object;", "[[I"] {
+            assert!(matches!(
+                heap.allocate_array(name, -1),
+                Err(VmError::Exception(VmException { class, message }))
+                    if class == "java/lang/NegativeArraySizeException" && message == "-1"
+            ));
+        }
+        // nothing was reserved for the rejected arrays
+        assert_eq!(heap.used(), 0);
+
+        let empty = heap.allocate_array("[I", 0)?;
+        assert_eq!(heap.array_length(empty)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn array_element_names() -> Result<()> {
+        let mut heap = Heap::default();
+
+        let strings = heap.allocate_array("[Ljava/lang/String;", 2)?;
+        assert_eq!(heap.array_element_name(strings), Some("java/lang/String"));
+
+        let matrix = heap.allocate_array("[[I", 2)?;
+        assert_eq!(heap.array_element_name(matrix), Some("[I"));
+
+        let bytes = heap.allocate_array_with_values("[B", vec![1, 2])?;
+        assert_eq!(heap.array_element_name(bytes), Some("B"));
+
+        let object = heap.allocate_instance(Instance::without_fields("java/lang/Object"))?;
+        assert_eq!(heap.array_element_name(object), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn array_element_widths() -> Result<()> {
+        let mut heap = Heap::default();
+
+        let bytes = heap.allocate_array_with_values("[B", vec![0xFE])?;
+        assert_eq!(heap.get_array_value(bytes, 0)?, [-2]);
+
+        let chars = heap.allocate_array_with_values("[C", 0xFFFFu16.to_ne_bytes().to_vec())?;
+        assert_eq!(heap.get_array_value(chars, 0)?, [0xFFFF]);
+
+        let shorts = heap.allocate_array_with_values("[S", (-3i16).to_ne_bytes().to_vec())?;
+        assert_eq!(heap.get_array_value(shorts, 0)?, [-3]);
+
+        let long = -0x1_2345_6789_i64;
+        let longs = heap.allocate_array_with_values("[J", long.to_ne_bytes().to_vec())?;
+        let [high, low] = heap.get_array_value(longs, 0)?[..] else {
+            panic!("a long takes two slots");
+        };
+        assert_eq!(((high as i64) << 32) | low as u32 as i64, long);
+
+        assert!(heap.get_array_value(longs, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn array_store_round_trip() -> Result<()> {
+        let mut heap = Heap::default();
+        let double = (-1.5f64).to_bits() as i64;
+        let cases: [(&str, &[i32], &[i32]); 8] = [
+            ("[B", &[0x1FF], &[-1]),
+            ("[Z", &[1], &[1]),
+            ("[C", &[-1], &[0xFFFF]),
+            ("[S", &[0x18000], &[-0x8000]),
+            ("[I", &[i32::MIN], &[i32::MIN]),
+            ("[F", &[2.5f32.to_bits() as i32], &[2.5f32.to_bits() as i32]),
+            ("[J", &[-2, 7], &[-2, 7]),
+            (
+                "[D",
+                &[(double >> 32) as i32, double as i32],
+                &[(double >> 32) as i32, double as i32],
+            ),
+        ];
+
+        for (name, stored, loaded) in cases {
+            let array = heap.allocate_array(name, 2)?;
+            heap.set_array_value(array, 1, stored)?;
+
+            assert_eq!(heap.get_array_value(array, 1)?, loaded, "{name}");
+            assert_eq!(heap.get_array_value(array, 0)?, vec![0; loaded.len()]);
+        }
+
+        let ints = heap.allocate_array("[I", 1)?;
+        assert!(heap.set_array_value(ints, 1, &[1]).is_err());
+        assert!(heap.set_array_value(0, 0, &[1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn object_array_round_trip() -> Result<()> {
+        let mut heap = Heap::default();
+        let array = heap.allocate_array("[Ljava/lang/Object;", 3)?;
+        let first = heap.allocate_instance(Instance::without_fields("java/lang/Object"))?;
+        let second = heap.allocate_array("[I", 4)?;
+
+        heap.set_array_reference(array, 0, first)?;
+        heap.set_array_reference(array, 2, second)?;
+
+        assert_eq!(heap.get_array_value(array, 0)?, [first]);
+        assert_eq!(heap.get_array_value(array, 1)?, [0]);
+        assert_eq!(heap.get_array_value(array, 2)?, [second]);
+        assert!(heap.get_array_value(array, 3).is_err());
+        assert!(heap.set_array_reference(array, -1, first).is_err());
+
+        assert_eq!(heap.array_length(array)?, 3);
+        assert_eq!(heap.array_length(second)?, 4);
+        assert_eq!(heap.classname(array), Some("[Ljava/lang/Object;"));
+        assert_eq!(heap.array_element_name(array), Some("java/lang/Object"));
+
+        let matrix = heap.allocate_array("[[J", 2)?;
+        heap.set_array_reference(matrix, 1, second)?;
+        assert_eq!(heap.get_array_value(matrix, 1)?, [second]);
+        Ok(())
+    }
+
+    /// Instance of the last class of `hierarchy`, which lists each class, from the root down,
+    /// along with the fields it declares and their values.
+    fn instance(hierarchy: &[(&str, &[(&str, i32)])]) -> Instance {
+        let _ = MethodArea::initialise(".");
+        let mut parent: Option<&str> = None;
+        let mut instance = Instance::without_fields("");
+
+        for &(classname, fields) in hierarchy {
+            let class = Class::with_classname(classname);
+            let class = match parent {
+                Some(parent) => class.with_parent(parent),
+                None => class,
+            };
+            with_method_area(|area| area.insert(class));
+
+            let fields = fields
+                .iter()
+                .map(|&(name, value)| (name.to_string(), FieldValue::new(vec![value])));
+            instance
+                .fields
+                .insert(classname.to_string(), fields.collect());
+            instance.name = classname.to_string();
+            parent = Some(classname);
+        }
+
+        instance
+    }
+
+    #[test]
+    fn field_written_then_read() -> Result<()> {
+        let mut heap = Heap::default();
+        let point = heap.allocate_instance(instance(&[("Point", &[("x", 0), ("y", 0)])]))?;
+
+        heap.set_field_value(point, "Point", "y", vec![7])?;
+        assert_eq!(heap.get_field_value(point, "Point", "y")?, [7]);
+        assert_eq!(heap.get_field_value(point, "Point", "x")?, [0]);
+
+        assert!(matches!(
+            heap.set_field_value(point, "Point", "z", vec![1]),
+            Err(VmError::Runtime(Error::InvalidObjectAcess { .. }))
+        ));
+        assert!(matches!(
+            heap.set_field_value(0, "Point", "x", vec![1]),
+            Err(VmError::Exception(exception)) if exception.class == "java/lang/NullPointerException"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shadowed_fields() -> Result<()> {
+        let mut heap = Heap::default();
+        let derived = instance(&[
+            ("ShadowBase", &[("x", 1), ("z", 3)]),
+            ("ShadowDerived", &[("x", 2)]),
+        ]);
+        let derived = heap.allocate_instance(derived)?;
+
+        assert_eq!(heap.get_field_value(derived, "ShadowDerived", "x")?, [2]);
+        assert_eq!(heap.get_field_value(derived, "ShadowBase", "x")?, [1]);
+        assert_eq!(heap.get_field_value(derived, "ShadowDerived", "z")?, [3]);
+
+        heap.set_field_value(derived, "ShadowBase", "x", vec![10])?;
+        assert_eq!(heap.get_field_value(derived, "ShadowDerived", "x")?, [2]);
+        assert_eq!(heap.get_field_value(derived, "ShadowBase", "x")?, [10]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn strings_round_trip() -> Result<()> {
+        let mut heap = Heap::default();
+
+        for value in ["hello", "", "café", "π ≈ 3.14", "🦀"] {
+            let string = heap.allocate_string(value)?;
+            assert_eq!(heap.classname(string), Some(JAVA_LANG_STRING));
+            assert_eq!(heap.get_string(string)?, value);
+        }
+
+        let object = heap.allocate_instance(Instance::without_fields("java/lang/Object"))?;
+        assert!(heap.get_string(object).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn allocation_beyond_capacity() -> Result<()> {
+        let mut heap = Heap::with_capacity(1024);
+        heap.allocate_array("[I", 16)?;
+        heap.allocate_instance(Instance::without_fields("java/lang/Object"))?;
+        let used = heap.used();
+
+        let result = heap.allocate_array("[J", 1 << 20);
+        assert!(matches!(
+            result,
+            Err(VmError::Exception(exception)) if exception.class == "java/lang/OutOfMemoryError"
+        ));
+        // nothing is accounted for the failed allocation
+        assert_eq!(heap.used(), used);
+
+        // so it can still be thrown
+        let error = Instance::without_fields("java/lang/OutOfMemoryError");
+        let reference = heap.allocate_exception(error);
+        assert_eq!(
+            heap.classname(reference),
+            Some("java/lang/OutOfMemoryError")
+        );
+
+        heap.set_capacity(None);
+        heap.allocate_array("[J", 1 << 20)?;
+
+        Ok(())
+    }
+}
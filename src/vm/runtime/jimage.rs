@@ -0,0 +1,343 @@
+//! Reader of the `lib/modules` image, in the jimage format JDKs ship their platform classes in
+//! since Java 9.
+//! The image starts with an index, a hash table mapping resource names such as
+//! `/java.base/java/lang/Object.class` to their location, followed by the resources themselves.
+//! Only what's needed to extract classes is supported, so compressed resources are rejected.
+
+use parking_lot::Mutex;
+use std::{
+    fmt::{self, Debug, Formatter},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+use thiserror::Error;
+
+const MAGIC: u32 = 0xCAFE_DADA;
+const MAJOR_VERSION: u16 = 1;
+/// Size of the header, made of seven `u32`.
+const HEADER_SIZE: usize = 7 * 4;
+/// Multiplier and default seed of the hash of resource names.
+const HASH_MULTIPLIER: u32 = 0x0100_0193;
+
+/// Kinds of the attributes describing the location of a resource.
+const ATTRIBUTE_END: u8 = 0;
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+const ATTRIBUTE_COUNT: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum JImageError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Invalid jimage magic: {0:#010x}")]
+    InvalidMagic(u32),
+    #[error("Unsupported jimage version {0}.{1}")]
+    UnsupportedVersion(u16, u16),
+    #[error("Resource {0} is compressed, which is not supported")]
+    Compressed(String),
+    #[error("jimage index is malformed at byte {0}")]
+    Malformed(usize),
+}
+
+type Result<T> = std::result::Result<T, JImageError>;
+
+pub(in crate::vm) struct JImage {
+    file: Mutex<File>,
+    /// The whole index: header, hash table, locations and strings.
+    index: Vec<u8>,
+    /// Whether the image was written by a big-endian platform.
+    big_endian: bool,
+    table_length: usize,
+    locations_size: usize,
+}
+
+/// Attributes of a resource location, indexed by their kind.
+struct Location([u64; ATTRIBUTE_COUNT]);
+
+impl JImage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+
+        let big_endian = match u32::from_le_bytes(header[0..4].try_into().unwrap()) {
+            MAGIC => false,
+            magic if magic.swap_bytes() == MAGIC => true,
+            magic => return Err(JImageError::InvalidMagic(magic)),
+        };
+        let field = |idx: usize| {
+            let bytes = header[idx * 4..idx * 4 + 4].try_into().unwrap();
+            match big_endian {
+                true => u32::from_be_bytes(bytes),
+                false => u32::from_le_bytes(bytes),
+            }
+        };
+
+        let version = field(1);
+        let (major, minor) = ((version >> 16) as u16, version as u16);
+        if major != MAJOR_VERSION {
+            return Err(JImageError::UnsupportedVersion(major, minor));
+        }
+
+        let table_length = field(4) as usize;
+        let locations_size = field(5) as usize;
+        let strings_size = field(6) as usize;
+        let index_size = HEADER_SIZE + table_length * 8 + locations_size + strings_size;
+
+        let mut index = header.to_vec();
+        index.resize(index_size, 0);
+        file.read_exact(&mut index[HEADER_SIZE..])?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+            index,
+            big_endian,
+            table_length,
+            locations_size,
+        })
+    }
+
+    /// Bytes of the class file of `classname`, given in its internal form, e.g.
+    /// `java/lang/Object`, or `None` if no module of the image defines it.
+    pub fn class_bytes(&self, classname: &str) -> Result<Option<Vec<u8>>> {
+        let package = classname
+            .rsplit_once('/')
+            .map_or("", |(package, _)| package)
+            .replace('/', ".");
+        let Some(module) = self.module_of(&package)? else {
+            return Ok(None);
+        };
+
+        self.resource(&format!("/{module}/{classname}.class"))
+    }
+
+    /// Module defining the classes of `package`, e.g. `java.base` for `java.lang`.
+    fn module_of(&self, package: &str) -> Result<Option<String>> {
+        // a package resource lists the modules it appears in, as pairs of an `is_empty` flag
+        // and the offset of the module name
+        let Some(modules) = self.resource(&format!("/packages/{package}"))? else {
+            return Ok(None);
+        };
+
+        let module = modules
+            .chunks_exact(8)
+            .map(|pair| (self.u32(&pair[0..4]), self.u32(&pair[4..8])))
+            .find(|&(is_empty, _)| is_empty == 0)
+            .map(|(_, offset)| self.string(offset as usize).map(String::from))
+            .transpose()?;
+
+        Ok(module)
+    }
+
+    /// Contents of the resource `name`, or `None` if there is no such resource.
+    fn resource(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let Some(location) = self.location(name)? else {
+            return Ok(None);
+        };
+        if location.0[ATTRIBUTE_COMPRESSED] != 0 {
+            return Err(JImageError::Compressed(name.to_string()));
+        }
+
+        let mut bytes = vec![0; location.0[ATTRIBUTE_UNCOMPRESSED] as usize];
+        let mut file = self.file.lock();
+        file.seek(SeekFrom::Start(
+            self.index.len() as u64 + location.0[ATTRIBUTE_OFFSET],
+        ))?;
+        file.read_exact(&mut bytes)?;
+
+        Ok(Some(bytes))
+    }
+
+    /// Looks `name` up in the hash table, where collisions are resolved by a redirect to either
+    /// a direct slot or a second hashing with another seed.
+    fn location(&self, name: &str) -> Result<Option<Location>> {
+        if self.table_length == 0 {
+            return Ok(None);
+        }
+
+        let slot = hash(name, HASH_MULTIPLIER) as usize % self.table_length;
+        let slot = match self.i32_at(HEADER_SIZE + slot * 4) {
+            0 => return Ok(None),
+            redirect if redirect < 0 => (-1 - redirect) as usize,
+            seed => hash(name, seed as u32) as usize % self.table_length,
+        };
+
+        let offset = self.u32_at(HEADER_SIZE + (self.table_length + slot) * 4) as usize;
+        let location = self.decode_location(offset)?;
+
+        // a name missing from the table still lands on the slot of another one
+        match self.full_name(&location)? == name {
+            true => Ok(Some(location)),
+            false => Ok(None),
+        }
+    }
+
+    /// Decodes the attributes at `offset` of the locations, each one a byte holding its kind and
+    /// length followed by a big-endian value, until an end marker.
+    fn decode_location(&self, offset: usize) -> Result<Location> {
+        let locations = self.locations_start();
+        let mut attributes = [0u64; ATTRIBUTE_COUNT];
+        let mut position = locations + offset;
+
+        loop {
+            let byte = *self
+                .index
+                .get(position)
+                .ok_or(JImageError::Malformed(position))?;
+            let kind = byte >> 3;
+            if kind == ATTRIBUTE_END {
+                break;
+            }
+
+            let length = (byte & 0x7) as usize + 1;
+            let value = self
+                .index
+                .get(position + 1..position + 1 + length)
+                .filter(|_| (kind as usize) < ATTRIBUTE_COUNT)
+                .ok_or(JImageError::Malformed(position))?;
+
+            attributes[kind as usize] = value
+                .iter()
+                .fold(0, |value, &byte| (value << 8) | byte as u64);
+            position += 1 + length;
+        }
+
+        Ok(Location(attributes))
+    }
+
+    /// Rebuilds the name of a resource as `/module/parent/base.extension`, leaving out the
+    /// missing parts.
+    fn full_name(&self, location: &Location) -> Result<String> {
+        let part = |kind: usize| self.string(location.0[kind] as usize);
+        let (module, parent, base, extension) = (
+            part(ATTRIBUTE_MODULE)?,
+            part(ATTRIBUTE_PARENT)?,
+            part(ATTRIBUTE_BASE)?,
+            part(ATTRIBUTE_EXTENSION)?,
+        );
+
+        let mut name = String::from("/");
+        if !module.is_empty() {
+            name.extend([module, "/"]);
+        }
+        if !parent.is_empty() {
+            name.extend([parent, "/"]);
+        }
+        name.push_str(base);
+        if !extension.is_empty() {
+            name.extend([".", extension]);
+        }
+
+        Ok(name)
+    }
+
+    /// Null-terminated string at `offset` of the strings table.
+    fn string(&self, offset: usize) -> Result<&str> {
+        let start = self.locations_start() + self.locations_size + offset;
+        let bytes = self
+            .index
+            .get(start..)
+            .ok_or(JImageError::Malformed(start))?;
+        let length = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or(JImageError::Malformed(start))?;
+
+        std::str::from_utf8(&bytes[..length]).map_err(|_| JImageError::Malformed(start))
+    }
+
+    fn locations_start(&self) -> usize {
+        HEADER_SIZE + self.table_length * 8
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        }
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        self.u32(&self.index[offset..offset + 4])
+    }
+
+    fn i32_at(&self, offset: usize) -> i32 {
+        self.u32_at(offset) as i32
+    }
+}
+
+impl Debug for JImage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JImage")
+            .field("table_length", &self.table_length)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Hash of resource names used by the index, the 32-bit FNV-1 of their UTF-8 bytes.
+fn hash(name: &str, seed: u32) -> u32 {
+    let hash = name.bytes().fold(seed, |hash, byte| {
+        hash.wrapping_mul(HASH_MULTIPLIER) ^ byte as u32
+    });
+
+    hash & 0x7FFF_FFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::Classfile;
+    use std::path::PathBuf;
+
+    /// Home of the JDK given by `JAVA_HOME` or, failing that, of the `java` found in `PATH`.
+    fn java_home() -> Option<PathBuf> {
+        if let Some(home) = std::env::var_os("JAVA_HOME") {
+            return Some(PathBuf::from(home));
+        }
+
+        std::env::split_paths(&std::env::var_os("PATH")?)
+            .map(|dir| dir.join("java"))
+            .find(|java| java.is_file())
+            .and_then(|java| java.canonicalize().ok())
+            .and_then(|java| Some(java.parent()?.parent()?.to_path_buf()))
+    }
+
+    #[test]
+    #[ignore = "needs a JDK with a module image, found through JAVA_HOME or PATH"]
+    fn object_from_modules() -> Result<()> {
+        let modules = java_home()
+            .map(|home| home.join("lib").join("modules"))
+            .filter(|modules| modules.is_file())
+            .expect("no JDK with a module image was found");
+
+        let image = JImage::open(modules)?;
+        let bytes = image.class_bytes("java/lang/Object")?.unwrap();
+
+        let arena = bumpalo::Bump::new();
+        let object = Classfile::new(&bytes, &arena).unwrap();
+        assert_eq!(object.class_name(), Some("java/lang/Object"));
+        assert_eq!(object.super_class(), None);
+
+        assert!(image.class_bytes("java/lang/DoesNotExist")?.is_none());
+        assert!(image.class_bytes("does/not/Exist")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_magic() {
+        let path = std::env::temp_dir().join("ignis-invalid.jimage");
+        std::fs::write(&path, [0u8; HEADER_SIZE]).unwrap();
+
+        assert!(matches!(
+            JImage::open(&path),
+            Err(JImageError::InvalidMagic(0))
+        ));
+    }
+}
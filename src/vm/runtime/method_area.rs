@@ -1,15 +1,37 @@
-use crate::vm::{
-    Result, VmError,
-    interpreter::StackFrame,
-    runtime::{RuntimeError, heap::Instance},
+use crate::{
+    classfile::{Classfile, FieldFlags, MethodFlags},
+    vm::{
+        ClassProvider, Result, Sink, VmError, VmException,
+        interpreter::{InterpreterError, StackFrame, verifier::verify},
+        runtime::{
+            RuntimeError,
+            constant_pool::{Constant, RuntimeConstantPool},
+            heap::{Instance, with_mut_heap},
+            jimage::JImage,
+        },
+    },
 };
 use dashmap::DashMap;
 use indexmap::IndexMap;
 use once_cell::sync::{Lazy, OnceCell};
-use parking_lot::RwLock;
-use std::{collections::HashMap, ops::Index, path::Path, sync::Arc};
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Debug, Formatter},
+    io::{self, Write},
+    ops::Index,
+    path::Path,
+    sync::Arc,
+};
 
 static METHOD_AREA: OnceCell<MethodArea> = OnceCell::new();
+const JAVA_LANG_OBJECT: &str = "java/lang/Object";
+const JAVA_LANG_CLASS: &str = "java/lang/Class";
+const JAVA_LANG_CLONEABLE: &str = "java/lang/Cloneable";
+const JAVA_IO_SERIALIZABLE: &str = "java/io/Serializable";
+const JAVA_LANG_THREAD: &str = "java/lang/Thread";
+const JAVA_LANG_THREAD_GROUP: &str = "java/lang/ThreadGroup";
+const JAVA_IO_PRINT_STREAM: &str = "java/io/PrintStream";
 static PRIMITIVE_TYPE: Lazy<HashMap<&str, &str>> = {
     Lazy::new(|| {
         let mut hm = HashMap::new();
@@ -25,6 +47,72 @@ static PRIMITIVE_TYPE: Lazy<HashMap<&str, &str>> = {
         hm
     })
 };
+/// Wrapper class of each primitive type, by descriptor, as used by autoboxing.
+static BOXED_TYPE: Lazy<HashMap<&str, &str>> = {
+    Lazy::new(|| {
+        let mut hm = HashMap::new();
+        hm.insert("B", "java/lang/Byte");
+        hm.insert("C", "java/lang/Character");
+        hm.insert("D", "java/lang/Double");
+        hm.insert("F", "java/lang/Float");
+        hm.insert("I", "java/lang/Integer");
+        hm.insert("J", "java/lang/Long");
+        hm.insert("S", "java/lang/Short");
+        hm.insert("Z", "java/lang/Boolean");
+        hm.insert("V", "java/lang/Void");
+        hm
+    })
+};
+
+/// Exceptions the VM may raise by itself, with their superclass, so handlers can catch them before
+/// any class is loaded.
+const VM_EXCEPTIONS: [(&str, &str); 18] = [
+    ("java/lang/Throwable", JAVA_LANG_OBJECT),
+    ("java/lang/Exception", "java/lang/Throwable"),
+    ("java/lang/RuntimeException", "java/lang/Exception"),
+    (
+        "java/lang/NullPointerException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/ArithmeticException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/ArrayStoreException",
+        "java/lang/RuntimeException",
+    ),
+    ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+    (
+        "java/lang/ArrayIndexOutOfBoundsException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/NegativeArraySizeException",
+        "java/lang/RuntimeException",
+    ),
+    ("java/lang/Error", "java/lang/Throwable"),
+    ("java/lang/LinkageError", "java/lang/Error"),
+    (
+        "java/lang/IncompatibleClassChangeError",
+        "java/lang/LinkageError",
+    ),
+    (
+        "java/lang/AbstractMethodError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+    (
+        "java/lang/ExceptionInInitializerError",
+        "java/lang/LinkageError",
+    ),
+    ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+    ("java/lang/VirtualMachineError", "java/lang/Error"),
+    (
+        "java/lang/OutOfMemoryError",
+        "java/lang/VirtualMachineError",
+    ),
+];
 
 #[derive(Debug)]
 pub(in crate::vm) struct MethodArea {
@@ -33,6 +121,42 @@ pub(in crate::vm) struct MethodArea {
     thread_id: OnceCell<i32>,
     /// Thread group created by the VM.
     group_thread_id: OnceCell<i32>,
+    /// Streams behind `System.out` and `System.err`.
+    stdout: StandardStream,
+    stderr: StandardStream,
+    sources: ClassSources,
+}
+
+/// Where the class files of classes not defined yet are looked up.
+struct ClassSources {
+    /// Supplied by the user, consulted before anything else.
+    provider: RwLock<Option<ClassProvider>>,
+    /// Module image of the JDK, holding the platform classes, if there is one.
+    modules: Option<JImage>,
+}
+
+/// Progress of the initialisation of a class, i.e. of running its `<clinit>`, see JVMS (5.5).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+enum Initialisation {
+    #[default]
+    Pending,
+    InProgress,
+    Done,
+    /// `<clinit>` failed, so the class can't be used anymore.
+    Erroneous,
+}
+
+/// Standard stream a program can print to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(in crate::vm) enum Stream {
+    Out,
+    Err,
+}
+
+/// A standard stream, along with the `java/io/PrintStream` object standing for it.
+struct StandardStream {
+    print_stream: OnceCell<i32>,
+    sink: Mutex<Sink>,
 }
 
 #[derive(Debug)]
@@ -41,9 +165,31 @@ pub(in crate::vm) struct Class {
     methods: IndexMap<String, Arc<Method>>,
     static_fields: IndexMap<String, Arc<FieldValue>>,
     parent: Option<String>,
+    /// Interfaces directly implemented by this class, or extended if it is an interface.
+    interfaces: Vec<String>,
+    /// Major version of the class file this class was defined from, if known.
+    major_version: Option<u16>,
+    /// Whether the class file sets `ACC_SUPER`, see [`Class::is_super`].
+    super_flag: bool,
+    constant_pool: RuntimeConstantPool,
+    /// Heap reference of the `java/lang/Class` object representing this class.
+    mirror: OnceCell<i32>,
+    initialisation: Mutex<Initialisation>,
 
     fields_hierarchy: OnceCell<IndexMap<String, IndexMap<String, FieldValue>>>,
     fields_schema: IndexMap<String, FieldValue>,
+    /// Built on first dispatch, see [`MethodArea::resolve_virtual`].
+    vtable: OnceCell<VTable>,
+}
+
+/// Methods invokable on the instances of a class, keyed by their `name:descriptor` signature.
+#[derive(Debug, Default)]
+struct VTable {
+    /// Methods declared by the class, overriding the ones inherited from its superclasses.
+    declared: IndexMap<String, Arc<Method>>,
+    /// Default methods of the implemented interfaces, in the order they're searched, for the
+    /// signatures no superclass declares.
+    defaults: IndexMap<String, Arc<Method>>,
 }
 
 #[derive(Debug)]
@@ -51,8 +197,8 @@ pub(in crate::vm) struct Method {
     classname: Arc<str>,
     signature: Arc<str>,
     context: Option<Context>,
-    /// Indicates wheter a method is native or not.
-    native: bool,
+    /// Access and property flags, e.g. whether the method is native or private.
+    flags: MethodFlags,
 
     annotations: Option<Vec<u8>>,
 }
@@ -62,6 +208,20 @@ pub(in crate::vm) struct Context {
     max_stack: u16,
     max_locals: u16,
     bytecode: Arc<[u8]>,
+    exception_handlers: Arc<[ExceptionHandler]>,
+}
+
+/// Entry of a method exception table, see JVMS (4.7.3).
+#[derive(Debug, PartialEq, Clone)]
+pub(in crate::vm) struct ExceptionHandler {
+    /// First pc covered by the handler.
+    pub start_pc: u16,
+    /// First pc past the ones covered by the handler.
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    /// Class of the exceptions caught by the handler, or `None` if it catches all of them, as
+    /// `finally` blocks do.
+    pub catch_type: Option<String>,
 }
 
 #[derive(Debug)]
@@ -89,8 +249,16 @@ impl MethodArea {
             .map_err(|_| RuntimeError::MethodAreaInitialised.into())
     }
 
+    pub fn is_initialised() -> bool {
+        METHOD_AREA.get().is_some()
+    }
+
     pub fn new<'a>(path: impl AsRef<Path>) -> Result<Self> {
         let modules = path.as_ref().join("lib").join("modules");
+        let modules = match modules.is_file() {
+            true => Some(JImage::open(modules).map_err(RuntimeError::from)?),
+            false => None,
+        };
         let classes = Self::generate_classes();
 
         Ok(Self {
@@ -98,6 +266,12 @@ impl MethodArea {
             reflection: DashMap::new(),
             thread_id: OnceCell::new(),
             group_thread_id: OnceCell::new(),
+            stdout: StandardStream::new(Box::new(io::stdout())),
+            stderr: StandardStream::new(Box::new(io::stderr())),
+            sources: ClassSources {
+                provider: RwLock::new(None),
+                modules,
+            },
         })
     }
 
@@ -114,8 +288,250 @@ impl MethodArea {
             return Ok(class);
         }
 
-        // TODO: load from file
-        todo!()
+        match self.class_bytes(classname)? {
+            Some(bytes) => {
+                let class = Class::define(&bytes)?;
+                if class.name != classname {
+                    return Err(RuntimeError::WrongClassName {
+                        expected: classname.to_string(),
+                        found: class.name,
+                    }
+                    .into());
+                }
+
+                Ok(self.insert(class))
+            }
+            None => Err(RuntimeError::ClassNotFound(classname.to_string()).into()),
+        }
+    }
+
+    /// Makes `provider` the first place class files are looked up in, replacing any previous one.
+    pub fn set_class_provider(&self, provider: ClassProvider) {
+        *self.sources.provider.write() = Some(provider);
+    }
+
+    /// Bytes of the class file of `classname`, looked up through the class provider, if any,
+    /// then in the module image of the JDK.
+    pub fn class_bytes(&self, classname: &str) -> Result<Option<Vec<u8>>> {
+        let provided = self
+            .sources
+            .provider
+            .read()
+            .as_ref()
+            .and_then(|provider| provider(classname));
+        if provided.is_some() {
+            return Ok(provided);
+        }
+
+        match &self.sources.modules {
+            Some(modules) => Ok(modules.class_bytes(classname).map_err(RuntimeError::from)?),
+            None => Ok(None),
+        }
+    }
+
+    /// Registers an already built class, replacing any class with the same name.
+    pub fn insert(&self, class: Class) -> Arc<Class> {
+        let class = Arc::new(class);
+        self.classes.insert(class.name.clone(), Arc::clone(&class));
+
+        class
+    }
+
+    /// Returns the heap reference of the `java/lang/Class` object for `classname`, allocating it
+    /// the first time the class is reflected upon.
+    pub fn class_object(&self, classname: &str) -> Result<i32> {
+        let class = self.get(classname)?;
+        let reference = class.mirror.get_or_try_init(|| {
+            let instance = Instance::without_fields(JAVA_LANG_CLASS);
+            let reference = with_mut_heap(|heap| heap.allocate_instance(instance))?;
+            self.reflection.insert(reference, classname.to_string());
+
+            Ok::<_, VmError>(reference)
+        })?;
+
+        Ok(*reference)
+    }
+
+    /// Name of the class reflected by the `java/lang/Class` object at `reference`, if it is one.
+    pub fn reflected_classname(&self, reference: i32) -> Option<String> {
+        self.reflection
+            .get(&reference)
+            .map(|classname| classname.value().clone())
+    }
+
+    /// Heap reference of the `java/lang/Thread` object standing for the main thread, allocated
+    /// on first use.
+    pub fn current_thread_id(&self) -> Result<i32> {
+        self.thread_id
+            .get_or_try_init(|| {
+                let instance = Instance::without_fields(JAVA_LANG_THREAD);
+                with_mut_heap(|heap| heap.allocate_instance(instance))
+            })
+            .copied()
+    }
+
+    /// Heap reference of the `java/lang/ThreadGroup` the main thread belongs to, allocated on
+    /// first use.
+    pub fn thread_group_id(&self) -> Result<i32> {
+        self.group_thread_id
+            .get_or_try_init(|| {
+                let instance = Instance::without_fields(JAVA_LANG_THREAD_GROUP);
+                with_mut_heap(|heap| heap.allocate_instance(instance))
+            })
+            .copied()
+    }
+
+    /// Heap reference of the `java/io/PrintStream` object writing to `stream`, allocated on first
+    /// use.
+    pub fn print_stream(&self, stream: Stream) -> Result<i32> {
+        self.standard_stream(stream)
+            .print_stream
+            .get_or_try_init(|| {
+                let instance = Instance::without_fields(JAVA_IO_PRINT_STREAM);
+                with_mut_heap(|heap| heap.allocate_instance(instance))
+            })
+            .copied()
+    }
+
+    /// Redirects whatever is printed to `stream` into `sink`.
+    pub fn set_sink(&self, stream: Stream, sink: Sink) {
+        *self.standard_stream(stream).sink.lock() = sink;
+    }
+
+    /// Standard stream behind the `print_stream` reference, if it's one of the print streams the
+    /// VM allocated, rather than one the program created.
+    pub fn standard_stream_of(&self, print_stream: i32) -> Option<Stream> {
+        [Stream::Out, Stream::Err].into_iter().find(|&stream| {
+            let reference = self.standard_stream(stream).print_stream.get();
+            reference == Some(&print_stream)
+        })
+    }
+
+    /// Writes `text` to the sink of `stream`.
+    pub fn print(&self, stream: Stream, text: &str) {
+        // just as `PrintStream` does, failing writes are silently ignored
+        let mut sink = self.standard_stream(stream).sink.lock();
+        let _ = sink.write_all(text.as_bytes()).and_then(|_| sink.flush());
+    }
+
+    fn standard_stream(&self, stream: Stream) -> &StandardStream {
+        match stream {
+            Stream::Out => &self.stdout,
+            Stream::Err => &self.stderr,
+        }
+    }
+
+    /// Whether a reference of class `from` can be assigned to one of class `to`, following the
+    /// superclass chain, the implemented interfaces and the covariance of reference arrays.
+    pub fn is_assignable(&self, from: &str, to: &str) -> Result<bool> {
+        if from == to || to == JAVA_LANG_OBJECT {
+            return Ok(true);
+        }
+
+        if let Some(from_component) = component_name(from) {
+            return match component_name(to) {
+                Some(to_component)
+                    if !PRIMITIVE_TYPE.contains_key(from_component)
+                        && !PRIMITIVE_TYPE.contains_key(to_component) =>
+                {
+                    self.is_assignable(from_component, to_component)
+                }
+                Some(_) => Ok(false),
+                None => Ok(matches!(to, JAVA_LANG_CLONEABLE | JAVA_IO_SERIALIZABLE)),
+            };
+        }
+
+        let mut interfaces = Vec::new();
+        let mut current = from.to_string();
+        while current != JAVA_LANG_OBJECT {
+            let class = self.get(&current)?;
+            interfaces.extend(class.interfaces.iter().cloned());
+            match class.parent.clone() {
+                Some(parent) if parent == to => return Ok(true),
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+
+        let mut visited = HashSet::new();
+        while let Some(interface) = interfaces.pop() {
+            if interface == to {
+                return Ok(true);
+            }
+            if visited.insert(interface.clone()) {
+                interfaces.extend(self.get(&interface)?.interfaces.iter().cloned());
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Method `signature` to invoke on an instance of `classname`: the one it declares or inherits
+    /// from its closest superclass or, failing that, a default method of one of the interfaces
+    /// it implements, searched breadth first. Lookups go through the virtual method table of the
+    /// class, so the hierarchy is only walked the first time.
+    pub fn resolve_virtual(&self, classname: &str, signature: &str) -> Result<Arc<Method>> {
+        let class = self.get(classname)?;
+        let vtable = self.vtable(&class)?;
+
+        [&vtable.declared, &vtable.defaults]
+            .into_iter()
+            .find_map(|methods| Class::lookup(methods, signature))
+            .ok_or(RuntimeError::MethodNotFound(signature.into()).into())
+    }
+
+    /// Method `signature` referenced through `classname`, declared by it or by its closest
+    /// superclass, see JVMS (5.4.3.3). Unlike [`resolve_virtual`](Self::resolve_virtual), it also
+    /// finds the methods that are never dispatched virtually, such as constructors.
+    pub fn resolve_method(&self, classname: &str, signature: &str) -> Result<Arc<Method>> {
+        let mut current = Some(classname.to_string());
+        while let Some(classname) = current {
+            let class = self.get(&classname)?;
+            if let Ok(method) = class.get_method(signature) {
+                return Ok(method);
+            }
+
+            current = class.parent.clone();
+        }
+
+        self.resolve_virtual(classname, signature)
+    }
+
+    /// Virtual method table of `class`, built from the one of its superclass.
+    fn vtable<'c>(&self, class: &'c Class) -> Result<&'c VTable> {
+        class.vtable.get_or_try_init(|| {
+            let mut declared = match &class.parent {
+                Some(parent) => self.vtable(&*self.get(parent)?)?.declared.clone(),
+                None => IndexMap::new(),
+            };
+            declared.extend(virtual_methods(&class.methods));
+
+            let mut interfaces = VecDeque::new();
+            let mut current = Some(class.name.clone());
+            while let Some(classname) = current {
+                let class = self.get(&classname)?;
+                interfaces.extend(class.interfaces.iter().cloned());
+                current = class.parent.clone();
+            }
+
+            let mut defaults = IndexMap::new();
+            let mut visited = HashSet::new();
+            while let Some(interface) = interfaces.pop_front() {
+                if !visited.insert(interface.clone()) {
+                    continue;
+                }
+
+                let interface = self.get(&interface)?;
+                for (signature, method) in virtual_methods(&interface.methods) {
+                    if !method.is_abstract() && !defaults.contains_key(&signature) {
+                        defaults.insert(signature, method);
+                    }
+                }
+                interfaces.extend(interface.interfaces.iter().cloned());
+            }
+
+            Ok(VTable { declared, defaults })
+        })
     }
 
     pub fn create_instance_with_default(&self, classname: &str) -> Result<Instance> {
@@ -147,14 +563,16 @@ impl MethodArea {
     }
 
     fn generate_classes() -> DashMap<String, Arc<Class>> {
-        PRIMITIVE_TYPE
+        let primitives = PRIMITIVE_TYPE
             .keys()
-            .map(|class_name| {
-                (
-                    class_name.to_string(),
-                    Arc::new(Self::generate_class(class_name)),
-                )
-            })
+            .map(|class_name| Self::generate_class(class_name));
+        let exceptions = VM_EXCEPTIONS
+            .iter()
+            .map(|(class_name, parent)| Self::generate_class(class_name).with_parent(parent));
+
+        primitives
+            .chain(exceptions)
+            .map(|class| (class.name.clone(), Arc::new(class)))
             .collect()
     }
 
@@ -170,6 +588,94 @@ impl MethodArea {
 }
 
 impl Class {
+    /// Defines a class from the bytes of its class file, verifying the code of its methods.
+    /// Instance and static fields start out zeroed, the latter until the class is initialised,
+    /// except for constant static fields that hold their `ConstantValue` right away. Bootstrap
+    /// methods aren't carried over yet.
+    pub fn define(bytes: &[u8]) -> Result<Self> {
+        let arena = bumpalo::Bump::new();
+        let classfile = Classfile::new(bytes, &arena).map_err(RuntimeError::from)?;
+        let owned = classfile.to_owned().map_err(RuntimeError::from)?;
+        let name = owned.class_name();
+
+        let constants = classfile.constant_pool_entries().map(Constant::from);
+        let mut class = Class::with_classname(name)
+            .with_major_version(owned.version().0)
+            .with_constant_pool(RuntimeConstantPool::new(constants, Vec::new()));
+        if let Some(parent) = owned.super_class() {
+            class = class.with_parent(parent);
+        }
+        if owned.is_super() {
+            class = class.with_super_flag();
+        }
+        for interface in owned.interface_names() {
+            class = class.with_interface(interface);
+        }
+
+        for field in &owned.fields {
+            let value = match field.descriptor.as_str() {
+                "J" | "D" => vec![0, 0],
+                _ => vec![0],
+            };
+            match field.flags.contains(FieldFlags::STATIC) {
+                true => {
+                    let value = match field.constant_value {
+                        Some(index) => class.constant_pool.constant_value(index)?,
+                        None => value,
+                    };
+                    class = class.with_static_field(&field.name, value)
+                }
+                false => {
+                    class
+                        .fields_schema
+                        .insert(field.name.clone(), FieldValue::new(value));
+                }
+            }
+        }
+
+        for method in &owned.methods {
+            let signature = format!("{}:{}", method.name, method.descriptor);
+            let method_flags = method.flags;
+            let method = match &method.code {
+                Some(code) => {
+                    let handlers = classfile
+                        .exception_table(&method.name, &method.descriptor)
+                        .map_err(RuntimeError::from)?
+                        .into_iter()
+                        .map(
+                            |(start_pc, end_pc, handler_pc, catch_type)| ExceptionHandler {
+                                start_pc,
+                                end_pc,
+                                handler_pc,
+                                catch_type: catch_type.map(String::from),
+                            },
+                        )
+                        .collect::<Vec<_>>();
+                    let descriptor = |index| class.constant_pool.member_descriptor(index).ok();
+                    verify(&code.bytecode, code.max_stack, &handlers, descriptor)
+                        .map_err(InterpreterError::from)?;
+
+                    Method::new(
+                        name,
+                        &signature,
+                        code.max_stack,
+                        code.max_locals,
+                        &code.bytecode,
+                    )
+                    .with_exception_handlers(handlers)
+                }
+                None => Method::without_code(
+                    name,
+                    &signature,
+                    method.flags.contains(MethodFlags::NATIVE),
+                ),
+            };
+            class = class.with_method(method.with_flags(method_flags));
+        }
+
+        Ok(class)
+    }
+
     pub fn with_classname(classname: &str) -> Self {
         Self {
             name: classname.to_string(),
@@ -177,16 +683,110 @@ impl Class {
             static_fields: IndexMap::new(),
             fields_schema: IndexMap::new(),
             fields_hierarchy: OnceCell::new(),
+            vtable: OnceCell::new(),
             parent: None,
+            interfaces: Vec::new(),
+            major_version: None,
+            super_flag: false,
+            constant_pool: RuntimeConstantPool::default(),
+            mirror: OnceCell::new(),
+            initialisation: Mutex::default(),
         }
     }
 
+    pub fn with_constant_pool(mut self, constant_pool: RuntimeConstantPool) -> Self {
+        self.constant_pool = constant_pool;
+        self
+    }
+
+    pub fn with_parent(mut self, parent: &str) -> Self {
+        self.parent = Some(parent.to_string());
+        self
+    }
+
+    /// Declares the instance field `name`, which new instances start out with `value` in.
+    pub fn with_field(mut self, name: &str, value: Vec<i32>) -> Self {
+        self.fields_schema
+            .insert(name.to_string(), FieldValue::new(value));
+        self
+    }
+
+    /// Declares the static field `name`, holding `value` until the class initialises it.
+    pub fn with_static_field(mut self, name: &str, value: Vec<i32>) -> Self {
+        self.static_fields
+            .insert(name.to_string(), Arc::new(FieldValue::new(value)));
+        self
+    }
+
+    pub fn with_interface(mut self, interface: &str) -> Self {
+        self.interfaces.push(interface.to_string());
+        self
+    }
+
+    pub fn with_major_version(mut self, major_version: u16) -> Self {
+        self.major_version = Some(major_version);
+        self
+    }
+
+    pub fn with_super_flag(mut self) -> Self {
+        self.super_flag = true;
+        self
+    }
+
+    /// Whether `invokespecial` looks methods of superclasses up from the direct superclass of
+    /// this class, rather than running the one it references, as set by `ACC_SUPER`.
+    pub fn is_super(&self) -> bool {
+        self.super_flag
+    }
+
+    /// Adds `method`, keyed by its `name:descriptor` signature.
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.methods
+            .insert(method.signature.to_string(), Arc::new(method));
+        self
+    }
+
+    pub fn major_version(&self) -> Option<u16> {
+        self.major_version
+    }
+
+    pub fn parent(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    pub fn interfaces(&self) -> &[String] {
+        &self.interfaces
+    }
+
+    pub fn constant_pool(&self) -> &RuntimeConstantPool {
+        &self.constant_pool
+    }
+
     pub fn get_method(&self, signature: &str) -> Result<Arc<Method>> {
         self.get_full_method(signature)
             .and_then(|(_, method)| Some(method))
             .ok_or(RuntimeError::MethodNotFound(signature.into()).into())
     }
 
+    /// Constructor taking the parameters of `arg_descriptor`, e.g. `(Ljava/lang/String;I)`.
+    /// Unlike [`get_method`](Self::get_method), only an exact match is returned, since every
+    /// overload shares the `<init>` name.
+    pub fn find_constructor(&self, arg_descriptor: &str) -> Result<Arc<Method>> {
+        let signature = format!("<init>:{arg_descriptor}V");
+        self.methods
+            .get(&signature)
+            .cloned()
+            .ok_or(RuntimeError::MethodNotFound(signature).into())
+    }
+
+    /// Method `signature` among `methods`, falling back to a method keyed by its name alone.
+    fn lookup(methods: &IndexMap<String, Arc<Method>>, signature: &str) -> Option<Arc<Method>> {
+        methods
+            .get(signature)
+            .or_else(|| methods.get(signature.split(':').next()?))
+            .cloned()
+    }
+
     fn get_full_method(&self, signature: &str) -> Option<(usize, Arc<Method>)> {
         self.methods
             .get_full(signature)
@@ -198,6 +798,33 @@ impl Class {
             })
     }
 
+    /// Marks the class as being initialised, returning whether the caller is the one that must
+    /// do it. A class already initialised, or being initialised by an enclosing `<clinit>`, is
+    /// left alone, while one whose initialisation failed raises `NoClassDefFoundError`.
+    pub fn begin_initialisation(&self) -> Result<bool> {
+        let mut state = self.initialisation.lock();
+        match *state {
+            Initialisation::Pending => {
+                *state = Initialisation::InProgress;
+                Ok(true)
+            }
+            Initialisation::Erroneous => {
+                let message = format!("Could not initialize class {}", self.name);
+                Err(VmException::new("java/lang/NoClassDefFoundError", message).into())
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn finish_initialisation(&self) {
+        *self.initialisation.lock() = Initialisation::Done;
+    }
+
+    /// Marks the initialisation of the class as failed, for good.
+    pub fn fail_initialisation(&self) {
+        *self.initialisation.lock() = Initialisation::Erroneous;
+    }
+
     pub fn get_static(&self, static_field: &str) -> Option<Arc<FieldValue>> {
         self.static_fields
             .get(static_field)
@@ -219,6 +846,80 @@ impl Class {
 }
 
 impl Method {
+    pub fn new(
+        classname: &str,
+        signature: &str,
+        max_stack: u16,
+        max_locals: u16,
+        bytecode: &[u8],
+    ) -> Self {
+        Self {
+            classname: Arc::from(classname),
+            signature: Arc::from(signature),
+            context: Some(Context {
+                max_stack,
+                max_locals,
+                bytecode: Arc::from(bytecode),
+                exception_handlers: Arc::from([]),
+            }),
+            flags: MethodFlags::empty(),
+            annotations: None,
+        }
+    }
+
+    /// Method declared without a `Code` attribute, i.e. an abstract or a `native` one.
+    pub fn without_code(classname: &str, signature: &str, native: bool) -> Self {
+        Self {
+            classname: Arc::from(classname),
+            signature: Arc::from(signature),
+            context: None,
+            flags: match native {
+                true => MethodFlags::NATIVE,
+                false => MethodFlags::empty(),
+            },
+            annotations: None,
+        }
+    }
+
+    pub fn with_flags(mut self, flags: MethodFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn with_exception_handlers(mut self, handlers: Vec<ExceptionHandler>) -> Self {
+        if let Some(context) = &mut self.context {
+            context.exception_handlers = Arc::from(handlers);
+        }
+        self
+    }
+
+    /// Name of the class declaring the method.
+    pub fn classname(&self) -> &str {
+        &self.classname
+    }
+
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.flags.contains(MethodFlags::NATIVE)
+    }
+
+    /// Whether the method is selected through virtual dispatch, unlike private and static methods,
+    /// constructors and class initialisers, which are always invoked as they're resolved.
+    pub fn is_virtual(&self) -> bool {
+        !self
+            .flags
+            .intersects(MethodFlags::PRIVATE | MethodFlags::STATIC)
+            && !self.signature.starts_with('<')
+    }
+
+    /// Whether the method has no implementation, neither bytecode nor a native one.
+    pub fn is_abstract(&self) -> bool {
+        !self.is_native() && self.context.is_none()
+    }
+
     pub fn new_frame(&self) -> Result<StackFrame> {
         match &self.context {
             Some(ctx) => Ok(StackFrame::new(
@@ -226,7 +927,8 @@ impl Method {
                 ctx.max_stack as usize,
                 Arc::clone(&ctx.bytecode),
                 Arc::clone(&self.classname),
-            )),
+            )
+            .with_exception_handlers(Arc::clone(&ctx.exception_handlers))),
             None => Err(RuntimeError::MissingCodeContext {
                 classname: self.classname.to_string(),
                 signature: self.signature.to_string(),
@@ -236,8 +938,47 @@ impl Method {
     }
 }
 
+impl StandardStream {
+    fn new(sink: Sink) -> Self {
+        Self {
+            print_stream: OnceCell::new(),
+            sink: Mutex::new(sink),
+        }
+    }
+}
+
+impl Debug for ClassSources {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClassSources")
+            .field("provider", &self.provider.read().is_some())
+            .field("modules", &self.modules)
+            .finish()
+    }
+}
+
+impl Debug for StandardStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StandardStream")
+            .field("print_stream", &self.print_stream)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExceptionHandler {
+    /// Whether the instruction at `pc` is protected by this handler.
+    pub fn covers(&self, pc: usize) -> bool {
+        (self.start_pc as usize..self.end_pc as usize).contains(&pc)
+    }
+}
+
 impl FieldValue {
-    pub(super) fn value(&self) -> Result<Vec<i32>> {
+    pub fn new(value: Vec<i32>) -> Self {
+        Self {
+            value: RwLock::new(value),
+        }
+    }
+
+    pub fn value(&self) -> Result<Vec<i32>> {
         let guard = self.value.read();
         Ok(guard.clone())
     }
@@ -247,6 +988,54 @@ impl FieldValue {
         *guard = value;
         Ok(())
     }
+
+    /// Slots of the value, failing unless there are exactly `N` of them. Those of `long` and
+    /// `double` values hold the low half first, as on the operand stack.
+    fn slots<const N: usize>(&self) -> Result<[i32; N]> {
+        let slots = self.value.read();
+        <[i32; N]>::try_from(slots.as_slice()).map_err(|_| {
+            RuntimeError::FieldValueSize {
+                expected: N,
+                found: slots.len(),
+            }
+            .into()
+        })
+    }
+
+    pub fn get_int(&self) -> Result<i32> {
+        let [int] = self.slots()?;
+        Ok(int)
+    }
+
+    pub fn get_long(&self) -> Result<i64> {
+        let [low, high] = self.slots()?;
+        Ok(((high as i64) << 32) | low as u32 as i64)
+    }
+
+    pub fn get_float(&self) -> Result<f32> {
+        let [bits] = self.slots()?;
+        Ok(f32::from_bits(bits as u32))
+    }
+
+    pub fn get_double(&self) -> Result<f64> {
+        Ok(f64::from_bits(self.get_long()? as u64))
+    }
+
+    pub fn set_int(&self, value: i32) -> Result<()> {
+        self.set(vec![value])
+    }
+
+    pub fn set_long(&self, value: i64) -> Result<()> {
+        self.set(vec![value as i32, (value >> 32) as i32])
+    }
+
+    pub fn set_float(&self, value: f32) -> Result<()> {
+        self.set(vec![value.to_bits() as i32])
+    }
+
+    pub fn set_double(&self, value: f64) -> Result<()> {
+        self.set_long(value.to_bits() as i64)
+    }
 }
 
 impl Clone for FieldValue {
@@ -258,6 +1047,42 @@ impl Clone for FieldValue {
     }
 }
 
+/// Name of the elements of the array class `classname`, e.g. `java/lang/String` for
+/// `[Ljava/lang/String;` or `I` for `[I`, or `None` if it isn't an array class.
+pub(in crate::vm) fn component_name(classname: &str) -> Option<&str> {
+    let component = classname.strip_prefix('[')?;
+    let reference = component
+        .strip_prefix('L')
+        .and_then(|name| name.strip_suffix(';'));
+
+    Some(reference.unwrap_or(component))
+}
+
+/// Wrapper class of the primitive type `descriptor`, e.g. `java/lang/Integer` for `I`, or `None`
+/// if it isn't a primitive type.
+pub(in crate::vm) fn box_primitive(descriptor: &str) -> Option<&'static str> {
+    BOXED_TYPE.get(descriptor).copied()
+}
+
+/// Descriptor of the primitive type wrapped by `classname`, e.g. `I` for `java/lang/Integer`, or
+/// `None` if it isn't a wrapper class.
+pub(in crate::vm) fn unbox(classname: &str) -> Option<&'static str> {
+    BOXED_TYPE
+        .iter()
+        .find(|(_, wrapper)| **wrapper == classname)
+        .map(|(descriptor, _)| *descriptor)
+}
+
+/// Methods among `methods` that are dispatched virtually, see [`Method::is_virtual`].
+fn virtual_methods(
+    methods: &IndexMap<String, Arc<Method>>,
+) -> impl Iterator<Item = (String, Arc<Method>)> + '_ {
+    methods
+        .iter()
+        .filter(|(_, method)| method.is_virtual())
+        .map(|(signature, method)| (signature.clone(), Arc::clone(method)))
+}
+
 fn internal_and_external_names(string: &str) -> (String, String) {
     const SYNTH_CLASS_DELIM: &str = "#";
     if let Some(external) = PRIMITIVE_TYPE.get(string) {
@@ -277,3 +1102,290 @@ fn internal_and_external_names(string: &str) -> (String, String) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constructor_overloads() -> Result<()> {
+        let code = [0xB1]; // RETURN
+        let class = Class::with_classname("Overloaded")
+            .with_method(Method::new("Overloaded", "<init>:()V", 0, 1, &code))
+            .with_method(Method::new("Overloaded", "<init>:(I)V", 0, 2, &code))
+            .with_method(Method::new(
+                "Overloaded",
+                "<init>:(Ljava/lang/String;J)V",
+                0,
+                4,
+                &code,
+            ));
+
+        let constructor = class.find_constructor("(Ljava/lang/String;J)")?;
+        assert_eq!(&*constructor.signature, "<init>:(Ljava/lang/String;J)V");
+        assert_eq!(&*class.find_constructor("(I)")?.signature, "<init>:(I)V");
+        assert_eq!(&*class.find_constructor("()")?.signature, "<init>:()V");
+
+        assert!(matches!(
+            class.find_constructor("(J)"),
+            Err(VmError::Runtime(RuntimeError::MethodNotFound(signature)))
+                if signature == "<init>:(J)V"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn class_provider_serves_classes() -> Result<()> {
+        use crate::classfile::{ClassfileBuilder, OwnedCode};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+        let area = MethodArea::new("/does/not/exist")?;
+        area.set_class_provider(Box::new(|classname| {
+            REQUESTS.fetch_add(1, Ordering::Relaxed);
+            let class = ClassfileBuilder::new(classname)
+                .super_class(JAVA_LANG_OBJECT)
+                .field(FieldFlags::STATIC, "count", "J")
+                .method(
+                    MethodFlags::PUBLIC | MethodFlags::STATIC,
+                    "answer",
+                    "()I",
+                    Some(OwnedCode {
+                        max_stack: 1,
+                        max_locals: 0,
+                        bytecode: vec![0x10, 42, 0xAC], // BIPUSH 42, IRETURN
+                    }),
+                );
+
+            match classname {
+                "memory/Synthetic" => Some(class.to_bytes()),
+                "memory/Misnamed" => Some(ClassfileBuilder::new("memory/Other").to_bytes()),
+                _ => None,
+            }
+        }));
+
+        let class = area.get("memory/Synthetic")?;
+        assert_eq!(class.parent(), Some(JAVA_LANG_OBJECT));
+        assert_eq!(class.get_static("count").unwrap().value()?, [0, 0]);
+        assert!(class.get_method("answer:()I").is_ok());
+
+        // the class is only loaded once
+        area.get("memory/Synthetic")?;
+        assert_eq!(REQUESTS.load(Ordering::Relaxed), 1);
+
+        assert!(matches!(
+            area.get("memory/Missing"),
+            Err(VmError::Runtime(RuntimeError::ClassNotFound(name))) if name == "memory/Missing"
+        ));
+        assert!(matches!(
+            area.get("memory/Misnamed"),
+            Err(VmError::Runtime(RuntimeError::WrongClassName { found, .. }))
+                if found == "memory/Other"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn current_thread_id_is_stable() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+
+        let thread = with_method_area(|area| area.current_thread_id())?;
+        assert_eq!(thread, with_method_area(|area| area.current_thread_id())?);
+
+        let group = with_method_area(|area| area.thread_group_id())?;
+        assert_ne!(thread, group);
+        assert_eq!(group, with_method_area(|area| area.thread_group_id())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn assignability_follows_hierarchy() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("Shape")
+                    .with_parent(JAVA_LANG_OBJECT)
+                    .with_interface("Drawable"),
+            );
+            area.insert(Class::with_classname("Drawable").with_interface("Visible"));
+            area.insert(Class::with_classname("Visible"));
+            area.insert(Class::with_classname("Circle").with_parent("Shape"));
+            area.insert(Class::with_classname("Sound").with_parent(JAVA_LANG_OBJECT));
+        });
+
+        let assignable = |from, to| with_method_area(|area| area.is_assignable(from, to));
+        assert!(assignable("Circle", "Shape")?);
+        assert!(assignable("Circle", JAVA_LANG_OBJECT)?);
+        assert!(!assignable("Shape", "Circle")?);
+        assert!(!assignable("Sound", "Shape")?);
+        assert!(assignable("Circle", "Drawable")?);
+        assert!(assignable("Circle", "Visible")?);
+        assert!(!assignable("Sound", "Drawable")?);
+        assert!(!assignable("Drawable", "Shape")?);
+
+        assert!(assignable("[LCircle;", "[LShape;")?);
+        assert!(assignable("[[LCircle;", "[[LShape;")?);
+        assert!(!assignable("[LShape;", "[LCircle;")?);
+        assert!(!assignable("[I", "[J")?);
+        assert!(!assignable("[I", "[LShape;")?);
+        assert!(assignable("[LCircle;", "[LVisible;")?);
+        assert!(assignable("[I", JAVA_LANG_CLONEABLE)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn virtual_dispatch_through_vtable() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+        let method = |classname, signature| Method::new(classname, signature, 1, 1, &[0]);
+        with_method_area(|area| {
+            area.insert(
+                Class::with_classname("VtableAnimal")
+                    .with_method(method("VtableAnimal", "speak:()I"))
+                    .with_method(method("VtableAnimal", "legs:()I"))
+                    .with_method(method("VtableAnimal", "<init>:()V"))
+                    .with_method(
+                        method("VtableAnimal", "secret:()I").with_flags(MethodFlags::PRIVATE),
+                    )
+                    .with_method(
+                        method("VtableAnimal", "create:()I").with_flags(MethodFlags::STATIC),
+                    ),
+            );
+            area.insert(
+                Class::with_classname("VtableDog")
+                    .with_parent("VtableAnimal")
+                    .with_interface("VtablePet")
+                    .with_method(method("VtableDog", "speak:()I")),
+            );
+            area.insert(Class::with_classname("VtablePuppy").with_parent("VtableDog"));
+            area.insert(
+                Class::with_classname("VtablePet")
+                    .with_method(method("VtablePet", "legs:()I"))
+                    .with_method(method("VtablePet", "name:()Ljava/lang/String;")),
+            );
+        });
+
+        let resolve = |classname, signature| {
+            with_method_area(|area| area.resolve_virtual(classname, signature))
+        };
+        let declared = |classname, signature| {
+            with_method_area(|area| area.get(classname)?.get_method(signature))
+        };
+
+        // overrides win over inherited methods, which win over default ones
+        let speak = resolve("VtablePuppy", "speak:()I")?;
+        assert!(Arc::ptr_eq(&speak, &declared("VtableDog", "speak:()I")?));
+        let legs = resolve("VtablePuppy", "legs:()I")?;
+        assert!(Arc::ptr_eq(&legs, &declared("VtableAnimal", "legs:()I")?));
+        let name = resolve("VtablePuppy", "name:()Ljava/lang/String;")?;
+        assert!(Arc::ptr_eq(
+            &name,
+            &declared("VtablePet", "name:()Ljava/lang/String;")?
+        ));
+        assert!(resolve("VtableAnimal", "name:()Ljava/lang/String;").is_err());
+
+        // methods that are never dispatched virtually are left out, but still resolve as is
+        for signature in ["<init>:()V", "secret:()I", "create:()I"] {
+            assert!(resolve("VtableAnimal", signature).is_err());
+            let resolved = with_method_area(|area| area.resolve_method("VtablePuppy", signature))?;
+            assert!(Arc::ptr_eq(
+                &resolved,
+                &declared("VtableAnimal", signature)?
+            ));
+        }
+
+        // the tables are built once, along with the ones of the superclasses, so later calls
+        // don't walk the hierarchy, which would now find no `legs` in `VtableAnimal`
+        for classname in ["VtablePuppy", "VtableDog", "VtableAnimal"] {
+            let class = with_method_area(|area| area.get(classname))?;
+            assert!(class.vtable.get().is_some());
+        }
+        with_method_area(|area| area.insert(Class::with_classname("VtableAnimal")));
+        assert!(Arc::ptr_eq(&resolve("VtablePuppy", "legs:()I")?, &legs));
+
+        Ok(())
+    }
+
+    #[test]
+    fn class_objects_are_reflected() -> Result<()> {
+        let _ = MethodArea::initialise(".");
+
+        let reference = with_method_area(|area| area.class_object("[J"))?;
+        let classname = with_method_area(|area| area.reflected_classname(reference));
+        assert_eq!(classname.as_deref(), Some("[J"));
+
+        let thread = with_method_area(|area| area.current_thread_id())?;
+        assert_eq!(
+            with_method_area(|area| area.reflected_classname(thread)),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn constant_static_fields() -> Result<()> {
+        use crate::vm::runtime::heap::with_heap;
+
+        let bytes = std::fs::read("./tests/sources/Limits.class").unwrap();
+        let class = Class::define(&bytes)?;
+        let value = |name: &str| class.get_static(name).unwrap().value();
+
+        assert_eq!(value("X")?, [42]);
+        assert_eq!(value("MAX")?, [-2, -1]);
+        let bits = 0.5f64.to_bits() as i64;
+        assert_eq!(value("RATIO")?, [bits as i32, (bits >> 32) as i32]);
+        // only set by `<clinit>`, which didn't run
+        assert_eq!(value("counter")?, [0]);
+
+        let name = value("NAME")?[0];
+        assert_eq!(with_heap(|heap| heap.get_string(name))?, "limits");
+
+        Ok(())
+    }
+
+    #[test]
+    fn typed_field_values() -> Result<()> {
+        let field = FieldValue::new(vec![0]);
+        field.set_int(-7)?;
+        assert_eq!(field.get_int()?, -7);
+        field.set_float(2.5)?;
+        assert_eq!(field.get_float()?, 2.5);
+
+        let field = FieldValue::new(vec![0, 0]);
+        field.set_long(i64::MIN + 1)?;
+        assert_eq!(field.get_long()?, i64::MIN + 1);
+        field.set_long(-1)?;
+        assert_eq!(field.value()?, [-1, -1]);
+        field.set_double(-0.1)?;
+        assert_eq!(field.get_double()?, -0.1);
+
+        assert!(matches!(
+            field.get_int(),
+            Err(VmError::Runtime(RuntimeError::FieldValueSize {
+                expected: 1,
+                found: 2
+            }))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn boxing() {
+        assert_eq!(box_primitive("I"), Some("java/lang/Integer"));
+        assert_eq!(box_primitive("Z"), Some("java/lang/Boolean"));
+        assert_eq!(box_primitive("Ljava/lang/String;"), None);
+
+        assert_eq!(unbox("java/lang/Integer"), Some("I"));
+        assert_eq!(unbox("java/lang/Character"), Some("C"));
+        assert_eq!(unbox("java/lang/String"), None);
+
+        for descriptor in PRIMITIVE_TYPE.keys() {
+            assert_eq!(box_primitive(descriptor).and_then(unbox), Some(*descriptor));
+        }
+    }
+}
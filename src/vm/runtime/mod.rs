@@ -2,8 +2,11 @@
 //! resources. It holds the live objects, class metadata, and constant pools needed during execution,
 //! providing the dynamic state that the VM operates on.
 
+use crate::classfile::ClassfileError;
 use thiserror::Error;
+pub(in crate::vm) mod constant_pool;
 pub(in crate::vm) mod heap;
+pub(in crate::vm) mod jimage;
 pub(in crate::vm) mod method_area;
 
 #[derive(Error, Debug)]
@@ -11,6 +14,9 @@ pub enum RuntimeError {
     #[error("METHOD_AREA was already initialised")]
     MethodAreaInitialised,
 
+    #[error("METHOD_AREA was not initialised yet")]
+    MethodAreaUninitialised,
+
     #[error("Method with signature {0} does not exists")]
     MethodNotFound(String),
 
@@ -23,9 +29,33 @@ pub enum RuntimeError {
         signature: String,
     },
 
+    #[error("A field value of {expected} slots was expected, but it has {found}")]
+    FieldValueSize { expected: usize, found: usize },
+
     #[error("Invalid array entry size of: {0}")]
     InvalidArrayEntrySize(usize),
 
     #[error("Attempted to access non-existing entry on array with index: {0}")]
     InvalidArrayAccess(usize),
+
+    #[error("Reference {0} does not point to any heap value")]
+    InvalidReference(i32),
+
+    #[error("Constant pool entry {0} is missing or has an unexpected kind")]
+    InvalidConstant(u16),
+
+    #[error("Bootstrap method {0} is not declared in this class")]
+    BootstrapMethodNotFound(u16),
+
+    #[error(transparent)]
+    JImage(#[from] jimage::JImageError),
+
+    #[error(transparent)]
+    ClassFormat(#[from] ClassfileError),
+
+    #[error("Class {0} could not be found")]
+    ClassNotFound(String),
+
+    #[error("Expected the class file of {expected}, but it defines {found}")]
+    WrongClassName { expected: String, found: String },
 }
@@ -1,4 +1,8 @@
-use ignis::classfile::{Classfile, ClassfileError, FieldFlags, MethodFlags};
+use ignis::classfile::{
+    AccessFlags, AttributeKind, Classfile, ClassfileBuilder, ClassfileError, FieldFlags,
+    InnerClassFlags, MemberRef, MethodFlags, MethodSummary, OwnedCode, ParseOptions,
+    PublicConstant, RecordComponent, binary_name, classfile_version, parse_many, source_type_name,
+};
 use std::fs::{self};
 
 type Result<T> = std::result::Result<T, ClassfileError>;
@@ -94,3 +98,848 @@ fn enum_class() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn method_exceptions() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Resource.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    // `open` is declared as `throws IOException`
+    let exceptions = classfile.method_exceptions("open", "()V", &arena)?;
+    assert_eq!(exceptions, bumpalo::vec![in &arena; "java/io/IOException"]);
+
+    // whereas `close` has no `Exceptions` attribute at all
+    let exceptions = classfile.method_exceptions("close", "()V", &arena)?;
+    assert!(exceptions.is_empty());
+
+    assert!(matches!(
+        classfile.method_exceptions("missing", "()V", &arena),
+        Err(ClassfileError::MethodNotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn binary_names() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let buffer = fs::read("./tests/sources/Employee.class")?;
+    let classfile = Classfile::new(&buffer, &arena)?;
+
+    assert_eq!(classfile.class_name(), Some("example/Employee"));
+    assert_eq!(
+        classfile.class_name_binary().as_deref(),
+        Some("example.Employee")
+    );
+    assert_eq!(binary_name("java/util/Map$Entry"), "java.util.Map$Entry");
+
+    assert_eq!(source_type_name("I").as_deref(), Some("int"));
+    assert_eq!(source_type_name("V").as_deref(), Some("void"));
+    assert_eq!(source_type_name("[[J").as_deref(), Some("long[][]"));
+    assert_eq!(
+        source_type_name("[Ljava/lang/String;").as_deref(),
+        Some("java.lang.String[]")
+    );
+    assert_eq!(source_type_name("Ljava/lang/String").as_deref(), None);
+    assert_eq!(source_type_name("[V").as_deref(), None);
+    assert_eq!(source_type_name("II").as_deref(), None);
+
+    Ok(())
+}
+
+#[test]
+fn version_only() -> Result<()> {
+    let bytes = fs::read("./tests/sources/Employee.class")?;
+
+    let version = classfile_version(&bytes)?;
+    assert_eq!((version.major(), version.minor()), (68, 0));
+    // the header alone is enough
+    assert_eq!(classfile_version(&bytes[..8])?, version);
+
+    assert!(matches!(
+        classfile_version(&bytes[1..]),
+        Err(ClassfileError::InvalidClassfile)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn parse_many_in_parallel() {
+    let paths = [
+        "./tests/sources/Person.class",
+        "./tests/sources/Employee.class",
+        "./tests/sources/Missing.class",
+    ];
+    let parsed = parse_many(&paths);
+
+    assert_eq!(parsed.len(), 3);
+    assert_eq!(parsed[0].as_ref().unwrap().class_name(), "Person");
+    assert_eq!(parsed[1].as_ref().unwrap().class_name(), "example/Employee");
+    assert!(matches!(parsed[2], Err(ClassfileError::Io(_))));
+}
+
+#[test]
+fn parse_many_survives_a_panicking_parse() {
+    // a class attribute named by the class entry rather than a Utf8 one, which the parser panics on
+    let mut bytes = ClassfileBuilder::new("Broken").to_bytes();
+    bytes.truncate(bytes.len() - 2);
+    bytes.extend([0, 1, 0, 2, 0, 0, 0, 0]);
+
+    let path = std::env::temp_dir().join("ignis_parse_many_Broken.class");
+    fs::write(&path, bytes).unwrap();
+
+    let paths = [
+        path.clone(),
+        "./tests/sources/Person.class".into(),
+        path.clone(),
+    ];
+    let parsed = parse_many(&paths);
+
+    assert_eq!(parsed.len(), 3);
+    assert!(matches!(parsed[0], Err(ClassfileError::Panicked(_))));
+    assert_eq!(parsed[1].as_ref().unwrap().class_name(), "Person");
+    assert!(matches!(parsed[2], Err(ClassfileError::Panicked(_))));
+}
+
+#[test]
+fn methods_by_name() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let code = OwnedCode {
+        max_stack: 0,
+        max_locals: 3,
+        bytecode: vec![0xB1], // RETURN
+    };
+    let bytes = ClassfileBuilder::new("Pair")
+        .super_class("java/lang/Object")
+        .method(MethodFlags::PUBLIC, "<init>", "()V", Some(code.clone()))
+        .method(MethodFlags::PUBLIC, "first", "()I", Some(code.clone()))
+        .method(MethodFlags::PUBLIC, "<init>", "(II)V", Some(code))
+        .to_bytes();
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    assert_eq!(
+        classfile.methods_by_name("<init>", &arena)?,
+        [("<init>", "()V"), ("<init>", "(II)V")]
+    );
+    assert_eq!(
+        classfile.methods_by_name("first", &arena)?,
+        [("first", "()I")]
+    );
+    assert!(classfile.methods_by_name("second", &arena)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn all_method_code() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let code = classfile.all_method_code(&arena)?;
+    let concrete = classfile
+        .methods
+        .iter()
+        .filter(|m| !m.is_abstract())
+        .count();
+    assert_eq!(code.len(), concrete);
+    assert!(code.iter().all(|(_, _, bytecode)| !bytecode.is_empty()));
+    assert_eq!(code[0].0, "<init>");
+
+    // the only method of an interface is abstract
+    let bytes = fs::read("./tests/sources/Shape.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(classfile.methods.len(), 1);
+    assert!(classfile.all_method_code(&arena)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn bridge_method_summary() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Version.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    // `Comparable<Version>` makes the compiler generate `compareTo(Object)` forwarding to the
+    // declared `compareTo(Version)`
+    let bridge = classfile
+        .method_summary("compareTo", "(Ljava/lang/Object;)I")
+        .unwrap();
+    assert!(bridge.is_bridge && bridge.is_synthetic);
+    assert!(bridge.is_public && bridge.has_code);
+
+    let declared = classfile.method_summary("compareTo", "(LVersion;)I");
+    assert_eq!(
+        declared,
+        Some(MethodSummary {
+            descriptor: "(LVersion;)I",
+            is_public: true,
+            is_private: false,
+            is_static: false,
+            is_final: false,
+            is_abstract: false,
+            is_native: false,
+            is_synchronized: false,
+            is_synthetic: false,
+            is_bridge: false,
+            is_varargs: false,
+            has_code: true,
+        })
+    );
+    assert_eq!(classfile.method_summary("compareTo", "()I"), None);
+
+    Ok(())
+}
+
+#[test]
+fn trailing_bytes() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let mut bytes = fs::read("./tests/sources/Person.class")?;
+    bytes.extend([0xDE, 0xAD, 0xBE]);
+
+    let lenient = Classfile::new(&bytes, &arena)?;
+    assert_eq!(lenient.source_file(), Some("Person.java"));
+
+    let options = ParseOptions {
+        strict_eof: true,
+        ..Default::default()
+    };
+    let strict = Classfile::with_options(&bytes, &arena, options);
+    assert!(matches!(strict, Err(ClassfileError::TrailingBytes(3))));
+
+    Ok(())
+}
+
+#[test]
+fn skip_attribute_bodies() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let parsed = Classfile::new(&bytes, &arena)?;
+    let options = ParseOptions {
+        skip_attribute_bodies: true,
+        ..Default::default()
+    };
+    let skipped = Classfile::with_options(&bytes, &arena, options)?;
+
+    assert_eq!(
+        skipped.methods_signatures(&arena)?,
+        parsed.methods_signatures(&arena)?
+    );
+    assert_eq!(skipped.field_names(&arena)?, parsed.field_names(&arena)?);
+    assert_eq!(
+        skipped.interface_names(&arena)?,
+        parsed.interface_names(&arena)?
+    );
+
+    // the contents of the attributes are gone
+    assert_eq!(parsed.source_file(), Some("Person.java"));
+    assert_eq!(skipped.source_file(), None);
+    let owned = skipped.to_owned()?;
+    assert!(owned.methods.iter().all(|method| method.code.is_none()));
+
+    Ok(())
+}
+
+#[test]
+fn interface_indices() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let buffer = fs::read("./tests/sources/Employee.class")?;
+    let classfile = Classfile::new(&buffer, &arena)?;
+
+    assert_eq!(classfile.interface_count(), 1);
+    let indices: Vec<u16> = classfile.interface_indices().collect();
+    assert_eq!(indices.len(), 1);
+    assert_eq!(
+        classfile.constant(indices[0]),
+        Some(PublicConstant::Class("java/io/Serializable"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn find_method() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let buffer = fs::read("./tests/sources/Employee.class")?;
+    let classfile = Classfile::new(&buffer, &arena)?;
+
+    assert!(classfile.has_method("getSalary", "()D"));
+    let method = classfile.find_method("getSalary", "()D").unwrap();
+    assert!(method.contains(&[MethodFlags::ABSTRACT, MethodFlags::PUBLIC]));
+
+    // the name alone is not enough, the descriptor must match as well
+    assert!(!classfile.has_method("getSalary", "()I"));
+    assert!(classfile.find_method("getBonus", "()D").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn permitted_subclasses() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Shape.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    // `sealed interface Shape permits Circle, Square`
+    let permitted = classfile.permitted_subclasses(&arena)?;
+    assert_eq!(permitted, bumpalo::vec![in &arena; "Circle", "Square"]);
+
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert!(classfile.permitted_subclasses(&arena)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn owned_classfile() -> Result<()> {
+    let bytes = fs::read("./tests/sources/Employee.class")?;
+    let owned = {
+        let arena = bumpalo::Bump::new();
+        let classfile = Classfile::new(&bytes, &arena)?;
+        classfile.to_owned()?
+    }; // the arena is dropped here
+
+    assert_eq!(owned.class_name(), "example/Employee");
+    assert_eq!(owned.super_class(), Some("java/lang/Object"));
+    assert_eq!(owned.interface_names(), ["java/io/Serializable"]);
+    assert_eq!(owned.methods.len(), 4);
+
+    let salary = owned.find_method("getSalary", "()D").unwrap();
+    assert!(salary.flags.contains(MethodFlags::ABSTRACT));
+    assert!(salary.code.is_none());
+
+    let constructor = owned
+        .find_method("<init>", "(Ljava/lang/String;I)V")
+        .unwrap();
+    assert!(!constructor.code.as_ref().unwrap().bytecode.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn inner_classes() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Outer.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let inner_classes = classfile.inner_classes(&arena)?;
+    assert_eq!(inner_classes.len(), 2);
+
+    let nested = inner_classes
+        .iter()
+        .find(|class| class.class_name == "Outer$Nested")
+        .unwrap();
+    assert_eq!(nested.name, Some("Nested"));
+    assert_eq!(nested.outer_class, Some("Outer"));
+    assert!(nested.flags.contains(InnerClassFlags::STATIC));
+
+    // the anonymous `Runnable` has neither a simple name nor an outer class
+    let anonymous = inner_classes
+        .iter()
+        .find(|class| class.class_name == "Outer$1")
+        .unwrap();
+    assert_eq!(anonymous.name, None);
+    assert_eq!(anonymous.outer_class, None);
+
+    // an anonymous class lists itself, with both zero indexes resolving to `None`
+    let bytes = fs::read("./tests/sources/Greeter$1.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    let inner_classes = classfile.inner_classes(&arena)?;
+    let itself = inner_classes
+        .iter()
+        .find(|class| class.class_name == "Greeter$1")
+        .unwrap();
+    assert_eq!((itself.name, itself.outer_class), (None, None));
+
+    Ok(())
+}
+
+#[test]
+fn constant_pool_view() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let len = classfile.constant_pool_len() as u16;
+    let constants: Vec<_> = (1..=len)
+        .filter_map(|idx| classfile.constant(idx))
+        .collect();
+
+    assert!(
+        constants
+            .iter()
+            .any(|c| matches!(c, PublicConstant::Utf8(_)))
+    );
+    assert!(constants.contains(&PublicConstant::MethodRef(MemberRef {
+        class: "java/lang/Object",
+        name: "<init>",
+        descriptor: "()V",
+    })));
+    assert!(constants.contains(&PublicConstant::Class("Person")));
+
+    assert_eq!(classfile.constant(0), None);
+    assert_eq!(classfile.constant(len + 1), None);
+
+    Ok(())
+}
+
+#[test]
+fn describe_ref() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    assert_eq!(
+        classfile.describe_ref(1).as_deref(),
+        Some("java/lang/Object.<init>:()V")
+    );
+    assert_eq!(
+        classfile.describe_ref(7).as_deref(),
+        Some("Person.name:Ljava/lang/String;")
+    );
+    // a class entry is no member reference
+    assert_eq!(classfile.describe_ref(8), None);
+    assert_eq!(classfile.describe_ref(0), None);
+
+    Ok(())
+}
+
+#[test]
+fn local_variable_table() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let constructor = "(Ljava/lang/String;I)V";
+    assert_eq!(
+        classfile.local_variable("<init>", constructor, 1, 1),
+        Some(("name", "Ljava/lang/String;"))
+    );
+    assert_eq!(
+        classfile.local_variable("<init>", constructor, 4, 2),
+        Some(("age", "I"))
+    );
+    assert_eq!(
+        classfile.local_variable("getName", "()Ljava/lang/String;", 0, 0),
+        Some(("this", "LPerson;"))
+    );
+
+    // past the end of the range, in an unused slot and in a missing method
+    assert_eq!(classfile.local_variable("<init>", constructor, 5, 1), None);
+    assert_eq!(classfile.local_variable("<init>", constructor, 0, 3), None);
+    assert_eq!(classfile.local_variable("missing", "()V", 0, 0), None);
+
+    Ok(())
+}
+
+#[test]
+fn supplementary_characters() -> Result<()> {
+    // the literal is encoded in the class file as CESU-8, each emoji as a six-byte surrogate pair
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Emoji.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let len = classfile.constant_pool_len() as u16;
+    let constants: Vec<_> = (1..=len)
+        .filter_map(|idx| classfile.constant(idx))
+        .collect();
+
+    assert!(constants.contains(&PublicConstant::String("hi 👋 from 🦀")));
+    assert!(constants.contains(&PublicConstant::Utf8("hi 👋 from 🦀")));
+    assert!(!constants.iter().any(|constant| matches!(
+        constant,
+        PublicConstant::Utf8(string) if string.contains(char::REPLACEMENT_CHARACTER)
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn display_classfile() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let output = classfile.to_string();
+    assert!(output.starts_with("// class version 68.0\n"));
+    assert!(output.contains(
+        "public class Person extends java.lang.Object implements java.io.Serializable {"
+    ));
+    assert!(output.contains("  private name: Ljava/lang/String;"));
+    assert!(output.contains("  public <init>(Ljava/lang/String;I)V"));
+    assert!(output.contains("  public getName()Ljava/lang/String;"));
+
+    let bytes = fs::read("./tests/sources/Shape.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    // interfaces are implicitly abstract
+    assert!(classfile.to_string().contains("interface Shape extends"));
+    assert!(!classfile.to_string().contains("abstract interface"));
+
+    Ok(())
+}
+
+#[test]
+fn enclosing_method() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+
+    // `class Local` declared inside `Greeter::greet`
+    let bytes = fs::read("./tests/sources/Greeter$1Local.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    let (class, method) = classfile.enclosing_method().unwrap();
+    assert_eq!(class, "Greeter");
+    assert_eq!(
+        method,
+        Some((
+            "greet".to_string(),
+            "(Ljava/lang/String;)Ljava/lang/Runnable;".to_string()
+        ))
+    );
+
+    // an anonymous class in a field initializer belongs to the class, not to a method
+    let bytes = fs::read("./tests/sources/Greeter$1.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(
+        classfile.enclosing_method(),
+        Some(("Greeter".to_string(), None))
+    );
+
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(classfile.enclosing_method(), None);
+
+    Ok(())
+}
+
+#[test]
+fn source_file() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(classfile.source_file(), Some("Person.java"));
+
+    // nested classes report the file of their top-level class
+    let bytes = fs::read("./tests/sources/Greeter$1Local.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(classfile.source_file(), Some("Greeter.java"));
+
+    Ok(())
+}
+
+#[test]
+fn nest_mates() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Outer.class")?;
+    let outer = Classfile::new(&bytes, &arena)?;
+
+    // a top-level class hosts its own nest
+    assert_eq!(outer.nest_host(), Some("Outer"));
+    let members = outer.nest_members(&arena)?;
+    assert_eq!(members.len(), 2);
+    assert!(members.contains(&"Outer$Nested"));
+    assert!(members.contains(&"Outer$1"));
+
+    let bytes = fs::read("./tests/sources/Greeter$1Local.class")?;
+    let local = Classfile::new(&bytes, &arena)?;
+    assert_eq!(local.nest_host(), Some("Greeter"));
+    assert!(local.nest_members(&arena)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn record_components() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Point.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    // `record Point(int x, int y)`
+    let components = classfile.record_components(&arena)?;
+    assert_eq!(
+        components,
+        bumpalo::vec![in &arena;
+            RecordComponent { name: "x", descriptor: "I", signature: None },
+            RecordComponent { name: "y", descriptor: "I", signature: None },
+        ]
+    );
+
+    // `record Named<T>(String name, T value)`
+    let bytes = fs::read("./tests/sources/Named.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    let components = classfile.record_components(&arena)?;
+    assert_eq!(components[0].signature, None);
+    assert_eq!(components[1].descriptor, "Ljava/lang/Object;");
+    assert_eq!(components[1].signature, Some("TT;"));
+
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert!(classfile.record_components(&arena)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn method_flags() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Natives.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let nano_time = classfile.find_method("nanoTime", "()J").unwrap();
+    assert!(nano_time.is_native() && nano_time.is_static() && nano_time.is_public());
+    assert!(!nano_time.is_synchronized());
+
+    let lock = classfile.find_method("lock", "()V").unwrap();
+    assert!(lock.is_synchronized());
+    assert!(!lock.is_native() && !lock.is_static());
+
+    let log = classfile
+        .find_method("log", "([Ljava/lang/String;)V")
+        .unwrap();
+    assert!(log.is_varargs());
+    assert!(!log.is_bridge() && !log.is_synthetic());
+
+    Ok(())
+}
+
+#[test]
+fn generic_signatures() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Registry.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    assert_eq!(
+        classfile.generic_signature(),
+        Some("<T:Ljava/lang/Object;>Ljava/lang/Object;")
+    );
+    assert_eq!(
+        classfile.field_signature("names"),
+        Some("Ljava/util/List<Ljava/lang/String;>;")
+    );
+    assert_eq!(classfile.field_signature("size"), None);
+
+    // `<K extends Comparable<K>> K max(List<K> items)`, erased to its bounds in the descriptor
+    let descriptor = "(Ljava/util/List;)Ljava/lang/Comparable;";
+    assert!(classfile.has_method("max", descriptor));
+    assert_eq!(
+        classfile.method_signature("max", descriptor),
+        Some("<K::Ljava/lang/Comparable<TK;>;>(Ljava/util/List<TK;>;)TK;")
+    );
+    assert_eq!(classfile.method_signature("count", "()I"), None);
+
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(classfile.generic_signature(), None);
+
+    Ok(())
+}
+
+/// Builds a class named `Foo` whose only class attribute is `name`, claiming to be `length`
+/// bytes long while carrying just `content`.
+fn class_with_attribute(name: &str, length: u32, content: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 61];
+    bytes.extend([0, 4]); // constant pool count
+    bytes.extend([1, 0, 3]);
+    bytes.extend(b"Foo");
+    bytes.extend([7, 0, 1]);
+    bytes.extend([1, 0, name.len() as u8]);
+    bytes.extend(name.as_bytes());
+
+    bytes.extend([0x00, 0x21, 0, 2, 0, 0]); // access flags, this and super class
+    bytes.extend([0, 0, 0, 0, 0, 0]); // interfaces, fields and methods
+    bytes.extend([0, 1, 0, 3]);
+    bytes.extend(length.to_be_bytes());
+    bytes.extend(content);
+
+    bytes
+}
+
+#[test]
+fn oversized_attribute_length() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+
+    let bytes = class_with_attribute("RuntimeVisibleAnnotations", 0xFFFF_FFF0, &[0, 0]);
+    let Err(ClassfileError::Malformed { source, .. }) = Classfile::new(&bytes, &arena) else {
+        panic!("an oversized attribute must be rejected");
+    };
+    assert!(matches!(
+        *source,
+        ClassfileError::TruncatedAttribute {
+            length: 0xFFFF_FFF0,
+            ..
+        }
+    ));
+
+    // a well-formed length is still accepted
+    let bytes = class_with_attribute("RuntimeVisibleAnnotations", 2, &[0, 0]);
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(classfile.class_name(), Some("Foo"));
+
+    Ok(())
+}
+
+#[test]
+fn dependencies() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Employee.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+
+    let dependencies = classfile.dependencies(&arena)?;
+    assert!(dependencies.contains(&"java/lang/Object"));
+    assert!(dependencies.contains(&"java/io/Serializable"));
+    assert!(!dependencies.contains(&"example/Employee"));
+
+    // `String[]` is a dependency on `String`, and `Ledger[][]` one on the class itself
+    let bytes = fs::read("./tests/sources/Ledger.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert_eq!(
+        classfile.dependencies(&arena)?,
+        bumpalo::vec![in &arena;
+            "java/lang/Object",
+            "java/util/ArrayList",
+            "java/lang/String",
+            "java/util/List",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn build_classfile() -> Result<()> {
+    // return 42
+    let code = OwnedCode {
+        max_stack: 1,
+        max_locals: 0,
+        bytecode: vec![0x10, 42, 0xAC],
+    };
+    let bytes = ClassfileBuilder::new("Foo")
+        .super_class("java/lang/Object")
+        .interface("java/io/Serializable")
+        .field(FieldFlags::PRIVATE, "width", "I")
+        .field(FieldFlags::PRIVATE, "height", "I")
+        .method(
+            MethodFlags::PUBLIC | MethodFlags::STATIC,
+            "answer",
+            "()I",
+            Some(code.clone()),
+        )
+        .method(
+            MethodFlags::PUBLIC | MethodFlags::ABSTRACT,
+            "area",
+            "()I",
+            None,
+        )
+        .access_flags(AccessFlags::PUBLIC | AccessFlags::ABSTRACT)
+        .to_bytes();
+
+    let arena = bumpalo::Bump::new();
+    let classfile = Classfile::new(&bytes, &arena)?;
+    assert!(classfile.is_public() && classfile.is_abstract());
+    assert_eq!(classfile.class_name(), Some("Foo"));
+    assert_eq!(classfile.super_class(), Some("java/lang/Object"));
+    assert_eq!(classfile.version(), (61, 0));
+
+    let fields = classfile.field_names(&arena)?;
+    assert_eq!(fields, bumpalo::vec![in &arena; "width", "height"]);
+    assert!(classfile.has_method("area", "()I"));
+
+    let owned = classfile.to_owned()?;
+    assert_eq!(owned.interface_names(), ["java/io/Serializable"]);
+    assert_eq!(owned.find_method("answer", "()I").unwrap().code, Some(code));
+    assert_eq!(owned.find_method("area", "()I").unwrap().code, None);
+
+    // the shared `I` and `()I` descriptors are interned once
+    let len = classfile.constant_pool_len() as u16;
+    let constants: Vec<_> = (1..=len)
+        .filter_map(|idx| classfile.constant(idx))
+        .collect();
+    for (idx, constant) in constants.iter().enumerate() {
+        assert!(!constants[idx + 1..].contains(constant), "{constant:?}");
+    }
+    assert!(constants.contains(&PublicConstant::Utf8("I")));
+
+    Ok(())
+}
+
+#[test]
+fn malformed_offset() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+
+    // the constant pool starts right after the 10 bytes of magic, version and pool count, and
+    // spans several hundred bytes in `Person.class`
+    let pool_start = 10;
+    let truncated = &bytes[..pool_start + 40];
+
+    let error = Classfile::new(truncated, &arena).unwrap_err();
+    let offset = error.offset().unwrap();
+    assert!((pool_start..=truncated.len()).contains(&offset), "{offset}");
+    assert!(matches!(
+        error,
+        ClassfileError::Malformed { ref source, .. } if matches!(**source, ClassfileError::Io(_))
+    ));
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = 0;
+    let error = Classfile::new(&bad_magic, &arena).unwrap_err();
+    assert_eq!(error.offset(), Some(4));
+
+    Ok(())
+}
+
+#[test]
+fn class_flags_string() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let flags = |name: &str| -> Result<String> {
+        let bytes = fs::read(format!("./tests/sources/{name}.class"))?;
+        Ok(Classfile::new(&bytes, &arena)?.class_flags_string())
+    };
+
+    // interfaces are implicitly abstract, enums implicitly final, and `ACC_SUPER` is never shown
+    assert_eq!(flags("Shape")?, "public interface");
+    assert_eq!(flags("TaskStatus")?, "public enum");
+    assert_eq!(flags("Named")?, "public final class");
+    assert_eq!(flags("Person")?, "public class");
+
+    Ok(())
+}
+
+#[test]
+fn attribute_kinds() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let parsed = Classfile::new(&bytes, &arena)?;
+
+    let kinds: Vec<_> = parsed.attributes().collect();
+    assert!(kinds.contains(&AttributeKind::SourceFile));
+    assert!(!kinds.contains(&AttributeKind::Code));
+    assert_eq!(AttributeKind::SourceFile.name(), "SourceFile");
+
+    // skipped attributes are still known by name
+    let options = ParseOptions {
+        skip_attribute_bodies: true,
+        ..Default::default()
+    };
+    let skipped = Classfile::with_options(&bytes, &arena, options)?;
+    assert_eq!(skipped.attributes().collect::<Vec<_>>(), kinds);
+
+    Ok(())
+}
+
+#[test]
+fn unparsed_attributes_are_skipped() -> Result<()> {
+    // `class Ann extends @Tagged Object`, `Tagged` being a runtime-retained type annotation
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Ann.class")?;
+    let parsed = Classfile::new(&bytes, &arena)?;
+
+    let kinds: Vec<_> = parsed.attributes().collect();
+    assert!(kinds.contains(&AttributeKind::RuntimeVisibleTypeAnnotations));
+    assert!(kinds.contains(&AttributeKind::SourceFile));
+    assert_eq!(parsed.class_name(), Some("Ann"));
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use ignis::{
+    classfile::{Classfile, ClassfileError},
+    vm::disassembler::{Opcode, analyze_opcodes},
+};
+use std::{collections::HashMap, fs};
+
+type Result<T> = std::result::Result<T, ClassfileError>;
+
+#[test]
+fn person_opcodes() -> Result<()> {
+    let arena = bumpalo::Bump::new();
+    let bytes = fs::read("./tests/sources/Person.class")?;
+    let classfile = Classfile::new(&bytes, &arena)?;
+    let code = classfile.all_method_code(&arena)?;
+
+    // the constructor only calls the one of java/lang/Object, whose constant pool index, 1, is
+    // an operand rather than an ACONST_NULL
+    let (_, _, constructor) = code.iter().find(|(name, ..)| *name == "<init>").unwrap();
+    assert_eq!(
+        analyze_opcodes(constructor),
+        HashMap::from([
+            (Opcode::ALOAD_0, 1),
+            (Opcode::INVOKESPECIAL, 1),
+            (Opcode::RETURN, 1),
+        ])
+    );
+
+    let (_, _, get_name) = code.iter().find(|(name, ..)| *name == "getName").unwrap();
+    assert_eq!(
+        analyze_opcodes(get_name),
+        HashMap::from([
+            (Opcode::ALOAD_0, 1),
+            (Opcode::GETFIELD, 1),
+            (Opcode::ARETURN, 1),
+        ])
+    );
+
+    Ok(())
+}
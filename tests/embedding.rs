@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use ignis::{
+    classfile::{ClassfileBuilder, MethodFlags, OwnedCode},
+    vm::{self, Args, Value, VmError},
+};
+
+type Result<T> = std::result::Result<T, VmError>;
+
+/// Serves a bare `java/lang/Object` and a class with a static `add:(II)I`, so no JDK is needed.
+fn provider(classname: &str) -> Option<Vec<u8>> {
+    let class = match classname {
+        "java/lang/Object" => ClassfileBuilder::new(classname),
+        "embedded/Math" => ClassfileBuilder::new(classname)
+            .super_class("java/lang/Object")
+            .method(
+                MethodFlags::PUBLIC | MethodFlags::STATIC,
+                "add",
+                "(II)I",
+                Some(OwnedCode {
+                    max_stack: 2,
+                    max_locals: 2,
+                    bytecode: vec![0x1A, 0x1B, 0x60, 0xAC], // ILOAD_0, ILOAD_1, IADD, IRETURN
+                }),
+            ),
+        _ => return None,
+    };
+
+    Some(class.to_bytes())
+}
+
+#[test]
+fn invoke_after_initialise() -> Result<()> {
+    assert!(
+        vm::invoke(
+            "embedded/Math",
+            "add",
+            "(II)I",
+            &[Value::Int(1), Value::Int(2)]
+        )
+        .is_err()
+    );
+
+    let args = Args {
+        class_provider: Some(Box::new(provider)),
+        ..Default::default()
+    };
+    vm::initialise(args, Path::new("/does/not/exist"))?;
+
+    let sum = vm::invoke(
+        "embedded/Math",
+        "add",
+        "(II)I",
+        &[Value::Int(1), Value::Int(2)],
+    )?;
+    assert_eq!(sum, Some(Value::Int(3)));
+
+    // the VM is set up once per process
+    assert!(vm::initialise(Args::default(), Path::new("/does/not/exist")).is_err());
+
+    Ok(())
+}
@@ -7,7 +7,10 @@ type Result<T> = std::result::Result<T, VmError>;
 #[test]
 fn initialise_vm() -> Result<()> {
     let class = Path::new("./sources/Sum.class");
-    let args = Args { entry: "Main" };
+    let args = Args {
+        entry: "Main",
+        ..Default::default()
+    };
 
     let result = vm::run(args, class);
     assert!(result.is_ok());